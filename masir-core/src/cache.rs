@@ -0,0 +1,246 @@
+//! A cache of window properties which are expensive to query on every mouse
+//! movement
+
+use crate::hwnd::Hwnd;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+static CLEAR_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks the event loop to clear the caches of the running engine, for when
+/// cached properties have gone stale
+pub fn request_clear() {
+    CLEAR_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Returns true if clearing the caches has been requested since this was
+/// last called
+pub fn take_clear_requested() -> bool {
+    CLEAR_REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+/// Everything known about a single window, populated lazily
+#[derive(Debug, Clone)]
+pub struct WindowInfo {
+    /// The top-level window which this window belongs to
+    pub root: Option<Hwnd>,
+    pub class: Option<Arc<str>>,
+    /// Whether this window is eligible to be raised and to lose focus
+    pub eligible: Option<bool>,
+    /// When set, the cached eligibility is only valid until this time
+    pub eligible_until: Option<Instant>,
+    /// A window known to belong to the same application as this one
+    pub pair: Option<Hwnd>,
+    /// Whether this window is visible, not minimized and on screen, along
+    /// with when this stops being valid
+    pub visible: Option<(bool, Instant)>,
+    pub last_seen: Instant,
+}
+
+impl WindowInfo {
+    fn new() -> Self {
+        Self {
+            root: None,
+            class: None,
+            eligible: None,
+            eligible_until: None,
+            pair: None,
+            visible: None,
+            last_seen: Instant::now(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Caches {
+    windows: HashMap<Hwnd, WindowInfo>,
+    instantiation_time: Instant,
+}
+
+impl Default for Caches {
+    fn default() -> Self {
+        Self {
+            windows: HashMap::new(),
+            instantiation_time: Instant::now(),
+        }
+    }
+}
+
+impl Caches {
+    pub const MAX_AGE: Duration = Duration::from_secs(60 * 10); // 10 minutes
+
+    pub fn get(&self, hwnd: Hwnd) -> Option<&WindowInfo> {
+        self.windows.get(&hwnd)
+    }
+
+    /// Returns the cached information for a window, inserting an empty entry
+    /// if the window has not been seen before
+    pub fn entry(&mut self, hwnd: Hwnd) -> &mut WindowInfo {
+        let info = self.windows.entry(hwnd).or_insert_with(WindowInfo::new);
+        info.last_seen = Instant::now();
+        info
+    }
+
+    /// Removes everything known about a window
+    pub fn invalidate(&mut self, hwnd: Hwnd) {
+        self.windows.remove(&hwnd);
+    }
+
+    /// Removes everything known about the windows for which `keep` returns
+    /// false
+    pub fn retain(&mut self, mut keep: impl FnMut(Hwnd, &WindowInfo) -> bool) {
+        self.windows.retain(|hwnd, info| keep(*hwnd, info));
+    }
+
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// Clears the cache, retaining its allocated capacity
+    pub fn clear(&mut self) {
+        self.windows.clear();
+        self.instantiation_time = Instant::now();
+    }
+
+    /// Clears the cache if it is older than the given age, returning true if
+    /// it was cleared
+    pub fn clear_if_older_than(&mut self, max_age: Duration) -> bool {
+        if self.instantiation_time.elapsed() > max_age {
+            self.clear();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the cached eligibility of a window, unless it has expired
+    pub fn eligibility(&self, hwnd: Hwnd) -> Option<bool> {
+        let info = self.get(hwnd)?;
+
+        match info.eligible_until {
+            Some(until) if Instant::now() >= until => None,
+            _ => info.eligible,
+        }
+    }
+
+    /// Caches the eligibility of a window, optionally only for the given duration
+    pub fn set_eligibility(&mut self, hwnd: Hwnd, eligible: bool, ttl: Option<Duration>) {
+        let info = self.entry(hwnd);
+        info.eligible = Some(eligible);
+        info.eligible_until = ttl.map(|ttl| Instant::now() + ttl);
+    }
+
+    /// Returns the cached visibility of a window, unless it has expired
+    pub fn visibility(&self, hwnd: Hwnd) -> Option<bool> {
+        match self.get(hwnd)?.visible {
+            Some((visible, until)) if Instant::now() < until => Some(visible),
+            _ => None,
+        }
+    }
+
+    /// Forgets the cached visibility of every window, such as after the
+    /// monitors have changed
+    pub fn clear_visibility(&mut self) {
+        for info in self.windows.values_mut() {
+            info.visible = None;
+        }
+    }
+
+    /// Caches the visibility of a window for the given duration
+    pub fn set_visibility(&mut self, hwnd: Hwnd, visible: bool, ttl: Duration) {
+        self.entry(hwnd).visible = Some((visible, Instant::now() + ttl));
+    }
+
+    /// Returns true if both windows are known to be eligible
+    pub fn are_eligible(&self, first: Hwnd, second: Hwnd) -> Option<bool> {
+        let first = self.eligibility(first);
+        let second = self.eligibility(second);
+
+        match (first, second) {
+            (Some(first), Some(second)) => Some(first && second),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the two windows are known to refer to the same application
+    pub fn is_paired(&self, first: Hwnd, second: Hwnd) -> bool {
+        self.get(first).and_then(|info| info.pair) == Some(second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eligibility_requires_both_windows() {
+        let mut caches = Caches::default();
+        assert_eq!(caches.are_eligible(Hwnd(1), Hwnd(2)), None);
+
+        caches.entry(Hwnd(1)).eligible = Some(true);
+        assert_eq!(caches.are_eligible(Hwnd(1), Hwnd(2)), None);
+
+        caches.entry(Hwnd(2)).eligible = Some(true);
+        assert_eq!(caches.are_eligible(Hwnd(1), Hwnd(2)), Some(true));
+
+        caches.entry(Hwnd(2)).eligible = Some(false);
+        assert_eq!(caches.are_eligible(Hwnd(1), Hwnd(2)), Some(false));
+    }
+
+    #[test]
+    fn eligibility_expires_after_ttl() {
+        let mut caches = Caches::default();
+        caches.set_eligibility(Hwnd(1), true, None);
+        caches.set_eligibility(Hwnd(2), false, Some(Duration::from_millis(10)));
+
+        assert_eq!(caches.are_eligible(Hwnd(1), Hwnd(2)), Some(false));
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(caches.eligibility(Hwnd(1)), Some(true));
+        assert_eq!(caches.eligibility(Hwnd(2)), None);
+        assert_eq!(caches.are_eligible(Hwnd(1), Hwnd(2)), None);
+    }
+
+    #[test]
+    fn pairs_are_directional() {
+        let mut caches = Caches::default();
+        caches.entry(Hwnd(1)).pair = Some(Hwnd(2));
+
+        assert!(caches.is_paired(Hwnd(1), Hwnd(2)));
+        assert!(!caches.is_paired(Hwnd(2), Hwnd(1)));
+    }
+
+    #[test]
+    fn invalidates_single_window() {
+        let mut caches = Caches::default();
+        caches.entry(Hwnd(1)).class = Some("Shell_TrayWnd".into());
+        caches.entry(Hwnd(2)).class = Some("SDL_app".into());
+
+        caches.invalidate(Hwnd(1));
+
+        assert!(caches.get(Hwnd(1)).is_none());
+        assert!(caches.get(Hwnd(2)).is_some());
+    }
+
+    #[test]
+    fn clears_when_expired() {
+        let mut caches = Caches::default();
+        caches.entry(Hwnd(1)).class = Some("Shell_TrayWnd".into());
+
+        assert!(!caches.clear_if_older_than(Caches::MAX_AGE));
+        assert_eq!(caches.len(), 1);
+
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(caches.clear_if_older_than(Duration::ZERO));
+        assert!(caches.is_empty());
+    }
+}
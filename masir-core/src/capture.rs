@@ -0,0 +1,84 @@
+//! Tracks region capture overlays from screenshot and recording tools, while
+//! which raising is suspended so that moving across windows during a region
+//! selection doesn't reorder them and ruin the capture
+
+use crate::hwnd::Hwnd;
+use crate::winapi;
+use std::sync::Mutex;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::Accessibility::HWINEVENTHOOK;
+use windows::Win32::UI::WindowsAndMessaging::CHILDID_SELF;
+use windows::Win32::UI::WindowsAndMessaging::EVENT_OBJECT_SHOW;
+use windows::Win32::UI::WindowsAndMessaging::OBJID_WINDOW;
+
+/// Processes which show a window covering the whole monitor while a region of
+/// the screen is being selected or projected
+pub const CAPTURE_PROCESSES: [&str; 7] = [
+    "ScreenClippingHost.exe", // snip & sketch and the win + shift + s overlay
+    "SnippingTool.exe",
+    "ShareX.exe",
+    "Greenshot.exe",
+    "Lightshot.exe",
+    "obs64.exe", // fullscreen projectors
+    "obs32.exe",
+];
+
+// windows of capture processes which have been shown, since their overlays
+// often only cover the monitor once they have been laid out
+static CANDIDATES: Mutex<Vec<Hwnd>> = Mutex::new(vec![]);
+
+/// Returns true if a window of a capture process is visible and covers the
+/// whole of its monitor
+pub fn is_active() -> bool {
+    let mut candidates = CANDIDATES.lock().expect("could not lock capture overlays");
+
+    // hidden and destroyed windows are forgotten, and added again if they
+    // are shown again
+    candidates.retain(|hwnd| winapi::is_visible(*hwnd));
+    candidates.iter().any(|hwnd| winapi::covers_monitor(*hwnd))
+}
+
+/// Spawns a thread which hooks windows being shown to find capture overlays
+pub fn listen_for_capture_overlays() {
+    std::thread::spawn(|| {
+        match winapi::set_win_event_hook(EVENT_OBJECT_SHOW, EVENT_OBJECT_SHOW, Some(on_show)) {
+            Ok(_hook) => winapi::pump_messages(),
+            Err(error) => tracing::error!("failed to listen for capture overlays: {error}"),
+        }
+    });
+}
+
+unsafe extern "system" fn on_show(
+    _hook: HWINEVENTHOOK,
+    _event: u32,
+    hwnd: HWND,
+    id_object: i32,
+    id_child: i32,
+    _id_event_thread: u32,
+    _event_time: u32,
+) {
+    // this fires for every control, menu and caret, so only top-level windows
+    // are looked at any further
+    if id_object != OBJID_WINDOW.0 || id_child != CHILDID_SELF as i32 {
+        return;
+    }
+
+    let hwnd = Hwnd::from(hwnd);
+    if hwnd.root().ok() != Some(hwnd) {
+        return;
+    }
+
+    let is_capture_process = winapi::window_exe(hwnd).is_ok_and(|exe| {
+        CAPTURE_PROCESSES
+            .iter()
+            .any(|process| exe.eq_ignore_ascii_case(process))
+    });
+
+    if is_capture_process {
+        let mut candidates = CANDIDATES.lock().expect("could not lock capture overlays");
+        if !candidates.contains(&hwnd) {
+            tracing::debug!("watching hwnd {hwnd} for a capture overlay");
+            candidates.push(hwnd);
+        }
+    }
+}
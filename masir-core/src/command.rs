@@ -0,0 +1,202 @@
+//! An optional external program which decides whether windows are eligible to
+//! be raised, for integrating with tools that masir doesn't know about
+//!
+//! The program receives a JSON object with the `hwnd`, `class`, `exe` and
+//! `title` of the window under the cursor on stdin, and the window is
+//! eligible if it exits with a zero exit code.
+
+use crate::hwnd::Hwnd;
+use crate::script::ScriptWindow;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use std::collections::HashMap;
+use std::io::Write;
+use std::os::windows::process::CommandExt;
+use std::process::Child;
+use std::process::Command;
+use std::process::Stdio;
+use std::time::Duration;
+use std::time::Instant;
+use windows::Win32::System::Threading::CREATE_NO_WINDOW;
+
+/// A run of the program which hasn't exited yet
+struct Running {
+    hwnd: Hwnd,
+    child: Child,
+    started: Instant,
+}
+
+pub struct EligibilityCommand {
+    command: Vec<String>,
+    // the verdict for each window and when it was decided
+    verdicts: HashMap<Hwnd, (bool, Instant)>,
+    last_run: Option<Instant>,
+    running: Option<Running>,
+}
+
+impl EligibilityCommand {
+    /// How long a verdict is cached for each window
+    const TTL: Duration = Duration::from_secs(30);
+    /// The minimum time between two invocations of the program
+    const MIN_INTERVAL: Duration = Duration::from_millis(250);
+    /// How long the program may run before the window is assumed to be eligible
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    pub fn new(command: Vec<String>) -> Self {
+        Self {
+            command,
+            verdicts: HashMap::new(),
+            last_run: None,
+            running: None,
+        }
+    }
+
+    /// Returns whether the window is eligible, or None if the program is still
+    /// deciding or has run too recently to be run again
+    ///
+    /// The program is started in the background and never waited on, so that
+    /// a slow program can't hold up the event loop, and its verdict is picked
+    /// up by a later call once it has exited.
+    pub fn is_eligible(&mut self, window: &ScriptWindow) -> Option<bool> {
+        self.poll();
+
+        if let Some((verdict, decided)) = self.verdicts.get(&window.hwnd) {
+            if decided.elapsed() < Self::TTL {
+                return Some(*verdict);
+            }
+        }
+
+        if self.running.is_some()
+            || self
+                .last_run
+                .is_some_and(|last_run| last_run.elapsed() < Self::MIN_INTERVAL)
+        {
+            return None;
+        }
+
+        self.last_run = Some(Instant::now());
+
+        // verdicts for windows which have since been destroyed would
+        // otherwise pile up for as long as masir runs
+        self.verdicts
+            .retain(|_, (_, decided)| decided.elapsed() < Self::TTL);
+
+        match self.spawn(window) {
+            Ok(child) => {
+                self.running = Some(Running {
+                    hwnd: window.hwnd,
+                    child,
+                    started: Instant::now(),
+                });
+
+                None
+            }
+            Err(error) => Some(self.record(window.hwnd, Err(error))),
+        }
+    }
+
+    /// Records the verdict of the running program once it has exited, or
+    /// kills it once it has run for too long
+    fn poll(&mut self) {
+        let Some(running) = &mut self.running else {
+            return;
+        };
+
+        let result = match running.child.try_wait() {
+            Ok(Some(status)) => Ok(status.success()),
+            Ok(None) if running.started.elapsed() < Self::TIMEOUT => return,
+            Ok(None) => {
+                let _ = running.child.kill();
+                Err(eyre!("timed out after {}ms", Self::TIMEOUT.as_millis()))
+            }
+            Err(error) => Err(error.into()),
+        };
+
+        let hwnd = running.hwnd;
+        self.running = None;
+        self.record(hwnd, result);
+    }
+
+    fn record(&mut self, hwnd: Hwnd, result: Result<bool>) -> bool {
+        let verdict = result.unwrap_or_else(|error| {
+            // failing open means a broken program can't stop masir from working
+            tracing::warn!("eligibility command failed, assuming eligible: {error}");
+            true
+        });
+
+        tracing::debug!("eligibility command returned {verdict} for hwnd {hwnd}");
+        self.verdicts.insert(hwnd, (verdict, Instant::now()));
+
+        verdict
+    }
+
+    fn spawn(&self, window: &ScriptWindow) -> Result<Child> {
+        let (program, args) = self
+            .command
+            .split_first()
+            .ok_or_else(|| eyre!("the eligibility command is empty"))?;
+
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .creation_flags(CREATE_NO_WINDOW.0)
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(serde_json::to_string(window)?.as_bytes())?;
+        }
+
+        Ok(child)
+    }
+}
+
+impl Drop for EligibilityCommand {
+    fn drop(&mut self) {
+        // a reload replaces the engine, which shouldn't leave the program behind
+        if let Some(running) = &mut self.running {
+            let _ = running.child.kill();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(hwnd: Hwnd) -> ScriptWindow {
+        ScriptWindow {
+            hwnd,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn caches_and_rate_limits_verdicts() {
+        let mut command = EligibilityCommand::new(vec![
+            "cmd".to_string(),
+            "/C".to_string(),
+            "exit 1".to_string(),
+        ]);
+
+        // started in the background
+        assert_eq!(command.is_eligible(&window(Hwnd(1))), None);
+
+        let started = Instant::now();
+        let verdict = loop {
+            if let Some(verdict) = command.is_eligible(&window(Hwnd(1))) {
+                break verdict;
+            }
+
+            assert!(started.elapsed() < EligibilityCommand::TIMEOUT * 2);
+            std::thread::sleep(Duration::from_millis(5));
+        };
+
+        assert!(!verdict);
+        // cached
+        assert_eq!(command.is_eligible(&window(Hwnd(1))), Some(false));
+        // rate limited
+        assert_eq!(command.is_eligible(&window(Hwnd(2))), None);
+    }
+}
@@ -0,0 +1,59 @@
+//! Detection of other focus follows mouse tools, since two of them moving
+//! focus at the same time produces flickering which looks like a bug in masir
+
+use crate::komorebi;
+use crate::winapi;
+use color_eyre::Result;
+
+/// Processes of tools which can move focus to the window under the cursor,
+/// matched by the start of their executable name
+const PROCESSES: [(&str, &str); 3] = [
+    (
+        "XMouseButtonControl",
+        "X-Mouse Button Control is running, disable its option to activate the window under the cursor",
+    ),
+    (
+        "WizMouse",
+        "WizMouse is running, disable its option to bring windows to the front when scrolling them",
+    ),
+    (
+        "AutoHotkey",
+        "AutoHotkey is running, stop any script which activates the window under the cursor",
+    ),
+];
+
+/// Returns a warning for each other focus follows mouse tool which is running
+pub fn detect() -> Result<Vec<String>> {
+    let names = winapi::process_names()?;
+
+    let mut warnings: Vec<String> = PROCESSES
+        .iter()
+        .filter(|(prefix, _)| {
+            names.iter().any(|name| {
+                name.get(..prefix.len())
+                    .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+            })
+        })
+        .map(|(_, warning)| warning.to_string())
+        .collect();
+
+    // komorebi is only asked if it is running, so that this doesn't wait for
+    // komorebic when it isn't installed
+    let komorebi_is_running = names
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case("komorebi.exe"));
+
+    if komorebi_is_running {
+        if let Some(implementation) = komorebi::snapshot()
+            .ok()
+            .and_then(|snapshot| snapshot.focus_follows_mouse)
+        {
+            warnings.push(format!(
+                "komorebi's focus follows mouse ({implementation}) is enabled, disable it with \
+                 `komorebic toggle-focus-follows-mouse`"
+            ));
+        }
+    }
+
+    Ok(warnings)
+}
@@ -0,0 +1,918 @@
+//! The masir.toml configuration format and the settings derived from it
+
+use crate::eligibility::CLASS_IGNORELIST;
+use crate::eligibility::OVERLAY_RULES;
+use crate::eligibility::SHELL_PROCESSES;
+use crate::hit_test::HitTest;
+use crate::hot_corner::HotCorner;
+use crate::hot_corner::HotCornerConfig;
+use crate::komorebi;
+use crate::komorebi::FloatingPolicy;
+use crate::plugin::Plugin;
+use crate::portable;
+use crate::raise::FocusDelegate;
+use crate::raise::RaiseStrategy;
+use crate::schedule::QuietHours;
+use crate::schedule::QuietHoursConfig;
+use crate::script::EligibilityScript;
+use color_eyre::eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Config {
+    /// Settings used when no profile is selected
+    #[serde(flatten)]
+    pub base: ProfileConfig,
+    /// Named profiles which can be selected at startup with --profile
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ProfileConfig {
+    /// Time in milliseconds that the cursor must remain over a window before it is raised
+    pub delay: Option<u64>,
+    /// Distance in pixels that the cursor must travel inside a window before
+    /// it is raised, so that skimming along the edge of a window doesn't
+    /// raise it
+    pub hysteresis: Option<u32>,
+    /// Distance in pixels which the cursor must jump between two movements
+    /// to be treated as warped by another tool, such as remote control or KVM
+    /// software, after which it must move again before anything is raised, or
+    /// 0 to disable (default: 0)
+    pub teleport_distance: Option<u32>,
+    /// Time in milliseconds after a raise during which going back to the
+    /// window which had focus is treated as jitter across the boundary
+    /// between them, so that it is only raised once the cursor has stayed
+    /// over it for this long, or 0 to disable (default: 0)
+    pub jitter_window: Option<u64>,
+    /// Windows smaller than this are never raised, such as the tiny helper
+    /// and notification windows which some applications leave on screen
+    pub min_window_size: Option<WindowSize>,
+    /// How to find the window under the cursor, since the best choice differs
+    /// between applications such as Explorer, Electron apps and games
+    pub hit_test: Option<HitTest>,
+    /// Windows matching any of these rules will never be raised or lose focus
+    pub ignore_rules: Option<Vec<MatchingRule>>,
+    /// Windows matching any of these rules never lose focus while they are in
+    /// the foreground, whichever window is hovered, such as games running in
+    /// a window or a DAW while recording
+    pub protect_rules: Option<Vec<MatchingRule>>,
+    /// masir is paused while a window matching any of these rules is in the
+    /// foreground, such as remote desktop clients or CAD applications, and
+    /// resumes once it loses the foreground
+    pub pause_rules: Option<Vec<MatchingRule>>,
+    /// Windows showing another session, such as remote desktop clients and
+    /// virtual machine consoles, with how they are raised so that focus
+    /// follows mouse on the host doesn't fight the one inside the guest
+    pub guest_rules: Option<Vec<GuestRuleConfig>>,
+    /// Keep focus on the foreground window while its application is playing
+    /// audio, such as during a video call or while watching a video (default:
+    /// false)
+    pub protect_audio: Option<bool>,
+    /// Windows matching any of these rules can lose focus while their
+    /// application is playing audio, such as music players
+    pub protect_audio_exceptions: Option<Vec<MatchingRule>>,
+    /// komorebi's applications.json or applications.yaml, whose ignore rules
+    /// are added to ignore_rules
+    pub komorebi_applications: Option<PathBuf>,
+    /// Pause and resume along with komorebi, such as when it is paused for a
+    /// game (default: false)
+    pub komorebi_pause_sync: Option<bool>,
+    /// Whether windows which komorebi floats above its tiles can be raised,
+    /// only raised after a delay, or never raised (default: Eligible)
+    pub komorebi_floating: Option<FloatingPolicy>,
+    /// Rules for the windows on komorebi workspaces keyed by workspace name,
+    /// which can stop them from being raised, give them their own delay or
+    /// add ignore rules
+    pub komorebi_workspaces: Option<BTreeMap<String, WorkspaceConfig>>,
+    /// Entries of the built-in class ignorelist (e.g. Shell_TrayWnd or
+    /// Flow.Launcher), shell processes (e.g. explorer.exe) or overlays (e.g.
+    /// RTSS.exe) whose windows should be raised like any other
+    pub disabled_builtin_ignores: Option<Vec<String>>,
+    /// Ignore the windows of common game and GPU overlays, such as those of
+    /// Discord, GeForce Experience, Steam and RTSS (default: true)
+    pub ignore_overlays: Option<bool>,
+    /// Time in milliseconds between evaluations of mouse movements while
+    /// running on battery, when window properties are also cached for longer,
+    /// or 0 to run as usual on battery (default: 100)
+    pub battery_interval: Option<u64>,
+    /// Synthesize an Alt key tap instead of an empty mouse input to pass the
+    /// foreground lock check when raising windows
+    pub alt_tap: Option<bool>,
+    /// The raise strategies to try in order until one succeeds, starting with
+    /// the one which last succeeded for the window's class, which replaces
+    /// alt_tap (default: [SendInput, AttachThreadInput])
+    pub raise_strategies: Option<Vec<RaiseStrategy>>,
+    /// Raise strategies to try instead of raise_strategies for windows of the
+    /// given classes
+    pub raise_strategy_overrides: Option<BTreeMap<String, Vec<RaiseStrategy>>>,
+    /// Temporarily set the system foreground lock timeout to 0 while masir is
+    /// running, restoring the original value on exit
+    pub disable_foreground_lock_timeout: Option<bool>,
+    /// A window manager to ask to focus the windows it manages (those listed
+    /// in the hwnds files, if there are any) instead of raising them directly,
+    /// so that its own focus state stays in sync
+    pub focus_delegate: Option<FocusDelegate>,
+    /// Push the previously focused window one step down in the z-order when
+    /// a new window is raised
+    pub auto_lower: Option<bool>,
+    /// Treat all top-level windows of the same process as one focus group,
+    /// where moving between them never raises a window (default: false)
+    pub group_by_process: Option<bool>,
+    /// Give keyboard focus to the control under the cursor, such as a text
+    /// box, after raising a window (default: false)
+    pub deep_focus: Option<bool>,
+    /// Focus the desktop when the cursor moves over it, so that keyboard input
+    /// stops going to the previously focused window
+    pub strict: Option<bool>,
+    /// Show a toast notification whenever masir pauses or resumes
+    pub notifications: Option<bool>,
+    /// Pause or resume when the cursor is shaken quickly from side to side
+    /// (default: false)
+    pub shake_to_pause: Option<bool>,
+    /// A corner of the screen which pauses or resumes when the cursor rests
+    /// in it
+    pub hot_corner: Option<HotCornerConfig>,
+    /// Append a JSON line describing every raise (timestamp, window, outcome
+    /// and latency) to this file
+    pub raise_log: Option<PathBuf>,
+    /// A Rhai script defining should_raise(cursor, foreground), which can
+    /// force or veto a raise by returning true or false
+    pub eligibility_script: Option<PathBuf>,
+    /// A program and its arguments which receives the window under the cursor
+    /// as JSON on stdin, where a non-zero exit code vetoes the raise
+    pub eligibility_command: Option<Vec<String>>,
+    /// Programs and their arguments which are started whenever a window is
+    /// raised, where {hwnd}, {class}, {exe} and {title} in the arguments are
+    /// replaced with the properties of that window
+    pub on_raise: Option<Vec<Vec<String>>>,
+    /// A sound file (.wav) which is played whenever masir changes focus, so
+    /// that a silent move of keyboard focus can be noticed
+    pub focus_sound: Option<PathBuf>,
+    /// Sound files (.wav) keyed by monitor device name (e.g. \\.\DISPLAY2) or
+    /// number (e.g. 2), which are played instead of focus_sound when focus
+    /// moves to a window on that monitor
+    pub focus_sounds: Option<BTreeMap<String, PathBuf>>,
+    /// Rules for the windows on a monitor keyed by its device name (e.g.
+    /// \\.\DISPLAY2) or its number (e.g. 2), which can stop them from being
+    /// raised, give them their own delay or add ignore rules
+    pub monitors: Option<BTreeMap<String, MonitorConfig>>,
+    /// Time windows during which windows are never raised, such as a daily
+    /// standup or the weekend
+    pub quiet_hours: Option<Vec<QuietHoursConfig>>,
+    /// WebAssembly plugins which can force or veto a raise and choose raise
+    /// strategies, consulted in order
+    pub plugins: Option<Vec<PathBuf>>,
+    /// Files with known focus-able HWNDs written by window managers, in
+    /// addition to any given with --hwnds
+    pub hwnds: Option<Vec<PathBuf>>,
+}
+
+impl ProfileConfig {
+    /// Replaces the settings which are specified in the given profile
+    fn apply(&mut self, profile: &ProfileConfig) {
+        macro_rules! apply {
+            ( $( $field:ident ),+ ) => {
+                $(
+                    if profile.$field.is_some() {
+                        self.$field = profile.$field.clone();
+                    }
+                )+
+            };
+        }
+
+        apply!(
+            delay,
+            hysteresis,
+            teleport_distance,
+            jitter_window,
+            min_window_size,
+            hit_test,
+            ignore_rules,
+            protect_rules,
+            pause_rules,
+            guest_rules,
+            protect_audio,
+            protect_audio_exceptions,
+            komorebi_applications,
+            komorebi_pause_sync,
+            komorebi_floating,
+            komorebi_workspaces,
+            disabled_builtin_ignores,
+            ignore_overlays,
+            battery_interval,
+            alt_tap,
+            raise_strategies,
+            raise_strategy_overrides,
+            disable_foreground_lock_timeout,
+            focus_delegate,
+            auto_lower,
+            group_by_process,
+            deep_focus,
+            strict,
+            notifications,
+            shake_to_pause,
+            hot_corner,
+            raise_log,
+            eligibility_script,
+            eligibility_command,
+            on_raise,
+            focus_sound,
+            focus_sounds,
+            monitors,
+            quiet_hours,
+            plugins,
+            hwnds
+        );
+    }
+}
+
+/// Settings for the windows on a single monitor
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct MonitorConfig {
+    /// Never raise windows on the monitor (default: false)
+    pub disabled: Option<bool>,
+    /// Time in milliseconds that the cursor must remain over a window on the
+    /// monitor before it is raised, instead of delay
+    pub delay: Option<u64>,
+    /// Windows on the monitor matching any of these rules will never be
+    /// raised, in addition to ignore_rules
+    pub ignore_rules: Option<Vec<MatchingRule>>,
+}
+
+impl MonitorConfig {
+    fn compile(&self) -> Result<MonitorRule> {
+        Ok(MonitorRule {
+            disabled: self.disabled.unwrap_or_default(),
+            delay: self.delay.map(Duration::from_millis),
+            ignore_rules: self
+                .ignore_rules
+                .iter()
+                .flatten()
+                .map(IgnoreRule::compile)
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+/// The effective settings for the windows on a single monitor
+#[derive(Debug)]
+pub struct MonitorRule {
+    pub disabled: bool,
+    pub delay: Option<Duration>,
+    pub ignore_rules: Vec<IgnoreRule>,
+}
+
+/// Settings for the windows on a named komorebi workspace
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct WorkspaceConfig {
+    /// Never raise windows on the workspace (default: false)
+    pub disabled: Option<bool>,
+    /// Time in milliseconds that the cursor must remain over a window on the
+    /// workspace before it is raised, instead of delay
+    pub delay: Option<u64>,
+    /// Windows on the workspace matching any of these rules will never be
+    /// raised, in addition to ignore_rules
+    pub ignore_rules: Option<Vec<MatchingRule>>,
+}
+
+impl WorkspaceConfig {
+    fn compile(&self) -> Result<WorkspaceRule> {
+        Ok(WorkspaceRule {
+            disabled: self.disabled.unwrap_or_default(),
+            delay: self.delay.map(Duration::from_millis),
+            ignore_rules: self
+                .ignore_rules
+                .iter()
+                .flatten()
+                .map(IgnoreRule::compile)
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+/// The effective settings for the windows on a named komorebi workspace
+#[derive(Debug)]
+pub struct WorkspaceRule {
+    pub disabled: bool,
+    pub delay: Option<Duration>,
+    pub ignore_rules: Vec<IgnoreRule>,
+}
+
+/// Returns the device name of a monitor given either as a device name or as
+/// the number in its device name
+fn monitor_device_name(monitor: &str) -> String {
+    match monitor.parse::<u32>() {
+        Ok(number) => format!(r"\\.\DISPLAY{number}"),
+        Err(_) => monitor.to_string(),
+    }
+}
+
+/// How a window showing another session is raised
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum GuestPolicy {
+    /// Raise the window only once the cursor has remained over it for this
+    /// many milliseconds, or the configured delay if that is longer
+    Dwell(u64),
+    /// Never raise the window when it is hovered, leaving it to be focused by
+    /// clicking it
+    Click,
+}
+
+/// A rule for windows showing another session, such as remote desktop
+/// clients and virtual machine consoles
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GuestRuleConfig {
+    #[serde(flatten)]
+    pub rule: MatchingRule,
+    pub policy: GuestPolicy,
+}
+
+impl GuestRuleConfig {
+    fn compile(&self) -> Result<GuestRule> {
+        Ok(GuestRule {
+            rule: IgnoreRule::compile(&self.rule)?,
+            policy: self.policy,
+        })
+    }
+}
+
+/// A guest rule with its matching rule compiled
+#[derive(Debug)]
+pub struct GuestRule {
+    pub rule: IgnoreRule,
+    pub policy: GuestPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct WindowSize {
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MatchingRule {
+    pub kind: ApplicationIdentifier,
+    pub id: String,
+    #[serde(default)]
+    pub matching_strategy: MatchingStrategy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum ApplicationIdentifier {
+    Exe,
+    Class,
+    Title,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum MatchingStrategy {
+    #[default]
+    Equals,
+    StartsWith,
+    EndsWith,
+    Contains,
+    Regex,
+}
+
+/// The effective settings after a profile has been applied to the base config
+#[derive(Debug)]
+pub struct Settings {
+    pub delay: Duration,
+    pub hysteresis: u32,
+    pub teleport_distance: u32,
+    pub jitter_window: Duration,
+    pub min_window_size: Option<WindowSize>,
+    pub hit_test: HitTest,
+    pub ignore_rules: Vec<IgnoreRule>,
+    pub protect_rules: Vec<IgnoreRule>,
+    pub pause_rules: Vec<IgnoreRule>,
+    pub guest_rules: Vec<GuestRule>,
+    pub protect_audio: bool,
+    pub protect_audio_exceptions: Vec<IgnoreRule>,
+    pub komorebi_pause_sync: bool,
+    pub komorebi_floating: FloatingPolicy,
+    pub komorebi_workspaces: BTreeMap<String, WorkspaceRule>,
+    pub disabled_builtin_ignores: Vec<String>,
+    pub battery_interval: Duration,
+    /// The raise strategies to try, in order, for windows of a class without
+    /// a remembered strategy
+    pub raise_strategies: Vec<RaiseStrategy>,
+    /// The raise strategies to try instead for windows of each class
+    pub raise_strategy_overrides: BTreeMap<String, Vec<RaiseStrategy>>,
+    pub disable_foreground_lock_timeout: bool,
+    pub focus_delegate: Option<FocusDelegate>,
+    pub auto_lower: bool,
+    pub group_by_process: bool,
+    pub deep_focus: bool,
+    pub strict: bool,
+    pub notifications: bool,
+    pub shake_to_pause: bool,
+    pub hot_corner: Option<HotCorner>,
+    pub raise_log: Option<PathBuf>,
+    pub eligibility_script: Option<PathBuf>,
+    pub eligibility_command: Option<Vec<String>>,
+    pub on_raise: Vec<Vec<String>>,
+    pub focus_sound: Option<PathBuf>,
+    pub focus_sounds: BTreeMap<String, PathBuf>,
+    /// Rules for the windows on each monitor, keyed by device name
+    pub monitors: BTreeMap<String, MonitorRule>,
+    pub quiet_hours: Vec<QuietHours>,
+    pub plugins: Vec<PathBuf>,
+    pub hwnds: Vec<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            delay: Duration::ZERO,
+            hysteresis: 0,
+            teleport_distance: 0,
+            jitter_window: Duration::ZERO,
+            min_window_size: None,
+            hit_test: HitTest::default(),
+            ignore_rules: vec![],
+            protect_rules: vec![],
+            pause_rules: vec![],
+            guest_rules: vec![],
+            protect_audio: false,
+            protect_audio_exceptions: vec![],
+            komorebi_pause_sync: false,
+            komorebi_floating: FloatingPolicy::default(),
+            komorebi_workspaces: BTreeMap::new(),
+            disabled_builtin_ignores: vec![],
+            battery_interval: Duration::from_millis(100),
+            raise_strategies: vec![RaiseStrategy::SendInput, RaiseStrategy::AttachThreadInput],
+            raise_strategy_overrides: BTreeMap::new(),
+            disable_foreground_lock_timeout: false,
+            focus_delegate: None,
+            auto_lower: false,
+            group_by_process: false,
+            deep_focus: false,
+            strict: false,
+            notifications: false,
+            shake_to_pause: false,
+            hot_corner: None,
+            raise_log: None,
+            eligibility_script: None,
+            eligibility_command: None,
+            on_raise: vec![],
+            focus_sound: None,
+            focus_sounds: BTreeMap::new(),
+            monitors: BTreeMap::new(),
+            quiet_hours: vec![],
+            plugins: vec![],
+            hwnds: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IgnoreRule {
+    kind: ApplicationIdentifier,
+    id: String,
+    matching_strategy: MatchingStrategy,
+    regex: Option<Regex>,
+}
+
+impl IgnoreRule {
+    pub(crate) fn compile(rule: &MatchingRule) -> Result<Self> {
+        let regex = match rule.matching_strategy {
+            MatchingStrategy::Regex => {
+                Some(Regex::new(&rule.id).wrap_err_with(|| format!("invalid regex: {}", rule.id))?)
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            kind: rule.kind,
+            id: rule.id.clone(),
+            matching_strategy: rule.matching_strategy,
+            regex,
+        })
+    }
+
+    pub fn kind(&self) -> ApplicationIdentifier {
+        self.kind
+    }
+
+    /// Returns the rule this was compiled from
+    pub fn to_matching_rule(&self) -> MatchingRule {
+        MatchingRule {
+            kind: self.kind,
+            id: self.id.clone(),
+            matching_strategy: self.matching_strategy,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        match &self.regex {
+            Some(regex) => regex.is_match(value),
+            None => has_match(value, &self.id, &self.matching_strategy),
+        }
+    }
+}
+
+/// Returns true if the program can be started, either as a path to it or by
+/// name from a directory on the PATH, where a name without an extension is
+/// looked up as an executable
+fn is_program(program: &str) -> bool {
+    let program = Path::new(program);
+    let candidates = |path: PathBuf| {
+        let exe = path
+            .extension()
+            .is_none()
+            .then(|| path.with_extension("exe"));
+        std::iter::once(path).chain(exe)
+    };
+
+    if program.components().count() > 1 {
+        return candidates(program.to_path_buf()).any(|path| path.is_file());
+    }
+
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths)
+            .flat_map(|dir| candidates(dir.join(program)))
+            .any(|path| path.is_file())
+    })
+}
+
+pub fn has_match(str1: &str, str2: &str, matching_strategy: &MatchingStrategy) -> bool {
+    match matching_strategy {
+        MatchingStrategy::Equals => str1 == str2,
+        MatchingStrategy::StartsWith => str1.starts_with(str2),
+        MatchingStrategy::EndsWith => str1.ends_with(str2),
+        MatchingStrategy::Contains => str1.contains(str2),
+        MatchingStrategy::Regex => Regex::new(str2).is_ok_and(|regex| regex.is_match(str1)),
+    }
+}
+
+impl Config {
+    /// Returns the default path of the config file, which is next to the
+    /// executable in portable mode
+    pub fn path() -> PathBuf {
+        portable::config_dir().join("masir.toml")
+    }
+
+    /// Loads the config at the given path, falling back to the default config
+    /// if the file does not exist
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        let raw = std::fs::read_to_string(path)?;
+        toml::from_str(&raw).wrap_err_with(|| format!("could not parse {}", path.display()))
+    }
+
+    /// Compiles every rule in the base config and in all profiles, returning
+    /// a description of each problem found along with its location
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+
+        let sections = std::iter::once((String::new(), &self.base)).chain(
+            self.profiles
+                .iter()
+                .map(|(name, profile)| (format!("profiles.{name}."), profile)),
+        );
+
+        for (prefix, profile) in sections {
+            for (i, rule) in profile.ignore_rules.iter().flatten().enumerate() {
+                if let Err(error) = IgnoreRule::compile(rule) {
+                    errors.push(format!("{prefix}ignore_rules[{i}]: {error:#}"));
+                }
+            }
+
+            for (i, rule) in profile.protect_rules.iter().flatten().enumerate() {
+                if let Err(error) = IgnoreRule::compile(rule) {
+                    errors.push(format!("{prefix}protect_rules[{i}]: {error:#}"));
+                }
+            }
+
+            for (i, rule) in profile.pause_rules.iter().flatten().enumerate() {
+                if let Err(error) = IgnoreRule::compile(rule) {
+                    errors.push(format!("{prefix}pause_rules[{i}]: {error:#}"));
+                }
+            }
+
+            for (i, rule) in profile.guest_rules.iter().flatten().enumerate() {
+                if let Err(error) = rule.compile() {
+                    errors.push(format!("{prefix}guest_rules[{i}]: {error:#}"));
+                }
+            }
+
+            for (i, rule) in profile
+                .protect_audio_exceptions
+                .iter()
+                .flatten()
+                .enumerate()
+            {
+                if let Err(error) = IgnoreRule::compile(rule) {
+                    errors.push(format!("{prefix}protect_audio_exceptions[{i}]: {error:#}"));
+                }
+            }
+
+            if let Some(path) = &profile.komorebi_applications {
+                match komorebi::ignore_rules(path) {
+                    Ok(rules) => {
+                        for rule in rules {
+                            if let Err(error) = IgnoreRule::compile(&rule) {
+                                errors.push(format!("{prefix}komorebi_applications: {error:#}"));
+                            }
+                        }
+                    }
+                    Err(error) => errors.push(format!("{prefix}komorebi_applications: {error:#}")),
+                }
+            }
+
+            for (i, class) in profile
+                .disabled_builtin_ignores
+                .iter()
+                .flatten()
+                .enumerate()
+            {
+                if !CLASS_IGNORELIST
+                    .iter()
+                    .any(|(id, _)| id.eq_ignore_ascii_case(class))
+                    && !SHELL_PROCESSES
+                        .iter()
+                        .any(|process| process.eq_ignore_ascii_case(class))
+                    && !OVERLAY_RULES
+                        .iter()
+                        .any(|(_, id)| id.eq_ignore_ascii_case(class))
+                {
+                    errors.push(format!(
+                        "{prefix}disabled_builtin_ignores[{i}]: {class} is not in the built-in ignorelist"
+                    ));
+                }
+            }
+
+            if profile
+                .raise_strategies
+                .as_ref()
+                .is_some_and(|strategies| strategies.is_empty())
+            {
+                errors.push(format!("{prefix}raise_strategies: no strategies are given"));
+            }
+
+            for (class, strategies) in profile.raise_strategy_overrides.iter().flatten() {
+                if strategies.is_empty() {
+                    errors.push(format!(
+                        "{prefix}raise_strategy_overrides.{class}: no strategies are given"
+                    ));
+                }
+            }
+
+            match profile.eligibility_command.as_deref() {
+                Some([]) => {
+                    errors.push(format!("{prefix}eligibility_command: the command is empty"));
+                }
+                Some([program, ..]) if !is_program(program) => {
+                    errors.push(format!(
+                        "{prefix}eligibility_command: {program} could not be found"
+                    ));
+                }
+                _ => {}
+            }
+
+            if let Some(path) = &profile.raise_log {
+                // the raise log is created on startup, but not its directory
+                let dir = path
+                    .parent()
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .unwrap_or(Path::new("."));
+
+                if path.is_dir() {
+                    errors.push(format!(
+                        "{prefix}raise_log: {} is a directory",
+                        path.display()
+                    ));
+                } else if !dir.is_dir() {
+                    errors.push(format!(
+                        "{prefix}raise_log: {} does not exist",
+                        dir.display()
+                    ));
+                }
+            }
+
+            // hwnds files which don't exist are left out on startup
+            for (i, path) in profile.hwnds.iter().flatten().enumerate() {
+                if !path.is_file() {
+                    errors.push(format!(
+                        "{prefix}hwnds[{i}]: {} does not exist",
+                        path.display()
+                    ));
+                }
+            }
+
+            for (i, command) in profile.on_raise.iter().flatten().enumerate() {
+                if command.is_empty() {
+                    errors.push(format!("{prefix}on_raise[{i}]: the command is empty"));
+                }
+            }
+
+            if let Some(path) = &profile.focus_sound {
+                if !path.is_file() {
+                    errors.push(format!(
+                        "{prefix}focus_sound: {} does not exist",
+                        path.display()
+                    ));
+                }
+            }
+
+            for (monitor, config) in profile.monitors.iter().flatten() {
+                for (i, rule) in config.ignore_rules.iter().flatten().enumerate() {
+                    if let Err(error) = IgnoreRule::compile(rule) {
+                        errors.push(format!(
+                            "{prefix}monitors[{monitor}].ignore_rules[{i}]: {error:#}"
+                        ));
+                    }
+                }
+            }
+
+            for (workspace, config) in profile.komorebi_workspaces.iter().flatten() {
+                for (i, rule) in config.ignore_rules.iter().flatten().enumerate() {
+                    if let Err(error) = IgnoreRule::compile(rule) {
+                        errors.push(format!(
+                            "{prefix}komorebi_workspaces[{workspace}].ignore_rules[{i}]: {error:#}"
+                        ));
+                    }
+                }
+            }
+
+            for (i, quiet_hours) in profile.quiet_hours.iter().flatten().enumerate() {
+                if let Err(error) = quiet_hours.compile() {
+                    errors.push(format!("{prefix}quiet_hours[{i}]: {error:#}"));
+                }
+            }
+
+            for (monitor, path) in profile.focus_sounds.iter().flatten() {
+                if !path.is_file() {
+                    errors.push(format!(
+                        "{prefix}focus_sounds[{monitor}]: {} does not exist",
+                        path.display()
+                    ));
+                }
+            }
+
+            for (i, path) in profile.plugins.iter().flatten().enumerate() {
+                if let Err(error) = Plugin::load(path) {
+                    errors.push(format!("{prefix}plugins[{i}]: {error:#}"));
+                }
+            }
+
+            if let Some(path) = &profile.eligibility_script {
+                if let Err(error) = EligibilityScript::load(path) {
+                    errors.push(format!("{prefix}eligibility_script: {error:#}"));
+                }
+            }
+        }
+
+        errors
+    }
+
+    pub fn settings(&self, profile: Option<&str>) -> Result<Settings> {
+        let mut config = self.base.clone();
+
+        if let Some(name) = profile {
+            let profile = self
+                .profiles
+                .get(name)
+                .ok_or_else(|| eyre!("there is no profile named {name} in the config"))?;
+
+            config.apply(profile);
+        }
+
+        let foreground_lock_strategy = if config.alt_tap.unwrap_or_default() {
+            RaiseStrategy::AltTap
+        } else {
+            RaiseStrategy::SendInput
+        };
+
+        let mut ignore_rules = config.ignore_rules.unwrap_or_default();
+        if config.ignore_overlays.unwrap_or(true) {
+            let disabled = config.disabled_builtin_ignores.iter().flatten();
+
+            ignore_rules.extend(
+                OVERLAY_RULES
+                    .iter()
+                    .filter(|(_, id)| {
+                        !disabled
+                            .clone()
+                            .any(|disabled| disabled.eq_ignore_ascii_case(id))
+                    })
+                    .map(|(kind, id)| MatchingRule {
+                        kind: *kind,
+                        id: id.to_string(),
+                        matching_strategy: MatchingStrategy::Equals,
+                    }),
+            );
+        }
+
+        if let Some(path) = &config.komorebi_applications {
+            ignore_rules.extend(komorebi::ignore_rules(path)?);
+        }
+
+        Ok(Settings {
+            delay: Duration::from_millis(config.delay.unwrap_or_default()),
+            hysteresis: config.hysteresis.unwrap_or_default(),
+            teleport_distance: config.teleport_distance.unwrap_or_default(),
+            jitter_window: Duration::from_millis(config.jitter_window.unwrap_or_default()),
+            min_window_size: config.min_window_size,
+            hit_test: config.hit_test.unwrap_or_default(),
+            ignore_rules: ignore_rules
+                .iter()
+                .map(IgnoreRule::compile)
+                .collect::<Result<_>>()?,
+            protect_rules: config
+                .protect_rules
+                .iter()
+                .flatten()
+                .map(IgnoreRule::compile)
+                .collect::<Result<_>>()?,
+            pause_rules: config
+                .pause_rules
+                .iter()
+                .flatten()
+                .map(IgnoreRule::compile)
+                .collect::<Result<_>>()?,
+            guest_rules: config
+                .guest_rules
+                .iter()
+                .flatten()
+                .map(GuestRuleConfig::compile)
+                .collect::<Result<_>>()?,
+            protect_audio: config.protect_audio.unwrap_or_default(),
+            protect_audio_exceptions: config
+                .protect_audio_exceptions
+                .iter()
+                .flatten()
+                .map(IgnoreRule::compile)
+                .collect::<Result<_>>()?,
+            komorebi_pause_sync: config.komorebi_pause_sync.unwrap_or_default(),
+            komorebi_floating: config.komorebi_floating.unwrap_or_default(),
+            komorebi_workspaces: config
+                .komorebi_workspaces
+                .iter()
+                .flatten()
+                .map(|(workspace, config)| Ok((workspace.clone(), config.compile()?)))
+                .collect::<Result<_>>()?,
+            disabled_builtin_ignores: config.disabled_builtin_ignores.unwrap_or_default(),
+            battery_interval: Duration::from_millis(config.battery_interval.unwrap_or(100)),
+            raise_strategies: config.raise_strategies.unwrap_or_else(|| {
+                vec![foreground_lock_strategy, RaiseStrategy::AttachThreadInput]
+            }),
+            raise_strategy_overrides: config.raise_strategy_overrides.unwrap_or_default(),
+            disable_foreground_lock_timeout: config
+                .disable_foreground_lock_timeout
+                .unwrap_or_default(),
+            focus_delegate: config.focus_delegate,
+            auto_lower: config.auto_lower.unwrap_or_default(),
+            group_by_process: config.group_by_process.unwrap_or_default(),
+            deep_focus: config.deep_focus.unwrap_or_default(),
+            strict: config.strict.unwrap_or_default(),
+            notifications: config.notifications.unwrap_or_default(),
+            shake_to_pause: config.shake_to_pause.unwrap_or_default(),
+            hot_corner: config.hot_corner.as_ref().map(HotCornerConfig::compile),
+            raise_log: config.raise_log,
+            eligibility_script: config.eligibility_script,
+            eligibility_command: config.eligibility_command,
+            on_raise: config.on_raise.unwrap_or_default(),
+            focus_sound: config.focus_sound,
+            focus_sounds: config
+                .focus_sounds
+                .into_iter()
+                .flatten()
+                .map(|(monitor, path)| (monitor_device_name(&monitor), path))
+                .collect(),
+            monitors: config
+                .monitors
+                .iter()
+                .flatten()
+                .map(|(monitor, config)| Ok((monitor_device_name(monitor), config.compile()?)))
+                .collect::<Result<_>>()?,
+            quiet_hours: config
+                .quiet_hours
+                .iter()
+                .flatten()
+                .map(QuietHoursConfig::compile)
+                .collect::<Result<_>>()?,
+            plugins: config.plugins.unwrap_or_default(),
+            hwnds: config.hwnds.unwrap_or_default(),
+        })
+    }
+}
@@ -0,0 +1,104 @@
+//! Crash reports for rare crashes which can't be reproduced, made up of a
+//! minidump and a log naming the window which was last evaluated
+//!
+//! Unhandled exceptions are caught with the unhandled exception filter rather
+//! than a vectored exception handler, since vectored handlers also see first
+//! chance exceptions which are handled later, such as those thrown inside
+//! UI Automation providers.
+
+use crate::hwnd::Hwnd;
+use crate::winapi;
+use std::backtrace::Backtrace;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use windows::Win32::System::Diagnostics::Debug::EXCEPTION_CONTINUE_SEARCH;
+use windows::Win32::System::Diagnostics::Debug::EXCEPTION_POINTERS;
+
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+static WINDOW: Mutex<Option<(Hwnd, Option<Arc<str>>)>> = Mutex::new(None);
+
+/// Writes crash reports to the given directory for panics on any thread and
+/// for unhandled exceptions
+pub fn install(dir: PathBuf) {
+    if DIR.set(dir).is_err() {
+        return;
+    }
+
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        report(&format!("{info}\n\n{}", Backtrace::force_capture()), None);
+        previous(info);
+    }));
+
+    winapi::set_unhandled_exception_filter(Some(unhandled_exception_filter));
+}
+
+/// Records the window which is being evaluated, to be named in crash reports
+pub fn record_window(hwnd: Hwnd, class: Option<Arc<str>>) {
+    if let Ok(mut window) = WINDOW.lock() {
+        *window = Some((hwnd, class));
+    }
+}
+
+unsafe extern "system" fn unhandled_exception_filter(pointers: *const EXCEPTION_POINTERS) -> i32 {
+    let record = &*(*pointers).ExceptionRecord;
+    report(
+        &format!(
+            "unhandled exception {:#010x} at {:?}",
+            record.ExceptionCode.0, record.ExceptionAddress
+        ),
+        Some(pointers),
+    );
+
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+fn report(message: &str, exception: Option<*const EXCEPTION_POINTERS>) {
+    let Some(dir) = DIR.get() else {
+        return;
+    };
+
+    if let Err(error) = std::fs::create_dir_all(dir) {
+        tracing::error!("could not create {}: {error}", dir.display());
+        return;
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let dump = dir.join(format!("masir-{timestamp}.dmp"));
+    let log = dir.join(format!("masir-{timestamp}.log"));
+
+    let thread = std::thread::current();
+    let thread = thread.name().unwrap_or("unnamed");
+
+    // the lock may be held by the thread which crashed
+    let window = match WINDOW.try_lock().as_deref() {
+        Ok(Some((hwnd, class))) => format!(
+            "hwnd {hwnd} ({})",
+            class.as_deref().unwrap_or("unknown class")
+        ),
+        Ok(None) => String::from("none"),
+        Err(_) => String::from("unknown"),
+    };
+
+    let minidump = match winapi::write_minidump(&dump, exception) {
+        Ok(()) => dump.display().to_string(),
+        Err(error) => format!("could not be written: {error}"),
+    };
+
+    let contents = format!(
+        "masir {} crashed on the {thread} thread\n\nlast evaluated window: {window}\nminidump: {minidump}\n\n{message}\n",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    match std::fs::write(&log, contents) {
+        Ok(()) => tracing::error!("masir crashed, see {}", log.display()),
+        Err(error) => tracing::error!("could not write a crash log to {}: {error}", log.display()),
+    }
+}
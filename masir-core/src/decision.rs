@@ -0,0 +1,234 @@
+//! Machine-readable reasons for every decision to raise or skip the window
+//! under the cursor, so that tooling and bug reports carry exact causes
+
+use crate::hwnd::Hwnd;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+/// Which of the two windows being compared a reason applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// The top-level window under the cursor
+    Cursor,
+    /// The window which currently has focus
+    Foreground,
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::Cursor => write!(f, "the window under the cursor"),
+            Role::Foreground => write!(f, "the foreground window"),
+        }
+    }
+}
+
+/// Why the window under the cursor was or wasn't raised
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "content")]
+pub enum DecisionReason {
+    /// Both windows passed the eligibility checks
+    Eligible,
+    /// Both windows were found as eligible in the cache
+    CacheHitEligible,
+    /// The desktop is focused in strict mode once the cursor is over it
+    StrictDesktop,
+    /// A plugin forced the window to be raised
+    ForcedByPlugin,
+    /// The eligibility script forced the window to be raised
+    ForcedByScript,
+    /// The window under the cursor or the foreground window could not be found
+    NoWindow,
+    /// The window under the cursor already has focus
+    AlreadyFocused,
+    /// The top-level window under the cursor could not be found
+    NoRootWindow,
+    /// The window under the cursor belongs to the foreground window
+    SameRoot,
+    /// The raise of the window under the cursor was undone, and it isn't
+    /// raised again until the cursor leaves it
+    Undone,
+    /// The cursor went straight back to the window which had focus before the
+    /// last raise, and hasn't stayed over it for the jitter window yet
+    Jitter,
+    /// One of the windows belongs to masir
+    OwnWindow,
+    /// Raising the window under the cursor timed out recently
+    Unresponsive,
+    /// The window under the cursor is smaller than the minimum window size
+    TooSmall,
+    /// The window under the cursor is hidden, minimized or off screen
+    Hidden,
+    /// The window under the cursor is a menu, tooltip or other transient
+    /// surface
+    Transient,
+    /// The two windows are known to belong to the same application
+    PairedApp,
+    /// The two windows belong to the same process, which are grouped together
+    SameProcess,
+    /// The foreground window matches one of the protect rules
+    ProtectedForeground { rule: String },
+    /// The application of the foreground window is playing audio
+    PlayingAudio,
+    /// The cursor is over the desktop outside of strict mode, or the
+    /// foreground window must not lose focus to it
+    Desktop,
+    /// One of the windows was found as ineligible in the cache
+    CacheHitIneligible,
+    /// None of the hwnds files could be read
+    HwndsUnavailable,
+    /// One of the windows isn't listed in any of the hwnds files
+    NotManaged { window: Role },
+    /// One of the windows has a style which is never raised or focused away
+    /// from, such as a tool window
+    FilteredStyle { window: Role },
+    /// One of the windows matches a built-in ignorelist entry, a shell
+    /// process or an ignore rule
+    Ignored { window: Role, rule: String },
+    /// The window under the cursor is behind a komorebi monocle container
+    BehindMonocle,
+    /// The window under the cursor is floating in komorebi, which is ignored
+    Floating,
+    /// The window under the cursor matches a guest rule which leaves it to be
+    /// focused by clicking it
+    RequiresClick { rule: String },
+    /// It is currently one of the quiet hours during which windows are never
+    /// raised
+    QuietHours,
+    /// The window under the cursor is on a monitor where raising windows is
+    /// disabled
+    DisabledMonitor { monitor: String },
+    /// The window under the cursor is on a komorebi workspace where raising
+    /// windows is disabled
+    DisabledWorkspace { workspace: String },
+    /// The eligibility command is still deciding, or ran too recently to be
+    /// asked again
+    CommandThrottled,
+    /// The eligibility command vetoed the window
+    VetoedByCommand,
+    /// A plugin vetoed the window
+    VetoedByPlugin,
+    /// The eligibility script vetoed the window
+    VetoedByScript,
+    /// The window is waiting for the delay or hysteresis to pass
+    Delayed,
+}
+
+impl DecisionReason {
+    /// Returns true if the window under the cursor is raised for this reason
+    pub fn is_raise(&self) -> bool {
+        matches!(
+            self,
+            DecisionReason::Eligible
+                | DecisionReason::CacheHitEligible
+                | DecisionReason::StrictDesktop
+                | DecisionReason::ForcedByPlugin
+                | DecisionReason::ForcedByScript
+        )
+    }
+}
+
+impl Display for DecisionReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecisionReason::Eligible => write!(f, "both windows are eligible"),
+            DecisionReason::CacheHitEligible => {
+                write!(f, "both windows were found as eligible in the cache")
+            }
+            DecisionReason::StrictDesktop => write!(f, "the desktop is focused in strict mode"),
+            DecisionReason::ForcedByPlugin => write!(f, "a plugin forced the raise"),
+            DecisionReason::ForcedByScript => write!(f, "the eligibility script forced the raise"),
+            DecisionReason::NoWindow => write!(f, "a window could not be found"),
+            DecisionReason::AlreadyFocused => write!(f, "the window already has focus"),
+            DecisionReason::NoRootWindow => write!(f, "the top-level window could not be found"),
+            DecisionReason::Undone => write!(f, "the raise of the window was undone"),
+            DecisionReason::Jitter => {
+                write!(
+                    f,
+                    "the cursor is jittering across the boundary of the window"
+                )
+            }
+            DecisionReason::SameRoot => write!(f, "the window belongs to the foreground window"),
+            DecisionReason::OwnWindow => write!(f, "one of the windows belongs to masir"),
+            DecisionReason::Unresponsive => write!(f, "the window did not respond recently"),
+            DecisionReason::TooSmall => write!(f, "the window is smaller than the minimum size"),
+            DecisionReason::Hidden => write!(f, "the window is hidden, minimized or off screen"),
+            DecisionReason::Transient => write!(f, "the window is a transient surface"),
+            DecisionReason::SameProcess => write!(f, "both windows belong to the same process"),
+            DecisionReason::PairedApp => write!(f, "both windows belong to the same application"),
+            DecisionReason::ProtectedForeground { rule } => {
+                write!(f, "the foreground window is protected by {rule}")
+            }
+            DecisionReason::PlayingAudio => write!(f, "the foreground window is playing audio"),
+            DecisionReason::Desktop => write!(f, "the desktop is only focused in strict mode"),
+            DecisionReason::CacheHitIneligible => {
+                write!(f, "a window was found as ineligible in the cache")
+            }
+            DecisionReason::HwndsUnavailable => write!(f, "the hwnds files could not be read"),
+            DecisionReason::NotManaged { window } => {
+                write!(f, "{window} is not in the hwnds files")
+            }
+            DecisionReason::FilteredStyle { window } => write!(f, "{window} has a filtered style"),
+            DecisionReason::Ignored { window, rule } => write!(f, "{window} is ignored by {rule}"),
+            DecisionReason::BehindMonocle => write!(f, "the window is behind a monocle container"),
+            DecisionReason::Floating => write!(f, "the window is floating in komorebi"),
+            DecisionReason::RequiresClick { rule } => {
+                write!(
+                    f,
+                    "the window is only focused by clicking it because of {rule}"
+                )
+            }
+            DecisionReason::QuietHours => write!(f, "it is quiet hours"),
+            DecisionReason::DisabledMonitor { monitor } => {
+                write!(f, "the window is on the disabled monitor {monitor}")
+            }
+            DecisionReason::DisabledWorkspace { workspace } => {
+                write!(f, "the window is on the disabled workspace {workspace}")
+            }
+            DecisionReason::CommandThrottled => {
+                write!(f, "waiting for the eligibility command")
+            }
+            DecisionReason::VetoedByCommand => {
+                write!(f, "the eligibility command vetoed the raise")
+            }
+            DecisionReason::VetoedByPlugin => write!(f, "a plugin vetoed the raise"),
+            DecisionReason::VetoedByScript => write!(f, "the eligibility script vetoed the raise"),
+            DecisionReason::Delayed => write!(f, "waiting for the delay or hysteresis"),
+        }
+    }
+}
+
+/// The outcome of evaluating a single mouse movement
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Decision {
+    /// The top-level window under the cursor, if it could be found
+    pub cursor: Option<Hwnd>,
+    /// The window which had focus, if it could be found
+    pub foreground: Option<Hwnd>,
+    pub reason: DecisionReason,
+}
+
+impl Decision {
+    /// Returns the window to raise, if the decision was to raise one
+    pub fn raised(&self) -> Option<Hwnd> {
+        self.cursor.filter(|_| self.reason.is_raise())
+    }
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let verb = if self.reason.is_raise() {
+            "raising"
+        } else {
+            "skipping"
+        };
+
+        match self.cursor {
+            Some(cursor) => write!(f, "{verb} hwnd {cursor}: {}", self.reason),
+            None => write!(f, "{verb}: {}", self.reason),
+        }
+    }
+}
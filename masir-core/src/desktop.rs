@@ -0,0 +1,45 @@
+//! Tracks switches between desktops, such as to the secure desktop for UAC
+//! prompts and Ctrl+Alt+Del, while which mouse events (including button
+//! releases) are never received
+
+use crate::winapi;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::Accessibility::HWINEVENTHOOK;
+use windows::Win32::UI::WindowsAndMessaging::EVENT_SYSTEM_DESKTOPSWITCH;
+
+static SWITCHED: AtomicBool = AtomicBool::new(false);
+
+/// Returns true if the input desktop has been switched since this was last
+/// called
+pub fn take_switched() -> bool {
+    SWITCHED.swap(false, Ordering::SeqCst)
+}
+
+/// Spawns a thread which hooks switches of the input desktop
+pub fn listen_for_desktop_switches() {
+    std::thread::spawn(|| {
+        match winapi::set_win_event_hook(
+            EVENT_SYSTEM_DESKTOPSWITCH,
+            EVENT_SYSTEM_DESKTOPSWITCH,
+            Some(on_desktop_switch),
+        ) {
+            Ok(_hook) => winapi::pump_messages(),
+            Err(error) => tracing::error!("failed to listen for desktop switches: {error}"),
+        }
+    });
+}
+
+unsafe extern "system" fn on_desktop_switch(
+    _hook: HWINEVENTHOOK,
+    _event: u32,
+    _hwnd: HWND,
+    _id_object: i32,
+    _id_child: i32,
+    _id_event_thread: u32,
+    _event_time: u32,
+) {
+    tracing::info!("input desktop switched");
+    SWITCHED.store(true, Ordering::SeqCst);
+}
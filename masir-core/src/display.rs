@@ -0,0 +1,48 @@
+//! Tracks changes to the display configuration, such as docking or undocking
+//! a laptop, plugging in a monitor or changing a resolution, after which
+//! anything derived from the monitors which windows were on is stale
+
+use crate::winapi;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::Foundation::LPARAM;
+use windows::Win32::Foundation::LRESULT;
+use windows::Win32::Foundation::WPARAM;
+use windows::Win32::UI::WindowsAndMessaging::DefWindowProcW;
+use windows::Win32::UI::WindowsAndMessaging::WM_DISPLAYCHANGE;
+
+static CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Returns true if the display configuration has changed since this was last
+/// called
+pub fn take_changed() -> bool {
+    CHANGED.swap(false, Ordering::SeqCst)
+}
+
+/// Spawns a thread which receives display change notifications through a
+/// hidden window, since they are only broadcast to top-level windows
+pub fn listen_for_display_changes() {
+    std::thread::spawn(
+        || match winapi::create_hidden_window("masir_display", Some(window_proc)) {
+            Ok(_) => winapi::pump_messages(),
+            Err(error) => tracing::error!("failed to listen for display changes: {error}"),
+        },
+    );
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg != WM_DISPLAYCHANGE {
+        return DefWindowProcW(hwnd, msg, wparam, lparam);
+    }
+
+    tracing::info!("display configuration changed");
+    CHANGED.store(true, Ordering::SeqCst);
+
+    LRESULT(0)
+}
@@ -0,0 +1,406 @@
+//! Pure functions deciding whether a window is eligible to be raised or to
+//! lose focus
+
+use crate::config::has_match;
+use crate::config::ApplicationIdentifier;
+use crate::config::IgnoreRule;
+use crate::config::MatchingStrategy;
+use crate::hwnd::Hwnd;
+use crate::window_system::WindowSystem;
+use std::borrow::Cow;
+
+pub const CLASS_IGNORELIST: [(&str, MatchingStrategy); 13] = [
+    ("SHELLDLL_DefView", MatchingStrategy::Equals), // desktop window
+    ("Shell_TrayWnd", MatchingStrategy::Equals),    // tray
+    ("TrayNotifyWnd", MatchingStrategy::Equals),    // tray
+    ("MSTaskSwWClass", MatchingStrategy::Equals),   // start bar icons
+    ("Windows.UI.Core.CoreWindow", MatchingStrategy::Equals), // start menu
+    ("XamlExplorerHostIslandWindow", MatchingStrategy::Equals), // task switcher
+    ("ForegroundStaging", MatchingStrategy::Equals), // also task switcher
+    ("Flow.Launcher", MatchingStrategy::Contains),
+    ("PowerToys.PowerLauncher", MatchingStrategy::Contains),
+    ("IME", MatchingStrategy::Equals), // input method editor
+    ("MSCTFIME", MatchingStrategy::StartsWith), // ime composition and candidate windows
+    ("CiceroUIWndFrame", MatchingStrategy::Equals), // text services framework ui
+    ("Microsoft.IME", MatchingStrategy::StartsWith), // modern ime candidate windows
+];
+
+/// Game and GPU overlays, which are invisible or topmost windows that would
+/// otherwise take focus whenever the cursor passes over them, ignored unless
+/// ignore_overlays is disabled
+pub const OVERLAY_RULES: [(ApplicationIdentifier, &str); 8] = [
+    (ApplicationIdentifier::Exe, "NVIDIA Share.exe"), // geforce experience and shadowplay
+    (ApplicationIdentifier::Exe, "NVIDIA Overlay.exe"), // nvidia app
+    (ApplicationIdentifier::Class, "CEF-OSC-WIDGET"), // nvidia overlay windows
+    (ApplicationIdentifier::Exe, "GameOverlayUI.exe"), // steam overlay
+    (ApplicationIdentifier::Exe, "RTSS.exe"),         // rivatuner statistics server
+    (ApplicationIdentifier::Title, "Discord Overlay"),
+    (ApplicationIdentifier::Title, "Medal Overlay"),
+    (ApplicationIdentifier::Exe, "GameBar.exe"), // xbox game bar widgets
+];
+
+/// Processes which host the Windows shell, whose windows (the taskbar, start
+/// menu, search, notification centre and so on) are treated as shell surfaces
+/// regardless of their class, since new ones appear with every release
+pub const SHELL_PROCESSES: [&str; 5] = [
+    "explorer.exe",
+    "ShellExperienceHost.exe",
+    "StartMenuExperienceHost.exe",
+    "SearchHost.exe",
+    "SearchApp.exe",
+];
+
+/// Classes of windows created by shell processes which are regular windows
+/// rather than shell surfaces
+const SHELL_WINDOW_CLASSES: [&str; 2] = [
+    "CabinetWClass", // file explorer
+    "#32770",        // dialogs, e.g. file copy progress
+];
+
+/// Returns true if the window matches the built-in class ignorelist, belongs to
+/// a shell process, or matches any of the user-defined ignore rules, where
+/// built-in entries can be disabled by their class or process name
+pub fn is_ignored<W: WindowSystem>(
+    window_system: &W,
+    hwnd: Hwnd,
+    class: &str,
+    ignore_rules: &[IgnoreRule],
+    disabled_builtin_ignores: &[String],
+) -> bool {
+    ignored_by(
+        window_system,
+        hwnd,
+        class,
+        ignore_rules,
+        disabled_builtin_ignores,
+    )
+    .is_some()
+}
+
+/// Returns the id of the built-in ignorelist entry, shell process or ignore
+/// rule which the window is ignored by, if any
+pub fn ignored_by<W: WindowSystem>(
+    window_system: &W,
+    hwnd: Hwnd,
+    class: &str,
+    ignore_rules: &[IgnoreRule],
+    disabled_builtin_ignores: &[String],
+) -> Option<String> {
+    let is_enabled = |id: &str| {
+        !disabled_builtin_ignores
+            .iter()
+            .any(|disabled| disabled.eq_ignore_ascii_case(id))
+    };
+
+    if let Some((id, _)) = CLASS_IGNORELIST
+        .iter()
+        .filter(|(id, _)| is_enabled(id))
+        .find(|(id, strategy)| has_match(class, id, strategy))
+    {
+        return Some(id.to_string());
+    }
+
+    if !SHELL_WINDOW_CLASSES.contains(&class) {
+        if let Ok(exe) = window_system.exe(hwnd) {
+            if let Some(process) = SHELL_PROCESSES
+                .iter()
+                .find(|process| is_enabled(process) && exe.eq_ignore_ascii_case(process))
+            {
+                return Some(process.to_string());
+            }
+        }
+    }
+
+    matching_rule(window_system, hwnd, class, ignore_rules).map(|rule| rule.id().to_string())
+}
+
+/// Returns true if the window matches any of the given rules
+pub fn matches_any<W: WindowSystem>(
+    window_system: &W,
+    hwnd: Hwnd,
+    class: &str,
+    rules: &[IgnoreRule],
+) -> bool {
+    matching_rule(window_system, hwnd, class, rules).is_some()
+}
+
+/// Returns the first of the given rules which the window matches
+pub fn matching_rule<'a, W: WindowSystem>(
+    window_system: &W,
+    hwnd: Hwnd,
+    class: &str,
+    rules: &'a [IgnoreRule],
+) -> Option<&'a IgnoreRule> {
+    rules.iter().find(|rule| {
+        let value = match rule.kind() {
+            ApplicationIdentifier::Class => Some(Cow::Borrowed(class)),
+            ApplicationIdentifier::Exe => window_system.exe(hwnd).ok().map(Cow::Owned),
+            ApplicationIdentifier::Title => window_system.title(hwnd).ok().map(Cow::Owned),
+        };
+
+        value.is_some_and(|value| rule.is_match(&value))
+    })
+}
+
+/// Returns true if the window appears in the contents of a tiling window
+/// manager's hwnds file
+pub fn is_managed(raw_hwnds: &str, hwnd: Hwnd) -> bool {
+    raw_hwnds.contains(&hwnd.to_string())
+}
+
+/// Returns true if the class belongs to the desktop window which hosts the
+/// wallpaper and desktop icons
+pub fn is_desktop(class: &str) -> bool {
+    matches!(class, "Progman" | "WorkerW")
+}
+
+/// Returns true if the two classes are known to belong to different windows
+/// of the same application (e.g. the Steam client's web view and its SDL
+/// frame), in which case moving between them should not trigger a raise
+pub fn is_same_application(cursor_root_class: &str, foreground_class: &str) -> bool {
+    cursor_root_class == "Chrome_RenderWidgetHostHWND" && foreground_class == "SDL_app"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MatchingRule;
+    use crate::config::ProfileConfig;
+    use crate::window_system::MockWindow;
+    use crate::window_system::MockWindowSystem;
+
+    fn ignore_rules(rules: Vec<MatchingRule>) -> Vec<IgnoreRule> {
+        let config = crate::config::Config {
+            base: ProfileConfig {
+                ignore_rules: Some(rules),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        config.settings(None).unwrap().ignore_rules
+    }
+
+    #[test]
+    fn matching_strategies() {
+        assert!(has_match(
+            "Shell_TrayWnd",
+            "Shell_TrayWnd",
+            &MatchingStrategy::Equals
+        ));
+        assert!(!has_match(
+            "Shell_TrayWnd",
+            "Shell",
+            &MatchingStrategy::Equals
+        ));
+        assert!(has_match(
+            "Shell_TrayWnd",
+            "Shell",
+            &MatchingStrategy::StartsWith
+        ));
+        assert!(has_match(
+            "Shell_TrayWnd",
+            "TrayWnd",
+            &MatchingStrategy::EndsWith
+        ));
+        assert!(has_match(
+            "Flow.Launcher.exe",
+            "Flow.Launcher",
+            &MatchingStrategy::Contains
+        ));
+        assert!(has_match(
+            "Shell_TrayWnd",
+            "^Shell_.*Wnd$",
+            &MatchingStrategy::Regex
+        ));
+        assert!(!has_match(
+            "Shell_TrayWnd",
+            "^Tray",
+            &MatchingStrategy::Regex
+        ));
+    }
+
+    #[test]
+    fn builtin_ignorelist() {
+        let window_system = MockWindowSystem::default();
+
+        assert!(is_ignored(
+            &window_system,
+            Hwnd(1),
+            "Shell_TrayWnd",
+            &[],
+            &[]
+        ));
+        assert!(is_ignored(
+            &window_system,
+            Hwnd(1),
+            "HwndWrapper[Flow.Launcher;;]",
+            &[],
+            &[]
+        ));
+        assert!(!is_ignored(
+            &window_system,
+            Hwnd(1),
+            "Chrome_WidgetWin_1",
+            &[],
+            &[]
+        ));
+    }
+
+    #[test]
+    fn ime_windows() {
+        let window_system = MockWindowSystem::default();
+
+        for class in [
+            "IME",
+            "MSCTFIME UI",
+            "MSCTFIME Composition",
+            "CiceroUIWndFrame",
+            "Microsoft.IME.UIManager.CandidateWindow.Host",
+        ] {
+            assert!(
+                is_ignored(&window_system, Hwnd(1), class, &[], &[]),
+                "{class}"
+            );
+        }
+    }
+
+    #[test]
+    fn disabled_builtin_ignores() {
+        let window_system = MockWindowSystem::default();
+        let disabled = vec!["Flow.Launcher".to_string()];
+
+        assert!(!is_ignored(
+            &window_system,
+            Hwnd(1),
+            "HwndWrapper[Flow.Launcher;;]",
+            &[],
+            &disabled
+        ));
+        assert!(is_ignored(
+            &window_system,
+            Hwnd(1),
+            "Shell_TrayWnd",
+            &[],
+            &disabled
+        ));
+    }
+
+    #[test]
+    fn shell_surfaces() {
+        let window_system = MockWindowSystem::default()
+            .with_window(
+                Hwnd(1),
+                MockWindow::new("Shell_SecondaryTrayWnd").with_exe("explorer.exe"),
+            )
+            .with_window(
+                Hwnd(2),
+                MockWindow::new("Windows.UI.Core.CoreWindow").with_exe("SearchHost.exe"),
+            )
+            .with_window(
+                Hwnd(3),
+                MockWindow::new("CabinetWClass").with_exe("explorer.exe"),
+            );
+
+        assert!(is_ignored(
+            &window_system,
+            Hwnd(1),
+            "Shell_SecondaryTrayWnd",
+            &[],
+            &[]
+        ));
+        assert!(is_ignored(
+            &window_system,
+            Hwnd(2),
+            "Windows.UI.Core.CoreWindow",
+            &[],
+            &[]
+        ));
+        assert!(!is_ignored(
+            &window_system,
+            Hwnd(3),
+            "CabinetWClass",
+            &[],
+            &[]
+        ));
+
+        let disabled = vec!["explorer.exe".to_string()];
+        assert!(!is_ignored(
+            &window_system,
+            Hwnd(1),
+            "Shell_SecondaryTrayWnd",
+            &[],
+            &disabled
+        ));
+    }
+
+    #[test]
+    fn user_ignore_rules() {
+        let window_system = MockWindowSystem::default().with_window(
+            Hwnd(1),
+            MockWindow::new("Chrome_WidgetWin_1")
+                .with_exe("Discord.exe")
+                .with_title("#general - Discord"),
+        );
+
+        let rules = ignore_rules(vec![MatchingRule {
+            kind: ApplicationIdentifier::Exe,
+            id: "Discord.exe".to_string(),
+            matching_strategy: MatchingStrategy::Equals,
+        }]);
+        assert!(is_ignored(
+            &window_system,
+            Hwnd(1),
+            "Chrome_WidgetWin_1",
+            &rules,
+            &[]
+        ));
+
+        let rules = ignore_rules(vec![MatchingRule {
+            kind: ApplicationIdentifier::Title,
+            id: "- Discord$".to_string(),
+            matching_strategy: MatchingStrategy::Regex,
+        }]);
+        assert!(is_ignored(
+            &window_system,
+            Hwnd(1),
+            "Chrome_WidgetWin_1",
+            &rules,
+            &[]
+        ));
+
+        let rules = ignore_rules(vec![MatchingRule {
+            kind: ApplicationIdentifier::Exe,
+            id: "firefox.exe".to_string(),
+            matching_strategy: MatchingStrategy::Equals,
+        }]);
+        assert!(!is_ignored(
+            &window_system,
+            Hwnd(1),
+            "Chrome_WidgetWin_1",
+            &rules,
+            &[]
+        ));
+    }
+
+    #[test]
+    fn managed_hwnds() {
+        let raw_hwnds = "[1234, 5678]";
+
+        assert!(is_managed(raw_hwnds, Hwnd(1234)));
+        assert!(is_managed(raw_hwnds, Hwnd(5678)));
+        assert!(!is_managed(raw_hwnds, Hwnd(4321)));
+    }
+
+    #[test]
+    fn steam_windows_are_the_same_application() {
+        assert!(is_same_application(
+            "Chrome_RenderWidgetHostHWND",
+            "SDL_app"
+        ));
+        assert!(!is_same_application(
+            "SDL_app",
+            "Chrome_RenderWidgetHostHWND"
+        ));
+    }
+}
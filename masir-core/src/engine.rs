@@ -0,0 +1,2627 @@
+//! The decision engine which runs on every mouse movement
+
+use crate::cache::Caches;
+use crate::command::EligibilityCommand;
+use crate::config::GuestPolicy;
+use crate::config::Settings;
+use crate::crash;
+use crate::decision::Decision;
+use crate::decision::DecisionReason;
+use crate::decision::Role;
+use crate::eligibility::ignored_by;
+use crate::eligibility::is_desktop;
+use crate::eligibility::is_ignored;
+use crate::eligibility::is_managed;
+use crate::eligibility::is_same_application;
+use crate::eligibility::matches_any;
+use crate::eligibility::matching_rule;
+use crate::events;
+use crate::events::Event;
+use crate::hwnd::Hwnd;
+use crate::komorebi;
+use crate::komorebi::FloatingPolicy;
+use crate::komorebi::Snapshot;
+use crate::metrics;
+use crate::metrics::Cache;
+use crate::metrics::Counter;
+use crate::plugin::Plugin;
+use crate::raise::FocusDelegate;
+use crate::raise::RaiseStrategy;
+use crate::raise::RaiseTimedOut;
+use crate::raise::StrategyMemory;
+use crate::raise_hook;
+use crate::raise_log::Outcome;
+use crate::raise_log::RaiseLog;
+use crate::raise_log::RaiseRecord;
+use crate::script::EligibilityScript;
+use crate::script::ScriptWindow;
+use crate::status;
+use crate::winapi;
+use crate::window_system::WindowSystem;
+use color_eyre::eyre::eyre;
+use color_eyre::Report;
+use color_eyre::Result;
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+/// The target of the spans around each stage of evaluating a mouse movement,
+/// which can be enabled to see where latency goes on slow systems
+pub const PROFILE_TARGET: &str = "masir::profile";
+
+/// A window which will be raised once the cursor has remained over it for the
+/// configured delay and travelled the configured distance inside it
+struct PendingRaise {
+    hwnd: Hwnd,
+    since: Instant,
+    entered_at: Option<(i32, i32)>,
+}
+
+/// Decides which window, if any, should be raised whenever the mouse moves
+pub struct Engine<W: WindowSystem> {
+    window_system: W,
+    hwnds: Vec<PathBuf>,
+    settings: Settings,
+    caches: Caches,
+    raise_strategies: StrategyMemory,
+    pending_raise: Option<PendingRaise>,
+    // the root window under the cursor and when the cursor entered it
+    entered: Option<(Hwnd, Instant)>,
+    // where the cursor was at the last movement, to detect it being warped
+    last_cursor_pos: Option<(i32, i32)>,
+    // whether the system was on battery when the power source was last checked
+    on_battery: Option<(bool, Instant)>,
+    last_evaluation: Option<Instant>,
+    // the window which was raised last and the window which had focus before
+    last_raise: Option<(Hwnd, Hwnd)>,
+    last_raised_at: Option<Instant>,
+    // a window whose raise was undone, which isn't raised again until the
+    // cursor leaves it
+    undone: Option<Hwnd>,
+    // windows which didn't respond to being raised and when they timed out
+    unresponsive: HashMap<Hwnd, Instant>,
+    // whether it was quiet hours when the schedule was last checked
+    quiet_hours: Option<(bool, Instant)>,
+    // the executables which were playing audio when audio sessions were last checked
+    audio_playing: Option<(Vec<String>, Instant)>,
+    // how komorebi had laid out windows when it was last asked
+    komorebi: Option<(Snapshot, Instant)>,
+    // how long after the last evaluation a movement which was skipped to save
+    // battery or to catch up with a backlog, or which is waiting for the
+    // eligibility command, is due
+    deferred: Option<Duration>,
+    // movements are sampled until then because the event queue fell behind
+    sampling_until: Option<Instant>,
+    // the window which was last focused on each monitor, keyed by device name
+    last_focused: HashMap<String, Hwnd>,
+    last_foreground: Option<Hwnd>,
+    raise_log: Option<RaiseLog>,
+    script: Option<EligibilityScript>,
+    command: Option<EligibilityCommand>,
+    plugins: Vec<Plugin>,
+    last_decision: Option<Decision>,
+}
+
+impl<W: WindowSystem> Engine<W> {
+    /// How long a window which is not in the hwnds file is cached as ineligible
+    const INELIGIBLE_TTL: Duration = Duration::from_millis(1500);
+    /// How long the visibility of a window is cached, which is kept short
+    /// since windows are minimized and restored all the time
+    const VISIBILITY_TTL: Duration = Duration::from_millis(500);
+    /// How many times a failed raise is retried while the cursor remains over the window
+    const RAISE_RETRIES: u32 = 2;
+    /// The delay before the first retry, doubled for each subsequent retry
+    const RAISE_RETRY_BACKOFF: Duration = Duration::from_millis(10);
+    /// How long a window which didn't respond to being raised is skipped for
+    const UNRESPONSIVE_TTL: Duration = Duration::from_secs(10);
+    /// How often the power source is checked
+    const POWER_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+    /// How many times longer window properties are cached while on battery
+    const BATTERY_TTL_FACTOR: u32 = 4;
+    /// How often the local time is checked against the quiet hours
+    const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+    /// How often audio sessions are checked while audio protection is enabled
+    const AUDIO_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+    /// How often komorebi is asked for its state
+    const KOMOREBI_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+    /// How often movements are evaluated while catching up with a backlog
+    const SAMPLING_INTERVAL: Duration = Duration::from_millis(50);
+    /// How long movements are sampled for after a backlog was last detected
+    const SAMPLING_DURATION: Duration = Duration::from_secs(2);
+    /// How often the eligibility command is checked for a verdict while
+    /// waiting for one
+    const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    pub fn new(window_system: W, hwnds: Vec<PathBuf>, settings: Settings) -> Self {
+        let raise_log = settings
+            .raise_log
+            .as_deref()
+            .and_then(|path| match RaiseLog::open(path) {
+                Ok(raise_log) => Some(raise_log),
+                Err(error) => {
+                    tracing::error!("{error:#}");
+                    None
+                }
+            });
+
+        let script = settings.eligibility_script.as_deref().and_then(|path| {
+            match EligibilityScript::load(path) {
+                Ok(script) => Some(script),
+                Err(error) => {
+                    tracing::error!("{error:#}");
+                    None
+                }
+            }
+        });
+
+        let plugins = settings
+            .plugins
+            .iter()
+            .filter_map(|path| match Plugin::load(path) {
+                Ok(plugin) => {
+                    tracing::info!("loaded plugin {}", plugin.name());
+                    Some(plugin)
+                }
+                Err(error) => {
+                    tracing::error!("{error:#}");
+                    None
+                }
+            })
+            .collect();
+
+        let command = settings
+            .eligibility_command
+            .clone()
+            .map(EligibilityCommand::new);
+
+        Self {
+            window_system,
+            hwnds,
+            raise_strategies: StrategyMemory::new(
+                settings.raise_strategies.clone(),
+                settings.raise_strategy_overrides.clone(),
+            ),
+            settings,
+            caches: Caches::default(),
+            pending_raise: None,
+            entered: None,
+            last_cursor_pos: None,
+            on_battery: None,
+            last_raise: None,
+            last_raised_at: None,
+            undone: None,
+            unresponsive: HashMap::new(),
+            quiet_hours: None,
+            last_evaluation: None,
+            audio_playing: None,
+            komorebi: None,
+            deferred: None,
+            sampling_until: None,
+            last_focused: HashMap::new(),
+            last_foreground: None,
+            raise_log,
+            script,
+            command,
+            plugins,
+            last_decision: None,
+        }
+    }
+
+    /// Forgets everything cached about windows, e.g. after a remote desktop
+    /// connection where window handles may have been recreated
+    pub fn clear_caches(&mut self) {
+        self.caches.clear();
+        self.pending_raise = None;
+        self.entered = None;
+        self.last_foreground = None;
+        self.audio_playing = None;
+        self.komorebi = None;
+        self.unresponsive.clear();
+    }
+
+    /// Forgets everything cached about a window, such as after its styles
+    /// have changed
+    pub fn invalidate(&mut self, hwnd: Hwnd) {
+        self.caches.invalidate(hwnd);
+    }
+
+    /// Refreshes everything derived from the monitors after the display
+    /// configuration has changed, where the last focused windows are keyed by
+    /// whichever monitor they are on now
+    pub fn handle_display_change(&mut self) {
+        self.caches.clear_visibility();
+        self.pending_raise = None;
+        self.entered = None;
+        // the cursor can jump when monitors are rearranged, which shouldn't
+        // be taken for it being warped
+        self.last_cursor_pos = None;
+
+        let windows = std::mem::take(&mut self.last_focused).into_values();
+        for hwnd in windows {
+            if let Ok(monitor) = self.window_system.monitor(hwnd) {
+                self.last_focused.insert(monitor, hwnd);
+            }
+        }
+
+        status::replace_last_focused(self.last_focused.clone().into_iter().collect());
+    }
+
+    /// Forgets everything cached about windows which aren't on the current
+    /// virtual desktop, such as after switching away from the desktop they
+    /// are on, along with a raise which was pending for one of them
+    pub fn forget_other_virtual_desktops(&mut self) {
+        let window_system = &self.window_system;
+        self.caches.retain(|hwnd, info| {
+            window_system.is_on_current_virtual_desktop(info.root.unwrap_or(hwnd))
+        });
+
+        if self
+            .pending_raise
+            .as_ref()
+            .is_some_and(|pending| !window_system.is_on_current_virtual_desktop(pending.hwnd))
+        {
+            self.pending_raise = None;
+        }
+
+        self.entered = None;
+        self.last_foreground = None;
+    }
+
+    /// Evaluates the window under the cursor and raises it if it is eligible
+    ///
+    /// On battery or while catching up with a backlog, movements are evaluated
+    /// at most once per interval, and the last skipped movement is due at
+    /// [`Engine::deferred_until`].
+    pub fn handle_mouse_move(&mut self) {
+        if self.has_teleported() {
+            tracing::debug!("cursor was warped, waiting for it to move again");
+            self.deferred = None;
+            return;
+        }
+
+        if let Some(interval) = self.throttle_interval() {
+            if self
+                .last_evaluation
+                .is_some_and(|last| last.elapsed() < interval)
+            {
+                self.deferred = Some(interval);
+                return;
+            }
+        }
+
+        self.deferred = None;
+        self.last_evaluation = Some(Instant::now());
+
+        let _span = tracing::trace_span!(target: PROFILE_TARGET, "handle_mouse_move").entered();
+        let started = Instant::now();
+        let decision = self.evaluate();
+
+        metrics::increment(Counter::Events);
+        metrics::record_evaluation(started.elapsed());
+
+        // the eligibility command decides in the background, and its verdict
+        // shouldn't have to wait for the cursor to move again
+        if self
+            .last_decision
+            .as_ref()
+            .is_some_and(|decision| decision.reason == DecisionReason::CommandThrottled)
+        {
+            self.deferred = Some(Self::COMMAND_POLL_INTERVAL);
+        }
+
+        let Some(hwnd) = decision else {
+            return;
+        };
+
+        let previous_hwnd = self.window_system.foreground_window().ok();
+        let result = self.raise_with_retries(hwnd);
+
+        if self.raise_log.is_some() {
+            self.log_raise(hwnd, &result, started);
+        }
+
+        match result {
+            Ok(strategy) => {
+                tracing::info!("raised hwnd: {hwnd}");
+                events::emit(Event::Raised(hwnd));
+                metrics::increment(Counter::Raises);
+                if let Some((_, since)) = self.entered.filter(|(entered, _)| *entered == hwnd) {
+                    metrics::record_raise(since.elapsed());
+                }
+
+                tracing::debug!("hwnd {hwnd} was raised using {strategy:?}");
+                self.last_raise = previous_hwnd.map(|previous_hwnd| (hwnd, previous_hwnd));
+                self.last_raised_at = Some(Instant::now());
+                self.remember_focus(hwnd);
+                self.raise_owned_windows(hwnd);
+
+                if self.settings.deep_focus {
+                    if let Err(error) = self.window_system.focus_control_at_cursor(hwnd) {
+                        tracing::warn!("failed to focus the control under the cursor: {error}");
+                    }
+                }
+
+                if !self.settings.on_raise.is_empty() {
+                    let window = self.script_window(hwnd);
+                    raise_hook::run(&self.settings.on_raise, &window);
+                }
+
+                if let Some(sound) = self.focus_sound(hwnd) {
+                    if let Err(error) = winapi::play_sound(sound) {
+                        tracing::warn!("failed to play {}: {error}", sound.display());
+                    }
+                }
+
+                if self.settings.auto_lower {
+                    if let Some(previous_hwnd) = previous_hwnd {
+                        if let Err(error) = self.window_system.lower(previous_hwnd) {
+                            tracing::warn!("failed to lower hwnd {previous_hwnd}: {error}");
+                        }
+                    }
+                }
+            }
+            Err(error) => {
+                tracing::error!("failed to raise hwnd {hwnd}: {error}");
+                metrics::increment(Counter::RaiseFailures);
+
+                if is_timeout(&error) {
+                    tracing::warn!(
+                        "skipping hwnd {hwnd} for {}s since it is unresponsive",
+                        Self::UNRESPONSIVE_TTL.as_secs()
+                    );
+                    self.unresponsive.insert(hwnd, Instant::now());
+                }
+            }
+        }
+    }
+
+    /// Gives focus back to the window which had it before the last raise,
+    /// leaving the raised window alone until the cursor leaves it
+    pub fn undo_last_raise(&mut self) {
+        let Some((raised, previous)) = self.last_raise.take() else {
+            tracing::info!("there is no raise to undo");
+            return;
+        };
+
+        match self.raise(previous) {
+            Ok(_) => {
+                tracing::info!("undid the raise of hwnd {raised}, raised hwnd {previous} again");
+                self.undone = Some(raised);
+                self.pending_raise = None;
+                self.remember_focus(previous);
+            }
+            Err(error) => {
+                tracing::error!("failed to undo the raise of hwnd {raised}: {error}");
+            }
+        }
+    }
+
+    /// Returns when a movement which was skipped to save battery or to catch
+    /// up with a backlog should be evaluated, if there is one
+    pub fn deferred_until(&self) -> Option<Instant> {
+        Some(self.last_evaluation? + self.deferred?)
+    }
+
+    /// Forgets a movement which was skipped to save battery or to catch up
+    /// with a backlog, e.g. because evaluation has since been suspended
+    pub fn clear_deferred(&mut self) {
+        self.deferred = None;
+    }
+
+    /// Samples movements for a while instead of evaluating every one of them,
+    /// returning true if sampling wasn't already in progress
+    pub fn sample_movements(&mut self) -> bool {
+        let started = !self.is_sampling();
+        self.sampling_until = Some(Instant::now() + Self::SAMPLING_DURATION);
+        started
+    }
+
+    fn is_sampling(&self) -> bool {
+        self.sampling_until
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Returns the minimum time between evaluations, if movements are being
+    /// throttled to save battery or to catch up with a backlog
+    fn throttle_interval(&mut self) -> Option<Duration> {
+        let battery = Some(self.settings.battery_interval).filter(|_| self.is_saving_battery());
+        let sampling = Some(Self::SAMPLING_INTERVAL).filter(|_| self.is_sampling());
+
+        battery.max(sampling)
+    }
+
+    /// Returns true if audio protection is enabled and the application of the
+    /// given window is playing audio, checking audio sessions at most every
+    /// second
+    fn is_playing_audio(&mut self, hwnd: Hwnd, class: &str) -> bool {
+        if !self.settings.protect_audio
+            || matches_any(
+                &self.window_system,
+                hwnd,
+                class,
+                &self.settings.protect_audio_exceptions,
+            )
+        {
+            return false;
+        }
+
+        // audio is often played by a different process of the same
+        // application, such as the audio service of a browser
+        let Ok(exe) = self.window_system.exe(hwnd) else {
+            return false;
+        };
+
+        let is_stale = self
+            .audio_playing
+            .as_ref()
+            .is_none_or(|(_, checked)| checked.elapsed() >= Self::AUDIO_CHECK_INTERVAL);
+
+        if is_stale {
+            let exes = self
+                .window_system
+                .audio_playing_exes()
+                .unwrap_or_else(|error| {
+                    tracing::warn!("failed to check audio sessions: {error}");
+                    vec![]
+                });
+
+            self.audio_playing = Some((exes, Instant::now()));
+        }
+
+        self.audio_playing.as_ref().is_some_and(|(exes, _)| {
+            exes.iter()
+                .any(|playing| playing.eq_ignore_ascii_case(&exe))
+        })
+    }
+
+    /// Returns how komorebi has laid out windows, asking komorebi at most
+    /// every 500ms
+    fn komorebi_snapshot(&mut self) -> &Snapshot {
+        let is_stale = self
+            .komorebi
+            .as_ref()
+            .is_none_or(|(_, checked)| checked.elapsed() >= Self::KOMOREBI_CHECK_INTERVAL);
+
+        if is_stale {
+            // the hwnds files may belong to another window manager
+            let snapshot = komorebi::latest_snapshot().unwrap_or_else(|error| {
+                tracing::debug!("failed to query komorebi for its state: {error}");
+                Snapshot::default()
+            });
+
+            self.komorebi = Some((snapshot, Instant::now()));
+        }
+
+        &self
+            .komorebi
+            .as_ref()
+            .expect("komorebi snapshot was just set")
+            .0
+    }
+
+    /// Returns true if the system is on battery and battery saving is enabled,
+    /// checking the power source at most every 30 seconds
+    fn is_saving_battery(&mut self) -> bool {
+        if self.settings.battery_interval.is_zero() {
+            return false;
+        }
+
+        match self.on_battery {
+            Some((on_battery, checked)) if checked.elapsed() < Self::POWER_CHECK_INTERVAL => {
+                on_battery
+            }
+            previous => {
+                let on_battery = self.window_system.is_on_battery();
+                if previous.map(|(on_battery, _)| on_battery) != Some(on_battery) {
+                    tracing::info!(
+                        "running on {}",
+                        if on_battery { "battery" } else { "ac power" }
+                    );
+                }
+
+                self.on_battery = Some((on_battery, Instant::now()));
+                on_battery
+            }
+        }
+    }
+
+    /// Returns true if the cursor went back to the window which had focus
+    /// before the last raise within the jitter window, and hasn't stayed over
+    /// it for that long since, as when it oscillates across a tiling gap
+    fn is_jitter(&self, cursor_root_hwnd: Hwnd, foreground_hwnd: Hwnd) -> bool {
+        let jitter_window = self.settings.jitter_window;
+        if jitter_window.is_zero() {
+            return false;
+        }
+
+        let (Some((raised, previous)), Some(raised_at)) = (self.last_raise, self.last_raised_at)
+        else {
+            return false;
+        };
+
+        let Some((_, entered_at)) = self
+            .entered
+            .filter(|(entered, _)| *entered == cursor_root_hwnd)
+        else {
+            return false;
+        };
+
+        raised == foreground_hwnd
+            && previous == cursor_root_hwnd
+            && entered_at.saturating_duration_since(raised_at) < jitter_window
+            && entered_at.elapsed() < jitter_window
+    }
+
+    /// Returns true if both windows belong to the same process, where a process
+    /// which can't be looked up never matches
+    fn is_same_process(&self, hwnd: Hwnd, other: Hwnd) -> bool {
+        let process_id = self.window_system.process_id(hwnd);
+        process_id != 0 && process_id == self.window_system.process_id(other)
+    }
+
+    /// Returns true if raising a window timed out recently, forgetting windows
+    /// which have been skipped for long enough
+    fn is_unresponsive(&mut self, hwnd: Hwnd) -> bool {
+        self.unresponsive
+            .retain(|_, timed_out| timed_out.elapsed() < Self::UNRESPONSIVE_TTL);
+
+        self.unresponsive.contains_key(&hwnd)
+    }
+
+    /// Returns true if it is currently quiet hours, checking the local time at
+    /// most every 5 seconds
+    fn is_quiet_hours(&mut self) -> bool {
+        if self.settings.quiet_hours.is_empty() {
+            return false;
+        }
+
+        match self.quiet_hours {
+            Some((quiet, checked)) if checked.elapsed() < Self::SCHEDULE_CHECK_INTERVAL => quiet,
+            previous => {
+                let now = self.window_system.local_time();
+                let quiet = self
+                    .settings
+                    .quiet_hours
+                    .iter()
+                    .any(|quiet_hours| quiet_hours.contains(now));
+
+                if previous.map(|(quiet, _)| quiet) != Some(quiet) {
+                    if quiet {
+                        tracing::info!("quiet hours started");
+                    } else if previous.is_some() {
+                        tracing::info!("quiet hours ended");
+                    }
+                }
+
+                self.quiet_hours = Some((quiet, Instant::now()));
+                quiet
+            }
+        }
+    }
+
+    /// Returns the given cache lifetime, extended while saving battery
+    fn ttl(&mut self, ttl: Duration) -> Duration {
+        if self.is_saving_battery() {
+            ttl * Self::BATTERY_TTL_FACTOR
+        } else {
+            ttl
+        }
+    }
+
+    /// Raises a window, retrying with a short backoff for as long as the
+    /// cursor remains over it
+    fn raise_with_retries(&mut self, hwnd: Hwnd) -> Result<RaiseStrategy> {
+        let _span = tracing::trace_span!(target: PROFILE_TARGET, "raise").entered();
+        let mut retries = 0;
+        loop {
+            match self.raise(hwnd) {
+                Ok(strategy) => return Ok(strategy),
+                // a window which timed out would only block the event loop again
+                Err(error) if retries < Self::RAISE_RETRIES && !is_timeout(&error) => {
+                    // transient foreground lock failures often succeed milliseconds later
+                    std::thread::sleep(Self::RAISE_RETRY_BACKOFF * 2u32.pow(retries));
+                    retries += 1;
+
+                    let cursor_root_hwnd = self
+                        .window_system
+                        .window_at_cursor_pos(self.settings.hit_test)
+                        .ok()
+                        .and_then(|cursor_pos_hwnd| self.root_window(cursor_pos_hwnd));
+
+                    if cursor_root_hwnd != Some(hwnd) {
+                        return Err(error);
+                    }
+
+                    tracing::debug!(
+                        "retrying raise of hwnd {hwnd} ({retries}/{}): {error}",
+                        Self::RAISE_RETRIES
+                    );
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    fn log_raise(&mut self, hwnd: Hwnd, result: &Result<RaiseStrategy>, started: Instant) {
+        let latency = started.elapsed();
+        let class = self.class(hwnd);
+        let exe = self.window_system.exe(hwnd).ok();
+
+        let record = RaiseRecord {
+            timestamp: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis(),
+            hwnd,
+            class: class.as_deref(),
+            exe: exe.as_deref(),
+            outcome: if result.is_ok() {
+                Outcome::Raised
+            } else {
+                Outcome::Failed
+            },
+            strategy: result.as_ref().ok().copied(),
+            error: result.as_ref().err().map(|error| error.to_string()),
+            latency_us: latency.as_micros() as u64,
+        };
+
+        if let Some(raise_log) = &mut self.raise_log {
+            if let Err(error) = raise_log.record(&record) {
+                tracing::warn!("failed to write to raise log: {error}");
+            }
+        }
+    }
+
+    /// Returns the window under the cursor if it should be raised
+    pub fn evaluate(&mut self) -> Option<Hwnd> {
+        let decision = self.decide();
+
+        // every movement is decided, so only changes are logged
+        if self.last_decision.as_ref() != Some(&decision) {
+            tracing::debug!("{decision}");
+            events::emit(Event::Decision(decision.clone()));
+        }
+
+        let raised = decision.raised();
+        status::set_last_decision(&decision);
+        self.last_decision = Some(decision);
+
+        raised
+    }
+
+    /// Returns the last decision on whether to raise the window under the
+    /// cursor
+    pub fn last_decision(&self) -> Option<&Decision> {
+        self.last_decision.as_ref()
+    }
+
+    /// Decides whether the given window would be raised if the cursor came
+    /// to rest over it now, and why, applying the same checks as for the
+    /// window under the cursor
+    ///
+    /// This waits for the eligibility command if it has to be asked, so that
+    /// tools evaluating windows outside of the event loop get a final answer.
+    pub fn evaluate_window(&mut self, hwnd: Hwnd) -> DecisionReason {
+        if self.is_quiet_hours() {
+            return DecisionReason::QuietHours;
+        }
+
+        let Ok(foreground_hwnd) = self.window_system.foreground_window() else {
+            return DecisionReason::NoWindow;
+        };
+
+        if self.is_own_window(foreground_hwnd) {
+            return DecisionReason::OwnWindow;
+        }
+
+        if hwnd == foreground_hwnd {
+            return DecisionReason::AlreadyFocused;
+        }
+
+        let Some(root_hwnd) = self.root_window(hwnd) else {
+            return DecisionReason::NoRootWindow;
+        };
+
+        if root_hwnd == foreground_hwnd {
+            return DecisionReason::SameRoot;
+        }
+
+        loop {
+            match self.judge(root_hwnd, foreground_hwnd) {
+                (DecisionReason::CommandThrottled, _) => {
+                    std::thread::sleep(Self::COMMAND_POLL_INTERVAL);
+                }
+                (reason, _) => return reason,
+            }
+        }
+    }
+
+    /// Decides whether to raise the window under the cursor, and why
+    fn decide(&mut self) -> Decision {
+        // clear our caches every 10 minutes
+        if self.caches.clear_if_older_than(Caches::MAX_AGE) {
+            tracing::info!("cleared caches, cache age was >10 minutes");
+        }
+
+        if self.is_quiet_hours() {
+            return Decision {
+                cursor: None,
+                foreground: None,
+                reason: DecisionReason::QuietHours,
+            };
+        }
+
+        let hit_test = tracing::trace_span!(target: PROFILE_TARGET, "hit_test").entered();
+        let (Ok(cursor_pos_hwnd), Ok(foreground_hwnd)) = (
+            self.window_system
+                .window_at_cursor_pos(self.settings.hit_test),
+            self.window_system.foreground_window(),
+        ) else {
+            return Decision {
+                cursor: None,
+                foreground: None,
+                reason: DecisionReason::NoWindow,
+            };
+        };
+        drop(hit_test);
+
+        let skip_unrooted = |reason| Decision {
+            cursor: None,
+            foreground: Some(foreground_hwnd),
+            reason,
+        };
+
+        // masir's own windows never lose focus and are kept out of the caches,
+        // so that any ui it shows can't cause a focus loop
+        if self.is_own_window(foreground_hwnd) {
+            return skip_unrooted(DecisionReason::OwnWindow);
+        }
+
+        // focus may also have changed through the keyboard or a click
+        self.remember_focus(foreground_hwnd);
+
+        if cursor_pos_hwnd == foreground_hwnd {
+            self.entered = None;
+            self.undone = None;
+            return skip_unrooted(DecisionReason::AlreadyFocused);
+        }
+
+        let Some(cursor_root_hwnd) = self.root_window(cursor_pos_hwnd) else {
+            return skip_unrooted(DecisionReason::NoRootWindow);
+        };
+
+        let decided = |reason| Decision {
+            cursor: Some(cursor_root_hwnd),
+            foreground: Some(foreground_hwnd),
+            reason,
+        };
+
+        // a raise which was undone isn't repeated until the cursor leaves the
+        // window which was raised
+        if self.undone == Some(cursor_root_hwnd) {
+            return decided(DecisionReason::Undone);
+        }
+
+        self.undone = None;
+
+        if cursor_root_hwnd == foreground_hwnd {
+            self.entered = None;
+            return decided(DecisionReason::SameRoot);
+        }
+
+        if self.entered.map(|(entered, _)| entered) != Some(cursor_root_hwnd) {
+            self.entered = Some((cursor_root_hwnd, Instant::now()));
+        }
+
+        let (mut reason, delay) = self.judge(cursor_root_hwnd, foreground_hwnd);
+
+        if reason.is_raise() && self.after_delay(cursor_root_hwnd, delay).is_none() {
+            reason = DecisionReason::Delayed;
+        }
+
+        decided(reason)
+    }
+
+    /// Decides whether a top-level window should be raised in place of the
+    /// foreground window, along with how long the cursor has to rest over it
+    /// first
+    fn judge(
+        &mut self,
+        cursor_root_hwnd: Hwnd,
+        foreground_hwnd: Hwnd,
+    ) -> (DecisionReason, Duration) {
+        if self.is_own_window(cursor_root_hwnd) {
+            return (DecisionReason::OwnWindow, Duration::ZERO);
+        }
+
+        if self.is_unresponsive(cursor_root_hwnd) {
+            return (DecisionReason::Unresponsive, Duration::ZERO);
+        }
+
+        if self.is_too_small(cursor_root_hwnd) {
+            return (DecisionReason::TooSmall, Duration::ZERO);
+        }
+
+        // stale handles in the caches can otherwise point at windows which
+        // can't meaningfully receive focus
+        if !self.is_visible(cursor_root_hwnd) {
+            return (DecisionReason::Hidden, Duration::ZERO);
+        }
+
+        // not cached, since menus and dropdowns come and go and windows can be
+        // created with no size before being resized
+        if self.window_system.is_transient(cursor_root_hwnd) {
+            return (DecisionReason::Transient, Duration::ZERO);
+        }
+
+        let is_paired = self.caches.is_paired(cursor_root_hwnd, foreground_hwnd);
+        metrics::record_cache_lookup(Cache::Pair, is_paired);
+
+        if is_paired {
+            return (DecisionReason::PairedApp, Duration::ZERO);
+        }
+
+        if self.settings.group_by_process && self.is_same_process(cursor_root_hwnd, foreground_hwnd)
+        {
+            return (DecisionReason::SameProcess, Duration::ZERO);
+        }
+
+        // check our class cache to avoid syscalls
+        let cursor_root_class = self.class(cursor_root_hwnd);
+        let foreground_class = self.class(foreground_hwnd);
+        crash::record_window(cursor_root_hwnd, cursor_root_class.clone());
+
+        let _span = tracing::trace_span!(target: PROFILE_TARGET, "eligibility").entered();
+
+        // not cached, since titles change and the foreground window is only
+        // looked up once per evaluation
+        let protected_by = foreground_class.as_deref().and_then(|class| {
+            matching_rule(
+                &self.window_system,
+                foreground_hwnd,
+                class,
+                &self.settings.protect_rules,
+            )
+        });
+
+        if let Some(rule) = protected_by {
+            return (
+                DecisionReason::ProtectedForeground {
+                    rule: rule.id().to_string(),
+                },
+                Duration::ZERO,
+            );
+        }
+
+        if let Some(foreground_class) = &foreground_class {
+            if self.is_playing_audio(foreground_hwnd, foreground_class) {
+                return (DecisionReason::PlayingAudio, Duration::ZERO);
+            }
+        }
+
+        if cursor_root_class.as_deref().is_some_and(is_desktop) {
+            // in strict mode the desktop takes focus so that keyboard input stops
+            // flowing into a window which has visibly been left, unless that
+            // window is one which should never lose focus
+            let foreground_is_ignored = foreground_class.as_deref().is_some_and(|class| {
+                is_ignored(
+                    &self.window_system,
+                    foreground_hwnd,
+                    class,
+                    &self.settings.ignore_rules,
+                    &self.settings.disabled_builtin_ignores,
+                )
+            });
+
+            if !self.settings.strict || foreground_is_ignored {
+                return (DecisionReason::Desktop, Duration::ZERO);
+            }
+
+            return (DecisionReason::StrictDesktop, self.settings.delay);
+        }
+
+        if let (Some(cursor_root_class), Some(foreground_class)) =
+            (&cursor_root_class, &foreground_class)
+        {
+            // steam fixes - populate the hwnd pair cache if necessary
+            if is_same_application(cursor_root_class, foreground_class) {
+                self.caches.entry(cursor_root_hwnd).pair = Some(foreground_hwnd);
+                return (DecisionReason::PairedApp, Duration::ZERO);
+            }
+        }
+
+        // classes are only tested against the ignore rules when both are known
+        let classes_are_known = cursor_root_class.is_some() && foreground_class.is_some();
+        let cursor_root_class = cursor_root_class.filter(|_| classes_are_known);
+        let foreground_class = foreground_class.filter(|_| classes_are_known);
+
+        // check our eligibility caches
+        let mut reason = if let Some(are_eligible) =
+            self.caches.are_eligible(cursor_root_hwnd, foreground_hwnd)
+        {
+            metrics::record_cache_lookup(Cache::Eligibility, true);
+
+            if are_eligible {
+                DecisionReason::CacheHitEligible
+            } else {
+                DecisionReason::CacheHitIneligible
+            }
+        } else if !self.hwnds.is_empty() {
+            metrics::record_cache_lookup(Cache::Eligibility, false);
+
+            // use the hwnds files if twm integration is enabled
+            if let Some(raw_hwnds) = self.read_hwnds() {
+                let cursor_root_ineligibility = self.ineligibility(
+                    cursor_root_hwnd,
+                    cursor_root_class.as_deref(),
+                    Role::Cursor,
+                    Some(&raw_hwnds),
+                );
+                let foreground_ineligibility = self.ineligibility(
+                    foreground_hwnd,
+                    foreground_class.as_deref(),
+                    Role::Foreground,
+                    Some(&raw_hwnds),
+                );
+
+                // non-eligible results are only cached briefly due to potential
+                // delays with the twm writing newly managed windows to the hwnds file
+                let ineligible_ttl = self.ttl(Self::INELIGIBLE_TTL);
+                let ttl = |is_eligible: bool| (!is_eligible).then_some(ineligible_ttl);
+                let cursor_root_is_eligible = cursor_root_ineligibility.is_none();
+                let foreground_is_eligible = foreground_ineligibility.is_none();
+                self.caches.set_eligibility(
+                    cursor_root_hwnd,
+                    cursor_root_is_eligible,
+                    ttl(cursor_root_is_eligible),
+                );
+                self.caches.set_eligibility(
+                    foreground_hwnd,
+                    foreground_is_eligible,
+                    ttl(foreground_is_eligible),
+                );
+
+                cursor_root_ineligibility
+                    .or(foreground_ineligibility)
+                    .unwrap_or(DecisionReason::Eligible)
+            } else {
+                DecisionReason::HwndsUnavailable
+            }
+        } else {
+            metrics::record_cache_lookup(Cache::Eligibility, false);
+
+            let cursor_root_ineligibility = self.ineligibility(
+                cursor_root_hwnd,
+                cursor_root_class.as_deref(),
+                Role::Cursor,
+                None,
+            );
+            let foreground_ineligibility = self.ineligibility(
+                foreground_hwnd,
+                foreground_class.as_deref(),
+                Role::Foreground,
+                None,
+            );
+
+            self.caches.set_eligibility(
+                cursor_root_hwnd,
+                cursor_root_ineligibility.is_none(),
+                None,
+            );
+            self.caches
+                .set_eligibility(foreground_hwnd, foreground_ineligibility.is_none(), None);
+
+            cursor_root_ineligibility
+                .or(foreground_ineligibility)
+                .unwrap_or(DecisionReason::Eligible)
+        };
+
+        // komorebi leaves the other windows of a monocle workspace where they
+        // were, and raising one would pull focus out of the monocle container
+        if reason.is_raise()
+            && !self.hwnds.is_empty()
+            && self
+                .komorebi_snapshot()
+                .behind_monocle
+                .contains(&cursor_root_hwnd)
+        {
+            return (DecisionReason::BehindMonocle, Duration::ZERO);
+        }
+
+        let mut delay = self.settings.delay;
+
+        if reason.is_raise() && !self.settings.monitors.is_empty() {
+            let monitor = self.window_system.monitor(cursor_root_hwnd).ok();
+
+            if let Some((monitor, rule)) = monitor.and_then(|monitor| {
+                let rule = self.settings.monitors.get(&monitor)?;
+                Some((monitor, rule))
+            }) {
+                if rule.disabled {
+                    return (DecisionReason::DisabledMonitor { monitor }, Duration::ZERO);
+                }
+
+                let ignored_by = cursor_root_class.as_deref().and_then(|class| {
+                    matching_rule(
+                        &self.window_system,
+                        cursor_root_hwnd,
+                        class,
+                        &rule.ignore_rules,
+                    )
+                });
+
+                if let Some(ignored_by) = ignored_by {
+                    return (
+                        DecisionReason::Ignored {
+                            window: Role::Cursor,
+                            rule: ignored_by.id().to_string(),
+                        },
+                        Duration::ZERO,
+                    );
+                }
+
+                if let Some(monitor_delay) = rule.delay {
+                    delay = monitor_delay;
+                }
+            }
+        }
+
+        // workspace rules are more specific than monitor rules, so their delay
+        // takes precedence
+        if reason.is_raise() && !self.settings.komorebi_workspaces.is_empty() {
+            let workspace = self
+                .komorebi_snapshot()
+                .workspaces
+                .get(&cursor_root_hwnd)
+                .cloned();
+
+            if let Some((workspace, rule)) = workspace.and_then(|workspace| {
+                let rule = self.settings.komorebi_workspaces.get(&workspace)?;
+                Some((workspace, rule))
+            }) {
+                if rule.disabled {
+                    return (
+                        DecisionReason::DisabledWorkspace { workspace },
+                        Duration::ZERO,
+                    );
+                }
+
+                let ignored_by = cursor_root_class.as_deref().and_then(|class| {
+                    matching_rule(
+                        &self.window_system,
+                        cursor_root_hwnd,
+                        class,
+                        &rule.ignore_rules,
+                    )
+                });
+
+                if let Some(ignored_by) = ignored_by {
+                    return (
+                        DecisionReason::Ignored {
+                            window: Role::Cursor,
+                            rule: ignored_by.id().to_string(),
+                        },
+                        Duration::ZERO,
+                    );
+                }
+
+                if let Some(workspace_delay) = rule.delay {
+                    delay = workspace_delay;
+                }
+            }
+        }
+
+        if reason.is_raise() && self.settings.komorebi_floating != FloatingPolicy::Eligible {
+            let is_floating = self
+                .komorebi_snapshot()
+                .floating
+                .contains(&cursor_root_hwnd);
+
+            match self.settings.komorebi_floating {
+                FloatingPolicy::Ignored if is_floating => {
+                    return (DecisionReason::Floating, Duration::ZERO);
+                }
+                FloatingPolicy::Delayed(floating_delay) if is_floating => {
+                    delay = delay.max(Duration::from_millis(floating_delay));
+                }
+                _ => {}
+            }
+        }
+
+        // the first of two windows on either side of the cursor keeps focus
+        // until the cursor clearly commits to the other one
+        if reason.is_raise() && self.is_jitter(cursor_root_hwnd, foreground_hwnd) {
+            return (DecisionReason::Jitter, Duration::ZERO);
+        }
+
+        // remote desktop and virtual machine windows run their own focus
+        // handling and pointer capture, which raising them on hover fights
+        if reason.is_raise() && !self.settings.guest_rules.is_empty() {
+            let guest_rule = cursor_root_class.as_deref().and_then(|class| {
+                self.settings.guest_rules.iter().find(|guest_rule| {
+                    matching_rule(
+                        &self.window_system,
+                        cursor_root_hwnd,
+                        class,
+                        std::slice::from_ref(&guest_rule.rule),
+                    )
+                    .is_some()
+                })
+            });
+
+            match guest_rule.map(|guest_rule| (guest_rule.policy, guest_rule.rule.id())) {
+                Some((GuestPolicy::Click, rule)) => {
+                    return (
+                        DecisionReason::RequiresClick {
+                            rule: rule.to_string(),
+                        },
+                        Duration::ZERO,
+                    );
+                }
+                Some((GuestPolicy::Dwell(dwell), _)) => {
+                    delay = delay.max(Duration::from_millis(dwell));
+                }
+                None => {}
+            }
+        }
+
+        // an external command can veto windows which the rules consider eligible
+        if reason.is_raise() && self.command.is_some() {
+            let cursor = self.script_window(cursor_root_hwnd);
+            let is_eligible = self
+                .command
+                .as_mut()
+                .and_then(|command| command.is_eligible(&cursor));
+
+            match is_eligible {
+                Some(true) => {}
+                Some(false) => reason = DecisionReason::VetoedByCommand,
+                // the command is still deciding or ran too recently, so the
+                // movement is evaluated again shortly
+                None => return (DecisionReason::CommandThrottled, Duration::ZERO),
+            }
+        }
+
+        if let Some(verdict) = self.plugin_verdict(cursor_root_hwnd, foreground_hwnd) {
+            reason = if verdict {
+                DecisionReason::ForcedByPlugin
+            } else {
+                DecisionReason::VetoedByPlugin
+            };
+        }
+
+        // the user's script has the final say
+        if let Some(verdict) = self.script_verdict(cursor_root_hwnd, foreground_hwnd) {
+            reason = if verdict {
+                DecisionReason::ForcedByScript
+            } else {
+                DecisionReason::VetoedByScript
+            };
+        }
+
+        (reason, delay)
+    }
+
+    /// Returns why the window is ineligible to be raised or to lose focus, if
+    /// it is, testing it against the hwnds files if given and otherwise against
+    /// the filtered window styles, and then against the ignore rules
+    fn ineligibility(
+        &self,
+        hwnd: Hwnd,
+        class: Option<&str>,
+        window: Role,
+        raw_hwnds: Option<&str>,
+    ) -> Option<DecisionReason> {
+        match raw_hwnds {
+            Some(raw_hwnds) if !is_managed(raw_hwnds, hwnd) => {
+                return Some(DecisionReason::NotManaged { window });
+            }
+            None if self.window_system.has_filtered_style(hwnd) => {
+                return Some(DecisionReason::FilteredStyle { window });
+            }
+            _ => {}
+        }
+
+        let rule = ignored_by(
+            &self.window_system,
+            hwnd,
+            class?,
+            &self.settings.ignore_rules,
+            &self.settings.disabled_builtin_ignores,
+        )?;
+
+        Some(DecisionReason::Ignored { window, rule })
+    }
+
+    /// Asks each plugin in turn whether to force or veto raising the window
+    /// under the cursor, stopping at the first which decides
+    fn plugin_verdict(&mut self, cursor_root_hwnd: Hwnd, foreground_hwnd: Hwnd) -> Option<bool> {
+        if self.plugins.is_empty() {
+            return None;
+        }
+
+        let cursor = self.script_window(cursor_root_hwnd);
+        let foreground = self.script_window(foreground_hwnd);
+
+        for plugin in &mut self.plugins {
+            match plugin.should_raise(&cursor, &foreground) {
+                Ok(Some(verdict)) => {
+                    tracing::debug!(
+                        "plugin {} returned {verdict} for hwnds {cursor_root_hwnd} and {foreground_hwnd}",
+                        plugin.name()
+                    );
+
+                    return Some(verdict);
+                }
+                Ok(None) => {}
+                Err(error) => tracing::warn!("plugin {} failed: {error}", plugin.name()),
+            }
+        }
+
+        None
+    }
+
+    /// Returns the first raise strategy preferred by a plugin for the window
+    fn plugin_raise_strategy(&mut self, hwnd: Hwnd) -> Option<RaiseStrategy> {
+        if self.plugins.is_empty() {
+            return None;
+        }
+
+        let window = self.script_window(hwnd);
+
+        self.plugins
+            .iter_mut()
+            .find_map(|plugin| match plugin.raise_strategy(&window) {
+                Ok(strategy) => strategy,
+                Err(error) => {
+                    tracing::warn!("plugin {} failed: {error}", plugin.name());
+                    None
+                }
+            })
+    }
+
+    /// Asks the eligibility script, if there is one, whether to force or veto
+    /// raising the window under the cursor
+    fn script_verdict(&mut self, cursor_root_hwnd: Hwnd, foreground_hwnd: Hwnd) -> Option<bool> {
+        self.script.as_ref()?;
+
+        let cursor = self.script_window(cursor_root_hwnd);
+        let foreground = self.script_window(foreground_hwnd);
+
+        match self.script.as_ref()?.verdict(cursor, foreground) {
+            Ok(verdict) => {
+                if let Some(verdict) = verdict {
+                    tracing::debug!(
+                        "eligibility script returned {verdict} for hwnds {cursor_root_hwnd} and {foreground_hwnd}"
+                    );
+                }
+
+                verdict
+            }
+            Err(error) => {
+                tracing::warn!("eligibility script failed: {error}");
+                None
+            }
+        }
+    }
+
+    fn script_window(&mut self, hwnd: Hwnd) -> ScriptWindow {
+        ScriptWindow {
+            hwnd,
+            class: self.class(hwnd).as_deref().unwrap_or_default().to_string(),
+            exe: self.window_system.exe(hwnd).unwrap_or_default(),
+            title: self.window_system.title(hwnd).unwrap_or_default(),
+        }
+    }
+
+    /// Returns the sound to play when focus moves to the given window, which
+    /// is the sound for its monitor if there is one
+    fn focus_sound(&self, hwnd: Hwnd) -> Option<&Path> {
+        let monitor_sound = if self.settings.focus_sounds.is_empty() {
+            None
+        } else {
+            self.window_system
+                .monitor(hwnd)
+                .ok()
+                .and_then(|monitor| self.settings.focus_sounds.get(&monitor))
+        };
+
+        monitor_sound
+            .or(self.settings.focus_sound.as_ref())
+            .map(PathBuf::as_path)
+    }
+
+    /// Returns the window which was last focused on the given monitor
+    pub fn last_focused(&self, monitor: &str) -> Option<Hwnd> {
+        self.last_focused.get(monitor).copied()
+    }
+
+    /// Returns true if the window was created by this process
+    fn is_own_window(&self, hwnd: Hwnd) -> bool {
+        self.window_system.process_id(hwnd) == std::process::id()
+    }
+
+    /// Returns true if the window is visible, not minimized and on screen,
+    /// checking the cache first
+    fn is_visible(&mut self, hwnd: Hwnd) -> bool {
+        if let Some(visible) = self.caches.visibility(hwnd) {
+            metrics::record_cache_lookup(Cache::Visibility, true);
+            return visible;
+        }
+
+        metrics::record_cache_lookup(Cache::Visibility, false);
+        let visible = self.window_system.is_visible(hwnd);
+        let ttl = self.ttl(Self::VISIBILITY_TTL);
+        self.caches.set_visibility(hwnd, visible, ttl);
+
+        visible
+    }
+
+    /// Returns true if the window is narrower or shorter than the configured
+    /// minimum window size, which isn't cached since windows can be resized
+    fn is_too_small(&self, hwnd: Hwnd) -> bool {
+        let Some(min) = self.settings.min_window_size else {
+            return false;
+        };
+
+        self.window_system
+            .size(hwnd)
+            .is_ok_and(|(width, height)| width < min.width || height < min.height)
+    }
+
+    /// Records the given window as the last focused window on its monitor,
+    /// unless it is the desktop or an ignored window such as the taskbar
+    fn remember_focus(&mut self, hwnd: Hwnd) {
+        if self.last_foreground == Some(hwnd) {
+            return;
+        }
+
+        self.last_foreground = Some(hwnd);
+
+        let Some(class) = self.class(hwnd) else {
+            return;
+        };
+
+        if is_desktop(&class)
+            || is_ignored(
+                &self.window_system,
+                hwnd,
+                &class,
+                &self.settings.ignore_rules,
+                &self.settings.disabled_builtin_ignores,
+            )
+        {
+            return;
+        }
+
+        if let Ok(monitor) = self.window_system.monitor(hwnd) {
+            tracing::debug!("hwnd {hwnd} is the last focused window on {monitor}");
+            status::set_last_focused(&monitor, hwnd);
+            self.last_focused.insert(monitor, hwnd);
+        }
+    }
+
+    /// Returns true if the window is listed in any of the hwnds files
+    fn is_managed(&self, hwnd: Hwnd) -> bool {
+        self.read_hwnds()
+            .is_some_and(|raw_hwnds| is_managed(&raw_hwnds, hwnd))
+    }
+
+    /// Returns the combined contents of every hwnds file which could be read,
+    /// so that a window is managed if it appears in any of them
+    fn read_hwnds(&self) -> Option<String> {
+        let contents: Vec<String> = self
+            .hwnds
+            .iter()
+            .filter_map(|hwnds| std::fs::read_to_string(hwnds).ok())
+            .collect();
+
+        (!contents.is_empty()).then(|| contents.join("\n"))
+    }
+
+    /// Returns the window once the cursor has remained over it for the given
+    /// delay and travelled the configured distance inside it, so that skimming
+    /// along the boundary between two windows doesn't flicker focus
+    fn after_delay(&mut self, hwnd: Hwnd, delay: Duration) -> Option<Hwnd> {
+        if !delay.is_zero() || self.settings.hysteresis > 0 {
+            let cursor_pos = self.window_system.cursor_pos().ok();
+
+            match &self.pending_raise {
+                Some(pending) if pending.hwnd == hwnd => {
+                    if pending.since.elapsed() < delay
+                        || !self.has_travelled(pending.entered_at, cursor_pos)
+                    {
+                        return None;
+                    }
+                }
+                _ => {
+                    self.pending_raise = Some(PendingRaise {
+                        hwnd,
+                        since: Instant::now(),
+                        entered_at: cursor_pos,
+                    });
+
+                    return None;
+                }
+            }
+        }
+
+        self.pending_raise = None;
+        Some(hwnd)
+    }
+
+    /// Returns true if the cursor has moved at least the configured hysteresis
+    /// distance from where it entered a window, or if either position is unknown
+    fn has_travelled(&self, from: Option<(i32, i32)>, to: Option<(i32, i32)>) -> bool {
+        let (Some(from), Some(to)) = (from, to) else {
+            return true;
+        };
+
+        is_at_least(from, to, self.settings.hysteresis)
+    }
+
+    /// Returns true if the cursor has jumped at least the configured teleport
+    /// distance since the last movement, as when another tool warps it
+    fn has_teleported(&mut self) -> bool {
+        if self.settings.teleport_distance == 0 {
+            return false;
+        }
+
+        let cursor_pos = self.window_system.cursor_pos().ok();
+        let previous = std::mem::replace(&mut self.last_cursor_pos, cursor_pos);
+
+        let (Some(from), Some(to)) = (previous, cursor_pos) else {
+            return false;
+        };
+
+        is_at_least(from, to, self.settings.teleport_distance)
+    }
+
+    /// Raises a window, falling back to other strategies if the one which last
+    /// worked for the window's class fails
+    fn raise(&mut self, hwnd: Hwnd) -> Result<RaiseStrategy> {
+        let class = self.class(hwnd);
+        let mut last_error = None;
+
+        let mut order = self.raise_strategies.order(class.as_deref());
+        if let Some(preferred) = self.plugin_raise_strategy(hwnd) {
+            order.retain(|strategy| *strategy != preferred);
+            order.insert(0, preferred);
+        }
+
+        // window managers only know how to focus the windows they manage, so
+        // their strategies are never remembered for a whole class. without an
+        // hwnds file, only glazewm replies and so rejects unmanaged windows,
+        // while komorebi is never asked whether it focused anything
+        if let Some(focus_delegate) = self.settings.focus_delegate {
+            let delegate = RaiseStrategy::from(focus_delegate);
+            order.retain(|strategy| *strategy != delegate);
+
+            let rejects_unmanaged = focus_delegate == FocusDelegate::GlazeWm;
+            if (self.hwnds.is_empty() && rejects_unmanaged) || self.is_managed(hwnd) {
+                order.insert(0, delegate);
+            }
+        }
+
+        for strategy in order {
+            match self.window_system.raise(hwnd, strategy) {
+                Ok(()) => {
+                    if let Some(class) = class.filter(|_| !strategy.is_delegated()) {
+                        self.raise_strategies.remember(class, strategy);
+                    }
+
+                    return Ok(strategy);
+                }
+                // a hung window manager says nothing about the window itself,
+                // which the remaining strategies may still be able to raise
+                Err(error) if is_timeout(&error) && !strategy.is_delegated() => return Err(error),
+                Err(error) => {
+                    tracing::debug!("failed to raise hwnd {hwnd} using {strategy:?}: {error}");
+                    last_error = Some(error);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| eyre!("there are no raise strategies to try")))
+    }
+
+    /// Brings the windows owned by a raised window (find dialogs, tool palettes)
+    /// forward with it so that multi-window applications come forward as a unit
+    fn raise_owned_windows(&self, hwnd: Hwnd) {
+        let owned_hwnds = match self.window_system.owned_windows(hwnd) {
+            Ok(owned_hwnds) => owned_hwnds,
+            Err(error) => {
+                tracing::warn!("failed to find windows owned by hwnd {hwnd}: {error}");
+                return;
+            }
+        };
+
+        for owned_hwnd in owned_hwnds {
+            match self.window_system.bring_to_top(owned_hwnd) {
+                Ok(()) => tracing::debug!("raised hwnd {owned_hwnd} owned by hwnd {hwnd}"),
+                Err(error) => tracing::warn!("failed to raise owned hwnd {owned_hwnd}: {error}"),
+            }
+        }
+    }
+
+    fn root_window(&mut self, hwnd: Hwnd) -> Option<Hwnd> {
+        let _span = tracing::trace_span!(target: PROFILE_TARGET, "root_window").entered();
+        let info = self.caches.entry(hwnd);
+
+        match info.root {
+            Some(root_hwnd) => {
+                tracing::debug!("hwnd {hwnd} root hwnd was found in the cache: {root_hwnd}");
+                metrics::record_cache_lookup(Cache::Root, true);
+                Some(root_hwnd)
+            }
+            // make syscalls if necessary and populate the cache
+            None => {
+                metrics::record_cache_lookup(Cache::Root, false);
+                let root_hwnd = self.window_system.root_window(hwnd).ok()?;
+                info.root = Some(root_hwnd);
+                Some(root_hwnd)
+            }
+        }
+    }
+
+    fn class(&mut self, hwnd: Hwnd) -> Option<Arc<str>> {
+        let _span = tracing::trace_span!(target: PROFILE_TARGET, "class").entered();
+        let info = self.caches.entry(hwnd);
+
+        match &info.class {
+            Some(class) => {
+                tracing::debug!("hwnd {hwnd} class was found in the cache: {class}");
+                metrics::record_cache_lookup(Cache::Class, true);
+                Some(Arc::clone(class))
+            }
+            // make syscalls if necessary and populate the cache
+            None => {
+                metrics::record_cache_lookup(Cache::Class, false);
+                let class: Arc<str> = self.window_system.class(hwnd).ok()?.into();
+                info.class = Some(Arc::clone(&class));
+                Some(class)
+            }
+        }
+    }
+}
+
+/// Returns true if the two positions are at least the given distance apart
+fn is_at_least(from: (i32, i32), to: (i32, i32), distance: u32) -> bool {
+    let dx = i64::from(to.0 - from.0);
+    let dy = i64::from(to.1 - from.1);
+    let distance = i64::from(distance);
+
+    dx * dx + dy * dy >= distance * distance
+}
+
+/// Returns true if a raise failed because the window didn't respond in time
+fn is_timeout(error: &Report) -> bool {
+    error.downcast_ref::<RaiseTimedOut>().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ApplicationIdentifier;
+    use crate::config::Config;
+    use crate::config::IgnoreRule;
+    use crate::config::MatchingRule;
+    use crate::config::MatchingStrategy;
+    use crate::config::WindowSize;
+    use crate::raise::FocusDelegate;
+    use crate::schedule::LocalTime;
+    use crate::schedule::Weekday;
+    use crate::window_system::MockWindow;
+    use crate::window_system::MockWindowSystem;
+
+    fn mock() -> MockWindowSystem {
+        MockWindowSystem::new(Hwnd(1), Hwnd(2))
+            .with_window(Hwnd(1), MockWindow::new("Chrome_WidgetWin_1"))
+            .with_window(Hwnd(2), MockWindow::new("CASCADIA_HOSTING_WINDOW_CLASS"))
+            .with_window(Hwnd(3), MockWindow::new("Shell_TrayWnd"))
+            .with_window(
+                Hwnd(4),
+                MockWindow::new("Chrome_WidgetWin_1").with_root(Hwnd(1)),
+            )
+            .with_window(Hwnd(5), MockWindow::new("Chrome_RenderWidgetHostHWND"))
+            .with_window(Hwnd(6), MockWindow::new("SDL_app"))
+            .with_window(Hwnd(7), MockWindow::new("Notepad").with_filtered_style())
+            .with_window(
+                Hwnd(8),
+                MockWindow::new("tooltips_class32").with_transient(),
+            )
+    }
+
+    #[test]
+    fn raises_eligible_window_under_cursor() {
+        let window_system = mock();
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+
+        engine.handle_mouse_move();
+
+        assert_eq!(window_system.raised(), vec![Hwnd(1)]);
+        assert_eq!(window_system.foreground_window().unwrap(), Hwnd(1));
+    }
+
+    #[test]
+    fn evaluates_windows_away_from_the_cursor() {
+        let window_system = mock();
+        let settings = Settings {
+            delay: Duration::from_millis(500),
+            ..Default::default()
+        };
+        let mut engine = Engine::new(&window_system, vec![], settings);
+
+        // delays only apply while the cursor rests over a window
+        assert_eq!(engine.evaluate_window(Hwnd(6)), DecisionReason::Eligible);
+        assert_eq!(
+            engine.evaluate_window(Hwnd(2)),
+            DecisionReason::AlreadyFocused
+        );
+        assert_eq!(engine.evaluate_window(Hwnd(8)), DecisionReason::Transient);
+        assert_eq!(
+            engine.evaluate_window(Hwnd(7)),
+            DecisionReason::FilteredStyle {
+                window: Role::Cursor
+            }
+        );
+        assert!(window_system.raised().is_empty());
+    }
+
+    #[test]
+    fn falls_back_to_attached_thread_input() {
+        let window_system = MockWindowSystem::new(Hwnd(1), Hwnd(2))
+            .with_window(
+                Hwnd(1),
+                MockWindow::new("SDL_app").with_failing_strategy(RaiseStrategy::SendInput),
+            )
+            .with_window(Hwnd(2), MockWindow::new("CASCADIA_HOSTING_WINDOW_CLASS"));
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+
+        assert_eq!(
+            engine.raise(Hwnd(1)).unwrap(),
+            RaiseStrategy::AttachThreadInput
+        );
+        assert_eq!(
+            engine.raise_strategies.get("SDL_app"),
+            Some(RaiseStrategy::AttachThreadInput)
+        );
+    }
+
+    #[test]
+    fn retries_failed_raise_while_cursor_remains() {
+        let window_system = MockWindowSystem::new(Hwnd(1), Hwnd(2))
+            .with_window(
+                Hwnd(1),
+                MockWindow::new("SDL_app")
+                    .with_failing_strategy(RaiseStrategy::SendInput)
+                    .with_failing_strategy(RaiseStrategy::AttachThreadInput),
+            )
+            .with_window(Hwnd(2), MockWindow::new("CASCADIA_HOSTING_WINDOW_CLASS"));
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+        engine.handle_mouse_move();
+
+        // every strategy is tried on the first attempt and on each retry
+        assert_eq!(window_system.raise_attempts(), 2 * 3);
+        assert!(window_system.raised().is_empty());
+    }
+
+    #[test]
+    fn undoes_the_last_raise() {
+        let window_system = mock();
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+
+        engine.handle_mouse_move();
+        engine.undo_last_raise();
+
+        assert_eq!(window_system.raised(), vec![Hwnd(1), Hwnd(2)]);
+        assert_eq!(window_system.foreground_window().unwrap(), Hwnd(2));
+
+        // the window isn't raised again while the cursor remains over it
+        engine.handle_mouse_move();
+        assert_eq!(
+            engine.last_decision().map(|decision| &decision.reason),
+            Some(&DecisionReason::Undone)
+        );
+
+        window_system.set_cursor_window(Hwnd(2));
+        engine.handle_mouse_move();
+        window_system.set_cursor_window(Hwnd(1));
+        engine.handle_mouse_move();
+
+        assert_eq!(window_system.raised(), vec![Hwnd(1), Hwnd(2), Hwnd(1)]);
+    }
+
+    #[test]
+    fn groups_windows_by_process_when_enabled() {
+        let window_system = mock()
+            .with_window(
+                Hwnd(1),
+                MockWindow::new("Chrome_WidgetWin_1").with_process_id(42),
+            )
+            .with_window(
+                Hwnd(2),
+                MockWindow::new("Chrome_WidgetWin_1").with_process_id(42),
+            );
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+        assert_eq!(engine.evaluate(), Some(Hwnd(1)));
+
+        let settings = Settings {
+            group_by_process: true,
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+        assert_eq!(engine.evaluate(), None);
+        assert_eq!(
+            engine.last_decision().map(|decision| &decision.reason),
+            Some(&DecisionReason::SameProcess)
+        );
+    }
+
+    #[test]
+    fn skips_windows_which_time_out_when_raised() {
+        let window_system = mock().with_window(Hwnd(1), MockWindow::new("SDL_app").unresponsive());
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+        engine.handle_mouse_move();
+
+        // neither other strategies nor retries are tried after a timeout
+        assert_eq!(window_system.raise_attempts(), 1);
+
+        engine.handle_mouse_move();
+
+        assert_eq!(window_system.raise_attempts(), 1);
+        assert_eq!(
+            engine.last_decision().map(|decision| &decision.reason),
+            Some(&DecisionReason::Unresponsive)
+        );
+    }
+
+    #[test]
+    fn lowers_previous_window_when_enabled() {
+        let window_system = mock();
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+        engine.handle_mouse_move();
+
+        assert!(window_system.lowered().is_empty());
+
+        let window_system = mock();
+        let settings = Settings {
+            auto_lower: true,
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+        engine.handle_mouse_move();
+
+        assert_eq!(window_system.raised(), vec![Hwnd(1)]);
+        assert_eq!(window_system.lowered(), vec![Hwnd(2)]);
+    }
+
+    #[test]
+    fn delegates_focus_of_managed_windows() {
+        let window_system = mock();
+        let hwnds = std::env::temp_dir().join("masir_engine_test.delegate.hwnd.json");
+        std::fs::write(&hwnds, "[1]").unwrap();
+
+        let settings = Settings {
+            focus_delegate: Some(FocusDelegate::Komorebi),
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![hwnds.clone()], settings);
+        let managed = engine.raise(Hwnd(1));
+        let unmanaged = engine.raise(Hwnd(2));
+
+        std::fs::remove_file(hwnds).unwrap();
+
+        assert_eq!(managed.unwrap(), RaiseStrategy::Komorebi);
+        assert_eq!(unmanaged.unwrap(), RaiseStrategy::SendInput);
+        assert_eq!(engine.raise_strategies.get("Chrome_WidgetWin_1"), None);
+    }
+
+    #[test]
+    fn only_delegates_unmanaged_windows_to_window_managers_which_reply() {
+        let window_system = mock().with_window(
+            Hwnd(1),
+            MockWindow::new("Chrome_WidgetWin_1").with_failing_strategy(RaiseStrategy::Komorebi),
+        );
+
+        let settings = Settings {
+            focus_delegate: Some(FocusDelegate::Komorebi),
+            ..Default::default()
+        };
+
+        // komorebi never replies, so it can't be told apart from success
+        let mut engine = Engine::new(&window_system, vec![], settings);
+        assert_eq!(engine.raise(Hwnd(1)).unwrap(), RaiseStrategy::SendInput);
+        assert_eq!(window_system.raise_attempts(), 1);
+
+        let settings = Settings {
+            focus_delegate: Some(FocusDelegate::GlazeWm),
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+        assert_eq!(engine.raise(Hwnd(1)).unwrap(), RaiseStrategy::GlazeWm);
+    }
+
+    #[test]
+    fn falls_back_when_window_manager_times_out() {
+        let window_system = mock().with_window(
+            Hwnd(1),
+            MockWindow::new("Chrome_WidgetWin_1").with_hung_strategy(RaiseStrategy::GlazeWm),
+        );
+
+        let settings = Settings {
+            focus_delegate: Some(FocusDelegate::GlazeWm),
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+        engine.handle_mouse_move();
+
+        assert_eq!(window_system.raised(), vec![Hwnd(1)]);
+        assert_eq!(window_system.raise_attempts(), 2);
+    }
+
+    #[test]
+    fn logs_raises_when_enabled() {
+        let window_system = mock();
+        let raise_log = std::env::temp_dir().join("masir_engine_test.raises.jsonl");
+        let _ = std::fs::remove_file(&raise_log);
+
+        let settings = Settings {
+            raise_log: Some(raise_log.clone()),
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+        engine.handle_mouse_move();
+        drop(engine);
+
+        let contents = std::fs::read_to_string(&raise_log).unwrap();
+        std::fs::remove_file(raise_log).unwrap();
+
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains(r#""hwnd":1"#));
+        assert!(lines[0].contains(r#""outcome":"raised""#));
+        assert!(lines[0].contains(r#""strategy":"SendInput""#));
+    }
+
+    #[test]
+    fn raises_owned_windows_with_owner() {
+        let window_system = mock()
+            .with_window(
+                Hwnd(8),
+                MockWindow::new("gdkWindowToplevel").with_owner(Hwnd(1)),
+            )
+            .with_window(
+                Hwnd(9),
+                MockWindow::new("gdkWindowToplevel").with_owner(Hwnd(2)),
+            );
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+        engine.handle_mouse_move();
+
+        assert_eq!(window_system.raised(), vec![Hwnd(1)]);
+        assert_eq!(window_system.brought_to_top(), vec![Hwnd(8)]);
+    }
+
+    #[test]
+    fn remembers_last_focused_window_per_monitor() {
+        let window_system = MockWindowSystem::new(Hwnd(1), Hwnd(2))
+            .with_window(
+                Hwnd(1),
+                MockWindow::new("Chrome_WidgetWin_1").with_monitor("DISPLAY1"),
+            )
+            .with_window(
+                Hwnd(2),
+                MockWindow::new("CASCADIA_HOSTING_WINDOW_CLASS").with_monitor("DISPLAY2"),
+            )
+            .with_window(
+                Hwnd(3),
+                MockWindow::new("Shell_TrayWnd").with_monitor("DISPLAY1"),
+            );
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+        engine.handle_mouse_move();
+
+        assert_eq!(engine.last_focused("DISPLAY1"), Some(Hwnd(1)));
+        assert_eq!(engine.last_focused("DISPLAY2"), Some(Hwnd(2)));
+
+        // ignored windows such as the taskbar are never remembered
+        window_system.set_foreground_window(Hwnd(3));
+        engine.evaluate();
+
+        assert_eq!(engine.last_focused("DISPLAY1"), Some(Hwnd(1)));
+    }
+
+    #[test]
+    fn refreshes_monitor_state_after_display_change() {
+        let window_system = mock().with_window(
+            Hwnd(1),
+            MockWindow::new("Chrome_WidgetWin_1").with_monitor("DISPLAY1"),
+        );
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+        engine.handle_mouse_move();
+        assert_eq!(engine.caches.visibility(Hwnd(1)), Some(true));
+
+        engine.handle_display_change();
+
+        assert_eq!(engine.caches.visibility(Hwnd(1)), None);
+        assert_eq!(engine.last_focused("DISPLAY1"), Some(Hwnd(1)));
+    }
+
+    #[test]
+    fn plays_the_focus_sound_of_the_monitor() {
+        let window_system = mock()
+            .with_window(
+                Hwnd(1),
+                MockWindow::new("Chrome_WidgetWin_1").with_monitor(r"\\.\DISPLAY2"),
+            )
+            .with_window(
+                Hwnd(3),
+                MockWindow::new("Notepad++").with_monitor(r"\\.\DISPLAY3"),
+            );
+
+        let config: Config = toml::from_str(
+            r#"
+            focus_sound = "focus.wav"
+
+            [focus_sounds]
+            2 = "display2.wav"
+            '\\.\DISPLAY3' = "display3.wav"
+            "#,
+        )
+        .unwrap();
+
+        let engine = Engine::new(&window_system, vec![], config.settings(None).unwrap());
+
+        assert_eq!(engine.focus_sound(Hwnd(1)), Some(Path::new("display2.wav")));
+        assert_eq!(engine.focus_sound(Hwnd(3)), Some(Path::new("display3.wav")));
+        assert_eq!(engine.focus_sound(Hwnd(2)), Some(Path::new("focus.wav")));
+    }
+
+    #[test]
+    fn focuses_desktop_only_in_strict_mode() {
+        let window_system = mock().with_window(Hwnd(8), MockWindow::new("Progman"));
+        window_system.set_cursor_window(Hwnd(8));
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+        assert_eq!(engine.evaluate(), None);
+
+        let settings = Settings {
+            strict: true,
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+        assert_eq!(engine.evaluate(), Some(Hwnd(8)));
+
+        window_system.set_foreground_window(Hwnd(3));
+        assert_eq!(engine.evaluate(), None);
+    }
+
+    #[test]
+    fn raises_root_of_child_window_under_cursor() {
+        let window_system = mock();
+        window_system.set_cursor_window(Hwnd(4));
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+
+        assert_eq!(engine.evaluate(), Some(Hwnd(1)));
+    }
+
+    #[test]
+    fn does_not_raise_foreground_root_window() {
+        let window_system = mock();
+        window_system.set_cursor_window(Hwnd(4));
+        window_system.set_foreground_window(Hwnd(1));
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+
+        assert_eq!(engine.evaluate(), None);
+    }
+
+    #[test]
+    fn does_not_raise_ignored_class() {
+        let window_system = mock();
+        window_system.set_cursor_window(Hwnd(3));
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+
+        assert_eq!(engine.evaluate(), None);
+    }
+
+    #[test]
+    fn does_not_raise_filtered_style() {
+        let window_system = mock();
+        window_system.set_cursor_window(Hwnd(7));
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+
+        assert_eq!(engine.evaluate(), None);
+    }
+
+    #[test]
+    fn focuses_the_control_under_the_cursor() {
+        let window_system = mock();
+        window_system.set_cursor_window(Hwnd(4));
+
+        let mut engine = Engine::new(
+            &window_system,
+            vec![],
+            Settings {
+                deep_focus: true,
+                ..Default::default()
+            },
+        );
+        engine.handle_mouse_move();
+
+        assert_eq!(window_system.raised(), vec![Hwnd(1)]);
+        assert_eq!(window_system.focused_controls(), vec![Hwnd(4)]);
+    }
+
+    #[test]
+    fn ignores_overlays_unless_disabled() {
+        let window_system = mock().with_window(
+            Hwnd(1),
+            MockWindow::new("Chrome_WidgetWin_1").with_exe("GameOverlayUI.exe"),
+        );
+
+        let settings = Config::default().settings(None).unwrap();
+        let mut engine = Engine::new(&window_system, vec![], settings);
+        assert_eq!(engine.evaluate(), None);
+
+        let config: Config =
+            toml::from_str(r#"disabled_builtin_ignores = ["GameOverlayUI.exe"]"#).unwrap();
+        let mut engine = Engine::new(&window_system, vec![], config.settings(None).unwrap());
+        assert_eq!(engine.evaluate(), Some(Hwnd(1)));
+
+        let config: Config = toml::from_str("ignore_overlays = false").unwrap();
+        let mut engine = Engine::new(&window_system, vec![], config.settings(None).unwrap());
+        assert_eq!(engine.evaluate(), Some(Hwnd(1)));
+    }
+
+    #[test]
+    fn skips_raising_during_quiet_hours() {
+        let window_system = mock();
+        window_system.set_local_time(LocalTime::new(Weekday::Monday, 9, 30));
+
+        let config: Config = toml::from_str(
+            r#"
+            quiet_hours = [{ days = ["Monday"], start = "09:00", end = "10:00" }]
+            "#,
+        )
+        .unwrap();
+
+        let mut engine = Engine::new(&window_system, vec![], config.settings(None).unwrap());
+
+        assert_eq!(engine.evaluate(), None);
+        assert_eq!(
+            engine.last_decision().map(|decision| &decision.reason),
+            Some(&DecisionReason::QuietHours)
+        );
+
+        // the schedule is only checked every few seconds
+        window_system.set_local_time(LocalTime::new(Weekday::Monday, 10, 0));
+        engine.quiet_hours = None;
+        assert_eq!(engine.evaluate(), Some(Hwnd(1)));
+    }
+
+    #[test]
+    fn applies_rules_for_monitors() {
+        let window_system = mock()
+            .with_window(
+                Hwnd(9),
+                MockWindow::new("Notepad++").with_monitor(r"\\.\DISPLAY2"),
+            )
+            .with_window(
+                Hwnd(10),
+                MockWindow::new("Chrome_WidgetWin_1").with_monitor(r"\\.\DISPLAY3"),
+            );
+
+        let config: Config = toml::from_str(
+            r#"
+            [monitors.2]
+            disabled = true
+
+            [monitors.'\\.\DISPLAY3']
+            ignore_rules = [{ kind = "Class", id = "Chrome_WidgetWin_1" }]
+            "#,
+        )
+        .unwrap();
+
+        let mut engine = Engine::new(&window_system, vec![], config.settings(None).unwrap());
+
+        window_system.set_cursor_window(Hwnd(9));
+        assert_eq!(engine.evaluate(), None);
+        assert_eq!(
+            engine.last_decision().map(|decision| &decision.reason),
+            Some(&DecisionReason::DisabledMonitor {
+                monitor: String::from(r"\\.\DISPLAY2")
+            })
+        );
+
+        window_system.set_cursor_window(Hwnd(10));
+        assert_eq!(engine.evaluate(), None);
+
+        window_system.set_cursor_window(Hwnd(1));
+        assert_eq!(engine.evaluate(), Some(Hwnd(1)));
+    }
+
+    #[test]
+    fn applies_policies_of_guest_rules() {
+        let window_system = mock()
+            .with_window(
+                Hwnd(9),
+                MockWindow::new("TscShellContainerClass").with_exe("mstsc.exe"),
+            )
+            .with_window(
+                Hwnd(10),
+                MockWindow::new("VMUIFrame").with_exe("vmware.exe"),
+            );
+
+        let config: Config = toml::from_str(
+            r#"
+            guest_rules = [
+              { kind = "Exe", id = "mstsc.exe", policy = "Click" },
+              { kind = "Class", id = "VMUIFrame", policy = { Dwell = 20 } },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        let mut engine = Engine::new(&window_system, vec![], config.settings(None).unwrap());
+
+        window_system.set_cursor_window(Hwnd(9));
+        assert_eq!(engine.evaluate(), None);
+        assert_eq!(
+            engine.last_decision().map(|decision| &decision.reason),
+            Some(&DecisionReason::RequiresClick {
+                rule: String::from("mstsc.exe")
+            })
+        );
+
+        window_system.set_cursor_window(Hwnd(10));
+        assert_eq!(engine.evaluate(), None);
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(engine.evaluate(), Some(Hwnd(10)));
+    }
+
+    #[test]
+    fn applies_rules_for_komorebi_workspaces() {
+        let window_system = mock();
+        let config: Config = toml::from_str(
+            r#"
+            delay = 60000
+
+            [komorebi_workspaces.media]
+            disabled = true
+
+            [komorebi_workspaces.code]
+            delay = 0
+
+            [komorebi_workspaces.chat]
+            ignore_rules = [{ kind = "Class", id = "Chrome_WidgetWin_1" }]
+            "#,
+        )
+        .unwrap();
+
+        let mut engine = Engine::new(&window_system, vec![], config.settings(None).unwrap());
+
+        let snapshot = |workspace: &str| Snapshot {
+            workspaces: HashMap::from([(Hwnd(1), workspace.to_string())]),
+            ..Default::default()
+        };
+
+        engine.komorebi = Some((snapshot("media"), Instant::now()));
+        assert_eq!(engine.evaluate(), None);
+        assert_eq!(
+            engine.last_decision().map(|decision| &decision.reason),
+            Some(&DecisionReason::DisabledWorkspace {
+                workspace: String::from("media")
+            })
+        );
+
+        engine.komorebi = Some((snapshot("chat"), Instant::now()));
+        assert_eq!(engine.evaluate(), None);
+        assert_eq!(
+            engine.last_decision().map(|decision| &decision.reason),
+            Some(&DecisionReason::Ignored {
+                window: Role::Cursor,
+                rule: String::from("Chrome_WidgetWin_1")
+            })
+        );
+
+        engine.komorebi = Some((snapshot("code"), Instant::now()));
+        assert_eq!(engine.evaluate(), Some(Hwnd(1)));
+    }
+
+    #[test]
+    fn records_the_reason_for_each_decision() {
+        let window_system = mock();
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+        let reason = |engine: &mut Engine<&MockWindowSystem>| {
+            engine.evaluate();
+            engine
+                .last_decision()
+                .map(|decision| decision.reason.clone())
+        };
+
+        assert_eq!(reason(&mut engine), Some(DecisionReason::Eligible));
+        assert_eq!(reason(&mut engine), Some(DecisionReason::CacheHitEligible));
+
+        window_system.set_cursor_window(Hwnd(3));
+        assert_eq!(
+            reason(&mut engine),
+            Some(DecisionReason::Ignored {
+                window: Role::Cursor,
+                rule: String::from("Shell_TrayWnd"),
+            })
+        );
+
+        window_system.set_cursor_window(Hwnd(7));
+        assert_eq!(
+            reason(&mut engine),
+            Some(DecisionReason::FilteredStyle {
+                window: Role::Cursor
+            })
+        );
+
+        window_system.set_cursor_window(Hwnd(4));
+        window_system.set_foreground_window(Hwnd(1));
+        assert_eq!(reason(&mut engine), Some(DecisionReason::SameRoot));
+    }
+
+    #[test]
+    fn does_not_raise_or_leave_own_windows() {
+        let window_system = mock().with_window(
+            Hwnd(9),
+            MockWindow::new("masir").with_process_id(std::process::id()),
+        );
+        window_system.set_cursor_window(Hwnd(9));
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+        assert_eq!(engine.evaluate(), None);
+
+        window_system.set_cursor_window(Hwnd(1));
+        window_system.set_foreground_window(Hwnd(9));
+        assert_eq!(engine.evaluate(), None);
+    }
+
+    #[test]
+    fn does_not_raise_windows_below_minimum_size() {
+        let window_system =
+            mock().with_window(Hwnd(9), MockWindow::new("Helper").with_size(40, 300));
+        window_system.set_cursor_window(Hwnd(9));
+
+        let settings = Settings {
+            min_window_size: Some(WindowSize {
+                width: 50,
+                height: 50,
+            }),
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+        assert_eq!(engine.evaluate(), None);
+
+        let window_system =
+            mock().with_window(Hwnd(9), MockWindow::new("Helper").with_size(50, 50));
+        window_system.set_cursor_window(Hwnd(9));
+
+        let settings = Settings {
+            min_window_size: Some(WindowSize {
+                width: 50,
+                height: 50,
+            }),
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+        assert_eq!(engine.evaluate(), Some(Hwnd(9)));
+    }
+
+    #[test]
+    fn does_not_raise_hidden_window() {
+        let window_system = mock().with_window(Hwnd(9), MockWindow::new("Minimized").with_hidden());
+        window_system.set_cursor_window(Hwnd(9));
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+
+        assert_eq!(engine.evaluate(), None);
+        assert_eq!(engine.caches.visibility(Hwnd(9)), Some(false));
+    }
+
+    #[test]
+    fn does_not_raise_transient_window() {
+        let window_system = mock();
+        window_system.set_cursor_window(Hwnd(8));
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+
+        assert_eq!(engine.evaluate(), None);
+    }
+
+    #[test]
+    fn does_not_steal_focus_from_ignored_foreground() {
+        let window_system = mock();
+        window_system.set_foreground_window(Hwnd(3));
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+
+        assert_eq!(engine.evaluate(), None);
+    }
+
+    #[test]
+    fn does_not_steal_focus_from_protected_foreground() {
+        let window_system = mock();
+
+        let settings = Settings {
+            protect_rules: vec![IgnoreRule::compile(&MatchingRule {
+                kind: ApplicationIdentifier::Class,
+                id: "CASCADIA_HOSTING_WINDOW_CLASS".to_string(),
+                matching_strategy: MatchingStrategy::Equals,
+            })
+            .unwrap()],
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+        assert_eq!(engine.evaluate(), None);
+
+        window_system.set_foreground_window(Hwnd(6));
+        assert_eq!(engine.evaluate(), Some(Hwnd(1)));
+    }
+
+    #[test]
+    fn does_not_steal_focus_from_foreground_playing_audio() {
+        let window_system = mock().with_window(
+            Hwnd(2),
+            MockWindow::new("TeamsWebView").with_exe("ms-teams.exe"),
+        );
+        window_system.set_audio_playing_exes(&["ms-teams.exe"]);
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+        assert_eq!(engine.evaluate(), Some(Hwnd(1)));
+
+        let settings = Settings {
+            protect_audio: true,
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+        assert_eq!(engine.evaluate(), None);
+
+        let settings = Settings {
+            protect_audio: true,
+            protect_audio_exceptions: vec![IgnoreRule::compile(&MatchingRule {
+                kind: ApplicationIdentifier::Exe,
+                id: "ms-teams.exe".to_string(),
+                matching_strategy: MatchingStrategy::Equals,
+            })
+            .unwrap()],
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+        assert_eq!(engine.evaluate(), Some(Hwnd(1)));
+    }
+
+    #[test]
+    fn pairs_steam_windows() {
+        let window_system = mock();
+        window_system.set_cursor_window(Hwnd(5));
+        window_system.set_foreground_window(Hwnd(6));
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+
+        assert_eq!(engine.evaluate(), None);
+        assert!(engine.caches.is_paired(Hwnd(5), Hwnd(6)));
+    }
+
+    #[test]
+    fn caches_window_properties() {
+        let window_system = mock();
+        window_system.set_cursor_window(Hwnd(4));
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+        engine.evaluate();
+
+        assert_eq!(
+            engine.caches.get(Hwnd(4)).and_then(|info| info.root),
+            Some(Hwnd(1))
+        );
+        assert_eq!(
+            engine
+                .caches
+                .get(Hwnd(1))
+                .and_then(|info| info.class.as_deref()),
+            Some("Chrome_WidgetWin_1")
+        );
+        assert_eq!(engine.caches.are_eligible(Hwnd(1), Hwnd(2)), Some(true));
+    }
+
+    #[test]
+    fn forgets_windows_on_other_virtual_desktops() {
+        let window_system = mock().with_window(
+            Hwnd(2),
+            MockWindow::new("CASCADIA_HOSTING_WINDOW_CLASS").with_virtual_desktop(1),
+        );
+        window_system.set_cursor_window(Hwnd(4));
+
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+        engine.evaluate();
+        engine.forget_other_virtual_desktops();
+
+        assert!(engine.caches.get(Hwnd(1)).is_some());
+        assert!(engine.caches.get(Hwnd(4)).is_some());
+        assert!(engine.caches.get(Hwnd(2)).is_none());
+    }
+
+    #[test]
+    fn briefly_caches_windows_missing_from_hwnds_file() {
+        let window_system = mock();
+        let hwnds = std::env::temp_dir().join("masir_engine_test.hwnd.json");
+        std::fs::write(&hwnds, "[2]").unwrap();
+
+        let mut engine = Engine::new(&window_system, vec![hwnds.clone()], Settings::default());
+        let decision = engine.evaluate();
+
+        std::fs::remove_file(hwnds).unwrap();
+
+        assert_eq!(decision, None);
+        assert_eq!(engine.caches.eligibility(Hwnd(1)), Some(false));
+        assert!(engine
+            .caches
+            .get(Hwnd(1))
+            .is_some_and(|info| info.eligible_until.is_some()));
+    }
+
+    #[test]
+    fn keeps_focus_while_the_cursor_jitters_across_a_boundary() {
+        let window_system = mock();
+        let settings = Settings {
+            jitter_window: Duration::from_millis(50),
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+
+        engine.handle_mouse_move();
+        window_system.set_cursor_window(Hwnd(2));
+        engine.handle_mouse_move();
+
+        assert_eq!(window_system.raised(), vec![Hwnd(1)]);
+        assert_eq!(
+            engine.last_decision().map(|decision| &decision.reason),
+            Some(&DecisionReason::Jitter)
+        );
+
+        // the window is raised once the cursor has committed to it
+        std::thread::sleep(Duration::from_millis(60));
+        engine.handle_mouse_move();
+        assert_eq!(window_system.raised(), vec![Hwnd(1), Hwnd(2)]);
+    }
+
+    #[test]
+    fn waits_for_delay_before_raising() {
+        let window_system = mock();
+        let settings = Settings {
+            delay: Duration::from_millis(20),
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+
+        assert_eq!(engine.evaluate(), None);
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(engine.evaluate(), Some(Hwnd(1)));
+    }
+
+    #[test]
+    fn throttles_evaluations_on_battery() {
+        let window_system = mock();
+        window_system.set_on_battery(true);
+
+        let settings = Settings {
+            battery_interval: Duration::from_millis(20),
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+
+        engine.handle_mouse_move();
+        assert_eq!(window_system.raised(), vec![Hwnd(1)]);
+
+        window_system.set_cursor_window(Hwnd(2));
+        engine.handle_mouse_move();
+        assert_eq!(window_system.raised(), vec![Hwnd(1)]);
+        assert!(engine.deferred_until().is_some());
+
+        std::thread::sleep(Duration::from_millis(30));
+        engine.handle_mouse_move();
+        assert_eq!(window_system.raised(), vec![Hwnd(1), Hwnd(2)]);
+        assert_eq!(engine.deferred_until(), None);
+    }
+
+    #[test]
+    fn samples_movements_while_catching_up_with_a_backlog() {
+        let window_system = mock();
+        let mut engine = Engine::new(&window_system, vec![], Settings::default());
+
+        assert!(engine.sample_movements());
+        assert!(!engine.sample_movements());
+
+        engine.handle_mouse_move();
+        assert_eq!(window_system.raised(), vec![Hwnd(1)]);
+
+        window_system.set_cursor_window(Hwnd(2));
+        engine.handle_mouse_move();
+        assert_eq!(window_system.raised(), vec![Hwnd(1)]);
+        assert!(engine.deferred_until().is_some());
+
+        std::thread::sleep(Engine::<&MockWindowSystem>::SAMPLING_INTERVAL);
+        engine.handle_mouse_move();
+        assert_eq!(window_system.raised(), vec![Hwnd(1), Hwnd(2)]);
+        assert_eq!(engine.deferred_until(), None);
+    }
+
+    #[test]
+    fn waits_for_cursor_to_move_after_being_warped() {
+        let window_system = mock();
+        window_system.set_cursor_pos(100, 100);
+
+        let settings = Settings {
+            teleport_distance: 500,
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+        engine.handle_mouse_move();
+        assert_eq!(window_system.raised(), vec![Hwnd(1)]);
+
+        window_system.set_foreground_window(Hwnd(2));
+        window_system.set_cursor_pos(1500, 100);
+        engine.handle_mouse_move();
+        assert_eq!(window_system.raised(), vec![Hwnd(1)]);
+
+        window_system.set_cursor_pos(1510, 100);
+        engine.handle_mouse_move();
+        assert_eq!(window_system.raised(), vec![Hwnd(1), Hwnd(1)]);
+    }
+
+    #[test]
+    fn waits_for_cursor_to_travel_inside_window() {
+        let window_system = mock();
+        window_system.set_cursor_pos(100, 100);
+
+        let settings = Settings {
+            hysteresis: 20,
+            ..Default::default()
+        };
+
+        let mut engine = Engine::new(&window_system, vec![], settings);
+
+        // entering the window only records where the cursor crossed its edge
+        assert_eq!(engine.evaluate(), None);
+
+        window_system.set_cursor_pos(110, 110);
+        assert_eq!(engine.evaluate(), None);
+
+        window_system.set_cursor_pos(100, 120);
+        assert_eq!(engine.evaluate(), Some(Hwnd(1)));
+    }
+}
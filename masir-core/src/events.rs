@@ -0,0 +1,46 @@
+//! Events which are broadcast to subscribers of the running instance, such as
+//! status bars and dashboards
+
+use crate::decision::Decision;
+use crate::hwnd::Hwnd;
+use serde::Deserialize;
+use serde::Serialize;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+
+static SUBSCRIBERS: Mutex<Vec<Sender<Event>>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "content")]
+pub enum Event {
+    /// A window was raised
+    Raised(Hwnd),
+    /// The decision on whether to raise the window under the cursor changed
+    Decision(Decision),
+    Paused,
+    Resumed,
+    /// The config was reloaded
+    Reloaded,
+}
+
+/// Returns a receiver of every event from now on, which unsubscribes when it
+/// is dropped
+pub fn subscribe() -> Receiver<Event> {
+    let (sender, receiver) = mpsc::channel();
+    SUBSCRIBERS
+        .lock()
+        .expect("could not lock subscribers")
+        .push(sender);
+
+    receiver
+}
+
+/// Sends an event to every subscriber, dropping those which have gone away
+pub fn emit(event: Event) {
+    SUBSCRIBERS
+        .lock()
+        .expect("could not lock subscribers")
+        .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+}
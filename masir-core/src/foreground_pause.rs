@@ -0,0 +1,107 @@
+//! Pauses masir while a window matching one of the pause rules is in the
+//! foreground, such as a remote desktop client or a game running in a window,
+//! and resumes once it loses the foreground
+
+use crate::config::IgnoreRule;
+use crate::config::Settings;
+use crate::eligibility::matching_rule;
+use crate::hwnd::Hwnd;
+use crate::pause;
+use crate::winapi;
+use crate::window_system::Win32;
+use crate::window_system::WindowSystem;
+use std::sync::Mutex;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::Accessibility::HWINEVENTHOOK;
+use windows::Win32::UI::WindowsAndMessaging::EVENT_SYSTEM_FOREGROUND;
+
+static RULES: Mutex<Vec<IgnoreRule>> = Mutex::new(Vec::new());
+
+/// Records the pause rules of the settings which the event loop is using,
+/// checking the current foreground window against them straight away
+pub fn set_rules(settings: &Settings) {
+    *RULES.lock().expect("could not lock pause rules") = settings.pause_rules.clone();
+
+    if let Ok(hwnd) = winapi::foreground_window() {
+        check(hwnd);
+    }
+}
+
+/// Spawns a thread which hooks foreground changes to pause and resume
+pub fn listen_for_foreground_changes() {
+    std::thread::spawn(|| {
+        match winapi::set_win_event_hook(
+            EVENT_SYSTEM_FOREGROUND,
+            EVENT_SYSTEM_FOREGROUND,
+            Some(on_foreground),
+        ) {
+            Ok(_hook) => winapi::pump_messages(),
+            Err(error) => tracing::error!("failed to listen for foreground changes: {error}"),
+        }
+    });
+}
+
+unsafe extern "system" fn on_foreground(
+    _hook: HWINEVENTHOOK,
+    _event: u32,
+    hwnd: HWND,
+    _id_object: i32,
+    _id_child: i32,
+    _id_event_thread: u32,
+    _event_time: u32,
+) {
+    check(Hwnd::from(hwnd));
+}
+
+fn check(hwnd: Hwnd) {
+    let rules = RULES.lock().expect("could not lock pause rules");
+    apply(&Win32, hwnd, &rules);
+}
+
+/// Pauses if the window matches one of the rules, or resumes otherwise
+fn apply<W: WindowSystem>(window_system: &W, hwnd: Hwnd, rules: &[IgnoreRule]) {
+    // a reload which removes every rule must still lift a pause they caused
+    if rules.is_empty() {
+        pause::set_foreground_rule(None);
+        return;
+    }
+
+    let rule = window_system
+        .class(hwnd)
+        .ok()
+        .and_then(|class| matching_rule(window_system, hwnd, &class, rules))
+        .map(|rule| rule.id().to_string());
+
+    pause::set_foreground_rule(rule);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ApplicationIdentifier;
+    use crate::config::MatchingRule;
+    use crate::config::MatchingStrategy;
+    use crate::window_system::MockWindow;
+    use crate::window_system::MockWindowSystem;
+
+    #[test]
+    fn resumes_when_the_rules_are_removed() {
+        let window_system = MockWindowSystem::new(Hwnd(1), Hwnd(1)).with_window(
+            Hwnd(1),
+            MockWindow::new("TscShellContainerClass").with_exe("mstsc.exe"),
+        );
+
+        let rules = vec![IgnoreRule::compile(&MatchingRule {
+            kind: ApplicationIdentifier::Exe,
+            id: "mstsc.exe".to_string(),
+            matching_strategy: MatchingStrategy::Equals,
+        })
+        .unwrap()];
+
+        apply(&window_system, Hwnd(1), &rules);
+        assert!(pause::is_paused());
+
+        apply(&window_system, Hwnd(1), &[]);
+        assert!(!pause::is_paused());
+    }
+}
@@ -0,0 +1,86 @@
+//! Integration with GlazeWM, which masir can delegate focusing to over its
+//! WebSocket IPC server so that the two don't fight over focus tracking
+
+use crate::hwnd::Hwnd;
+use color_eyre::eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::net::TcpStream;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::Message;
+use tungstenite::WebSocket;
+
+const IPC_URL: &str = "ws://localhost:6123";
+
+#[derive(Deserialize)]
+struct Response<T> {
+    data: Option<T>,
+    error: Option<String>,
+    success: bool,
+}
+
+#[derive(Deserialize)]
+struct Windows {
+    windows: Vec<Window>,
+}
+
+#[derive(Deserialize)]
+struct Window {
+    id: String,
+    handle: Hwnd,
+}
+
+/// Asks GlazeWM to focus a window which it manages, failing if it doesn't
+/// manage the window
+pub fn focus(hwnd: Hwnd) -> Result<()> {
+    let (mut socket, _) = tungstenite::connect(IPC_URL)
+        .wrap_err_with(|| format!("could not connect to GlazeWM at {IPC_URL}"))?;
+
+    // glazewm addresses windows by container id rather than by hwnd
+    let windows: Windows = request(&mut socket, "query windows")?
+        .ok_or_else(|| eyre!("GlazeWM did not return any windows"))?;
+
+    let window = windows
+        .windows
+        .iter()
+        .find(|window| window.handle == hwnd)
+        .ok_or_else(|| eyre!("hwnd {hwnd} is not managed by GlazeWM"))?;
+
+    request::<serde_json::Value>(
+        &mut socket,
+        &format!("command focus --container-id {}", window.id),
+    )?;
+
+    let _ = socket.close(None);
+
+    Ok(())
+}
+
+/// Sends a message to GlazeWM and returns the data of its response
+fn request<T: DeserializeOwned>(
+    socket: &mut WebSocket<MaybeTlsStream<TcpStream>>,
+    message: &str,
+) -> Result<Option<T>> {
+    socket.send(Message::Text(message.into()))?;
+
+    loop {
+        // nothing is subscribed to, so the only text messages are responses
+        let Message::Text(text) = socket.read()? else {
+            continue;
+        };
+
+        let response: Response<T> = serde_json::from_str(&text)
+            .wrap_err_with(|| format!("could not parse the response to {message}"))?;
+
+        return if response.success {
+            Ok(response.data)
+        } else {
+            Err(eyre!(
+                "GlazeWM failed to handle {message}: {}",
+                response.error.unwrap_or_default()
+            ))
+        };
+    }
+}
@@ -0,0 +1,31 @@
+//! The different ways of finding the window under the cursor, since no single
+//! API agrees with what the user sees for every application
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum HitTest {
+    /// The deepest window at the cursor position, skipping windows which
+    /// report themselves as transparent to hit testing
+    #[default]
+    WindowFromPoint,
+    /// Descend from the desktop through the children at the cursor position,
+    /// optionally skipping invisible windows and windows with the
+    /// WS_EX_TRANSPARENT style, such as click-through overlays
+    ChildWindowFromPointEx {
+        #[serde(default)]
+        skip_invisible: bool,
+        #[serde(default)]
+        skip_transparent: bool,
+    },
+    /// Descend from the desktop through the children at the cursor position,
+    /// where group boxes never hide the controls inside them
+    RealChildWindowFromPoint,
+    /// Resolve the UI Automation element at the cursor position to the window
+    /// hosting it, which handles accessibility hosts and out-of-process
+    /// content such as WebView2 islands, at the cost of a cross-process call
+    /// on every movement
+    UiAutomation,
+}
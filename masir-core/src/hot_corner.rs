@@ -0,0 +1,180 @@
+//! A screen corner which toggles the pause state when the cursor rests in it
+
+use crate::winapi;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use std::time::Duration;
+use std::time::Instant;
+use windows::Win32::Foundation::POINT;
+use windows::Win32::Foundation::RECT;
+
+/// How close to the corner of a monitor the cursor has to be, in pixels
+const CORNER_SIZE: i32 = 4;
+/// How long the cursor has to rest in the corner if no delay is given
+const DEFAULT_DELAY: Duration = Duration::from_millis(500);
+
+/// A corner of a monitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HotCornerConfig {
+    /// The corner of any monitor which toggles the pause state
+    pub corner: Corner,
+    /// Time in milliseconds that the cursor must rest in the corner (default:
+    /// 500)
+    pub delay: Option<u64>,
+}
+
+impl HotCornerConfig {
+    pub fn compile(&self) -> HotCorner {
+        HotCorner {
+            corner: self.corner,
+            delay: self.delay.map_or(DEFAULT_DELAY, Duration::from_millis),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HotCorner {
+    pub corner: Corner,
+    pub delay: Duration,
+}
+
+impl HotCorner {
+    /// Returns true if the point is in this corner of the monitor, where the
+    /// right and bottom edges of the monitor are exclusive
+    fn contains(&self, monitor: RECT, point: POINT) -> bool {
+        let left = point.x < monitor.left + CORNER_SIZE;
+        let right = point.x >= monitor.right - CORNER_SIZE;
+        let top = point.y < monitor.top + CORNER_SIZE;
+        let bottom = point.y >= monitor.bottom - CORNER_SIZE;
+
+        match self.corner {
+            Corner::TopLeft => top && left,
+            Corner::TopRight => top && right,
+            Corner::BottomLeft => bottom && left,
+            Corner::BottomRight => bottom && right,
+        }
+    }
+}
+
+/// Tracks how long the cursor has been resting in the hot corner
+#[derive(Debug)]
+pub struct HotCornerDwell {
+    hot_corner: HotCorner,
+    entered: Option<Instant>,
+    /// Whether the current visit has already toggled the pause state, so that
+    /// the cursor has to leave before it can toggle again
+    triggered: bool,
+}
+
+impl HotCornerDwell {
+    pub fn new(hot_corner: HotCorner) -> Self {
+        Self {
+            hot_corner,
+            entered: None,
+            triggered: false,
+        }
+    }
+
+    /// Checks whether the cursor is in the hot corner of the monitor it is on
+    pub fn update(&mut self, at: Instant) {
+        let in_corner = winapi::cursor_pos().is_ok_and(|cursor_pos| {
+            winapi::monitor_rect(cursor_pos)
+                .is_ok_and(|monitor| self.hot_corner.contains(monitor, cursor_pos))
+        });
+
+        self.set_in_corner(at, in_corner);
+    }
+
+    fn set_in_corner(&mut self, at: Instant, in_corner: bool) {
+        if !in_corner {
+            self.entered = None;
+            self.triggered = false;
+        } else if self.entered.is_none() {
+            self.entered = Some(at);
+        }
+    }
+
+    /// Returns when the cursor will have rested in the corner for long enough
+    pub fn deadline(&self) -> Option<Instant> {
+        self.entered
+            .filter(|_| !self.triggered)
+            .map(|entered| entered + self.hot_corner.delay)
+    }
+
+    /// Returns true once per visit when the cursor has rested in the corner
+    /// for long enough
+    pub fn take_triggered(&mut self, now: Instant) -> bool {
+        if self.deadline().is_some_and(|deadline| now >= deadline) {
+            self.triggered = true;
+            return true;
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONITOR: RECT = RECT {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1080,
+    };
+
+    fn hot_corner(corner: Corner) -> HotCorner {
+        HotCornerConfig {
+            corner,
+            delay: None,
+        }
+        .compile()
+    }
+
+    #[test]
+    fn contains_points_in_the_corner() {
+        let top_right = hot_corner(Corner::TopRight);
+
+        assert!(top_right.contains(MONITOR, POINT { x: 1919, y: 0 }));
+        assert!(top_right.contains(MONITOR, POINT { x: 1917, y: 2 }));
+        assert!(!top_right.contains(MONITOR, POINT { x: 1919, y: 10 }));
+        assert!(!top_right.contains(MONITOR, POINT { x: 0, y: 0 }));
+    }
+
+    #[test]
+    fn triggers_once_per_visit_after_the_delay() {
+        let start = Instant::now();
+        let mut dwell = HotCornerDwell::new(hot_corner(Corner::BottomLeft));
+
+        dwell.set_in_corner(start, true);
+        assert!(!dwell.take_triggered(start + Duration::from_millis(499)));
+        assert!(dwell.take_triggered(start + Duration::from_millis(500)));
+        assert!(!dwell.take_triggered(start + Duration::from_millis(2000)));
+
+        dwell.set_in_corner(start + Duration::from_millis(2000), false);
+        dwell.set_in_corner(start + Duration::from_millis(2100), true);
+        assert!(dwell.take_triggered(start + Duration::from_millis(2600)));
+    }
+
+    #[test]
+    fn leaving_early_cancels_the_toggle() {
+        let start = Instant::now();
+        let mut dwell = HotCornerDwell::new(hot_corner(Corner::TopLeft));
+
+        dwell.set_in_corner(start, true);
+        dwell.set_in_corner(start + Duration::from_millis(300), false);
+
+        assert_eq!(dwell.deadline(), None);
+        assert!(!dwell.take_triggered(start + Duration::from_millis(600)));
+    }
+}
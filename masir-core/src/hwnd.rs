@@ -0,0 +1,56 @@
+//! A typed window handle, so that handles can't be mixed up with other
+//! integers such as process ids or used as the wrong cache key, along with the
+//! window queries and actions which are commonly made on a single window
+
+use crate::winapi;
+use color_eyre::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::GA_ROOT;
+use windows::Win32::UI::WindowsAndMessaging::WINDOW_EX_STYLE;
+
+/// A window handle, which is (de)serialized and displayed as the number which
+/// Windows uses for it, as tiling window managers write them
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Hwnd(pub isize);
+
+impl Hwnd {
+    /// Returns the real class name of the window
+    pub fn class(self) -> Result<String> {
+        winapi::real_window_class_w(self)
+    }
+
+    /// Returns the top-level window which the window belongs to
+    pub fn root(self) -> Result<Hwnd> {
+        winapi::get_ancestor(self, GA_ROOT)
+    }
+
+    /// Returns the extended window styles of the window
+    pub fn ex_style(self) -> WINDOW_EX_STYLE {
+        winapi::get_window_ex_style(self)
+    }
+}
+
+impl Display for Hwnd {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<HWND> for Hwnd {
+    fn from(hwnd: HWND) -> Self {
+        Self(hwnd.0 as isize)
+    }
+}
+
+impl From<Hwnd> for HWND {
+    fn from(hwnd: Hwnd) -> Self {
+        HWND(hwnd.0 as *mut core::ffi::c_void)
+    }
+}
@@ -0,0 +1,666 @@
+//! Integration with komorebi beyond its hwnds file: delegating focus to it over
+//! its socket, querying its state for floating windows and workspaces in
+//! monocle mode (through `komorebic state` on versions which don't reply over
+//! the socket), pausing and resuming along with it, and importing the ignore
+//! rules from its application-specific configuration (applications.json or
+//! applications.yaml), so that tray popups, overlays and other windows which
+//! komorebi leaves alone don't have to be listed twice
+//!
+//! Only rules which masir can express are imported: composite rules, rules
+//! matching on the executable path and negated matching strategies are skipped.
+
+use crate::config::ApplicationIdentifier;
+use crate::config::MatchingRule;
+use crate::config::MatchingStrategy;
+use crate::hwnd::Hwnd;
+use crate::pause;
+use color_eyre::eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io::Read;
+use std::io::Write;
+use std::net::Shutdown;
+use std::os::windows::process::CommandExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::sync::Once;
+use std::time::Duration;
+use std::time::Instant;
+use uds_windows::UnixListener;
+use uds_windows::UnixStream;
+use windows::Win32::System::Threading::CREATE_NO_WINDOW;
+
+/// The socket message which asks komorebi to focus the window with the given
+/// hwnd, updating its focused monitor, workspace and container along the way
+const FOCUS_MESSAGE: &str = "FocusWindowHwnd";
+
+/// The socket message which asks komorebi to reply with its window manager
+/// state on the same connection
+const STATE_MESSAGE: &str = "State";
+
+/// How long to wait for komorebi to reply with its state
+const STATE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How often `komorebic state` is run once komorebi hasn't replied with its
+/// state over the socket, which is less often than the socket is asked since
+/// each query starts a process
+const KOMOREBIC_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long komorebi is left alone after it failed to reply over the socket,
+/// before it is asked there again in case it has since been updated
+const SOCKET_REPROBE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The name of the socket next to komorebi's own which komorebi connects to
+/// with each notification once masir has subscribed to them
+const SUBSCRIBER_SOCKET: &str = "masir.sock";
+
+/// How often the subscription is renewed, since komorebi forgets its
+/// subscribers when it restarts
+const SUBSCRIBE_INTERVAL: Duration = Duration::from_secs(10);
+
+// masir was paused because komorebi was, and should resume along with it
+static PAUSED_WITH_KOMOREBI: AtomicBool = AtomicBool::new(false);
+
+// what `komorebic state` last reported, kept up to date by a thread which is
+// only started once komorebi fails to reply over the socket
+static KOMOREBIC_SNAPSHOT: Mutex<Option<Snapshot>> = Mutex::new(None);
+static KOMOREBIC_POLLER: Once = Once::new();
+// when komorebi last failed to reply over the socket
+static SOCKET_FAILED_AT: Mutex<Option<Instant>> = Mutex::new(None);
+
+#[derive(Deserialize)]
+struct Application {
+    // komorebi called these float rules before they were renamed
+    #[serde(alias = "float")]
+    ignore: Option<Vec<Rule>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Rule {
+    Simple(Identifier),
+    Composite(Vec<Identifier>),
+}
+
+// kinds and strategies are kept as strings since komorebi supports some which
+// masir doesn't, and those shouldn't make the whole file unreadable
+#[derive(Deserialize)]
+struct Identifier {
+    kind: String,
+    id: String,
+    matching_strategy: Option<String>,
+}
+
+/// Whether windows which komorebi floats above its tiles can be raised
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum FloatingPolicy {
+    /// Raise floating windows like any other window
+    #[default]
+    Eligible,
+    /// Raise floating windows only once the cursor has remained over them for
+    /// this many milliseconds, or the configured delay if that is longer
+    Delayed(u64),
+    /// Never raise floating windows
+    Ignored,
+}
+
+/// The windows which masir treats differently because of how komorebi has
+/// laid them out
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    /// Windows on the focused workspace of a monitor while another container
+    /// on that workspace is in monocle mode
+    pub behind_monocle: Vec<Hwnd>,
+    /// Windows which komorebi floats above its tiles, on any workspace
+    pub floating: Vec<Hwnd>,
+    /// The focus follows mouse implementation which komorebi has enabled, if
+    /// any, which is either Komorebi or Windows
+    pub focus_follows_mouse: Option<String>,
+    /// The name of the workspace which each window is on, for workspaces
+    /// which have a name
+    pub workspaces: HashMap<Hwnd, String>,
+}
+
+#[derive(Deserialize)]
+struct Notification {
+    state: NotificationState,
+}
+
+#[derive(Deserialize)]
+struct NotificationState {
+    is_paused: bool,
+}
+
+// only the parts of komorebi's state which masir looks at, where every ring
+// serializes its elements alongside the index of the focused element
+#[derive(Deserialize)]
+struct State {
+    monitors: Ring<Monitor>,
+    #[serde(default)]
+    focus_follows_mouse: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Ring<T> {
+    elements: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct FocusedRing<T> {
+    elements: Vec<T>,
+    focused: usize,
+}
+
+#[derive(Deserialize)]
+struct Monitor {
+    workspaces: FocusedRing<Workspace>,
+}
+
+#[derive(Deserialize)]
+struct Workspace {
+    #[serde(default)]
+    name: Option<String>,
+    containers: Ring<Container>,
+    monocle_container: Option<Container>,
+    #[serde(default)]
+    floating_windows: Windows,
+}
+
+// older versions of komorebi serialize floating windows as a plain list
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Windows {
+    Ring(Ring<Window>),
+    List(Vec<Window>),
+}
+
+impl Default for Windows {
+    fn default() -> Self {
+        Self::List(vec![])
+    }
+}
+
+impl Windows {
+    fn hwnds(&self) -> impl Iterator<Item = Hwnd> + '_ {
+        let windows = match self {
+            Self::Ring(ring) => &ring.elements,
+            Self::List(list) => list,
+        };
+
+        windows.iter().map(|window| window.hwnd)
+    }
+}
+
+#[derive(Deserialize)]
+struct Container {
+    windows: Ring<Window>,
+}
+
+#[derive(Deserialize)]
+struct Window {
+    hwnd: Hwnd,
+}
+
+/// Returns the path of the socket which komorebi listens for commands on
+pub fn socket_path() -> PathBuf {
+    dirs::data_local_dir()
+        .expect("there is no local data directory")
+        .join("komorebi")
+        .join("komorebi.sock")
+}
+
+/// Asks komorebi to focus a window which it manages
+pub fn focus(hwnd: Hwnd) -> Result<()> {
+    send(&serde_json::json!({ "type": FOCUS_MESSAGE, "content": hwnd }))
+}
+
+/// Sends a message to komorebi without waiting for a reply
+fn send(message: &serde_json::Value) -> Result<()> {
+    let socket = socket_path();
+    let mut stream = UnixStream::connect(&socket)
+        .wrap_err_with(|| format!("could not connect to komorebi at {}", socket.display()))?;
+
+    stream.write_all(message.to_string().as_bytes())?;
+
+    Ok(())
+}
+
+/// Spawns threads which subscribe to komorebi's notifications, pausing masir
+/// when komorebi is paused and resuming it when komorebi is resumed
+///
+/// A pause which was started or ended through masir itself is left alone:
+/// resuming komorebi only resumes masir if it was paused along with komorebi.
+pub fn listen_for_pause_changes() {
+    std::thread::spawn(|| {
+        if let Err(error) = receive_notifications() {
+            tracing::error!("failed to listen for komorebi notifications: {error}");
+        }
+    });
+
+    std::thread::spawn(|| {
+        let message =
+            serde_json::json!({ "type": "AddSubscriberSocket", "content": SUBSCRIBER_SOCKET });
+
+        loop {
+            if let Err(error) = send(&message) {
+                tracing::debug!("failed to subscribe to komorebi notifications: {error}");
+            }
+
+            std::thread::sleep(SUBSCRIBE_INTERVAL);
+        }
+    });
+}
+
+fn receive_notifications() -> Result<()> {
+    let path = socket_path().with_file_name(SUBSCRIBER_SOCKET);
+
+    // a socket file left behind by a previous instance can't be bound again
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)
+        .wrap_err_with(|| format!("could not listen on {}", path.display()))?;
+
+    let mut was_paused = false;
+
+    for stream in listener.incoming() {
+        let mut raw = String::new();
+        if let Err(error) = stream.and_then(|mut stream| stream.read_to_string(&mut raw)) {
+            tracing::warn!("failed to read komorebi notification: {error}");
+            continue;
+        }
+
+        let is_paused = match serde_json::from_str::<Notification>(&raw) {
+            Ok(notification) => notification.state.is_paused,
+            Err(error) => {
+                tracing::warn!("failed to parse komorebi notification: {error}");
+                continue;
+            }
+        };
+
+        if is_paused != was_paused {
+            mirror_pause(is_paused);
+            was_paused = is_paused;
+        }
+    }
+
+    Ok(())
+}
+
+fn mirror_pause(is_paused: bool) {
+    if is_paused {
+        if !pause::is_paused() {
+            tracing::info!("komorebi was paused");
+            PAUSED_WITH_KOMOREBI.store(true, Ordering::SeqCst);
+            pause::pause(None);
+        }
+    } else if PAUSED_WITH_KOMOREBI.swap(false, Ordering::SeqCst) {
+        tracing::info!("komorebi was resumed");
+        pause::resume();
+    }
+}
+
+/// Asks komorebi for its state and returns the windows which masir treats
+/// differently because of it, waiting for `komorebic state` on versions of
+/// komorebi which don't reply with their state over the socket
+pub fn snapshot() -> Result<Snapshot> {
+    query_socket().or_else(|error| {
+        tracing::debug!("falling back to komorebic: {error}");
+        query_komorebic()
+    })
+}
+
+/// Returns the windows which masir treats differently because of komorebi's
+/// state without waiting on komorebic, for the event loop
+///
+/// Once komorebi fails to reply over the socket, it is only asked there again
+/// every minute, and is queried with `komorebic state` in the background
+/// instead, where the latest result of that is returned.
+pub fn latest_snapshot() -> Result<Snapshot> {
+    let mut failed_at = SOCKET_FAILED_AT
+        .lock()
+        .expect("could not lock komorebi socket state");
+
+    if failed_at.is_none_or(|failed_at| failed_at.elapsed() >= SOCKET_REPROBE_INTERVAL) {
+        match query_socket() {
+            Ok(snapshot) => {
+                *failed_at = None;
+                return Ok(snapshot);
+            }
+            Err(error) => {
+                tracing::debug!("falling back to komorebic: {error}");
+                *failed_at = Some(Instant::now());
+            }
+        }
+    }
+
+    drop(failed_at);
+    komorebic_snapshot()
+}
+
+fn query_socket() -> Result<Snapshot> {
+    let socket = socket_path();
+    let mut stream = UnixStream::connect(&socket)
+        .wrap_err_with(|| format!("could not connect to komorebi at {}", socket.display()))?;
+    stream.set_read_timeout(Some(STATE_TIMEOUT))?;
+
+    let message = serde_json::json!({ "type": STATE_MESSAGE });
+    stream.write_all(format!("{message}\n").as_bytes())?;
+    stream.shutdown(Shutdown::Write)?;
+
+    let mut raw = String::new();
+    stream
+        .read_to_string(&mut raw)
+        .wrap_err("could not read komorebi's state")?;
+
+    let state: State = serde_json::from_str(&raw).wrap_err("could not parse komorebi's state")?;
+
+    Ok(summarize(&state))
+}
+
+fn komorebic_snapshot() -> Result<Snapshot> {
+    KOMOREBIC_POLLER.call_once(|| {
+        std::thread::spawn(|| loop {
+            let snapshot = query_komorebic()
+                .inspect_err(|error| tracing::debug!("failed to query komorebic: {error}"))
+                .ok();
+
+            *KOMOREBIC_SNAPSHOT
+                .lock()
+                .expect("could not lock komorebic snapshot") = snapshot;
+
+            std::thread::sleep(KOMOREBIC_INTERVAL);
+        });
+    });
+
+    KOMOREBIC_SNAPSHOT
+        .lock()
+        .expect("could not lock komorebic snapshot")
+        .clone()
+        .ok_or_else(|| eyre!("komorebic has not reported komorebi's state"))
+}
+
+fn query_komorebic() -> Result<Snapshot> {
+    let output = Command::new("komorebic")
+        .arg("state")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW.0)
+        .output()
+        .wrap_err("could not run komorebic")?;
+
+    if !output.status.success() {
+        return Err(eyre!("komorebic state exited with {}", output.status));
+    }
+
+    let state: State =
+        serde_json::from_slice(&output.stdout).wrap_err("could not parse komorebic's output")?;
+
+    Ok(summarize(&state))
+}
+
+fn summarize(state: &State) -> Snapshot {
+    let monitors = &state.monitors.elements;
+
+    let behind_monocle = monitors
+        .iter()
+        .filter_map(|monitor| monitor.workspaces.elements.get(monitor.workspaces.focused))
+        .filter(|workspace| workspace.monocle_container.is_some())
+        .flat_map(|workspace| &workspace.containers.elements)
+        .flat_map(|container| &container.windows.elements)
+        .map(|window| window.hwnd)
+        .collect();
+
+    let floating = monitors
+        .iter()
+        .flat_map(|monitor| &monitor.workspaces.elements)
+        .flat_map(|workspace| workspace.floating_windows.hwnds())
+        .collect();
+
+    let mut workspaces = HashMap::new();
+    for workspace in monitors
+        .iter()
+        .flat_map(|monitor| &monitor.workspaces.elements)
+    {
+        let Some(name) = &workspace.name else {
+            continue;
+        };
+
+        let hwnds = workspace
+            .containers
+            .elements
+            .iter()
+            .chain(&workspace.monocle_container)
+            .flat_map(|container| &container.windows.elements)
+            .map(|window| window.hwnd)
+            .chain(workspace.floating_windows.hwnds());
+
+        for hwnd in hwnds {
+            workspaces.insert(hwnd, name.clone());
+        }
+    }
+
+    Snapshot {
+        behind_monocle,
+        floating,
+        workspaces,
+        focus_follows_mouse: state.focus_follows_mouse.clone(),
+    }
+}
+
+/// Reads the ignore rules of every application in komorebi's applications
+/// file, which is parsed as YAML if it has a .yaml or .yml extension and as
+/// JSON otherwise
+pub fn ignore_rules(path: &Path) -> Result<Vec<MatchingRule>> {
+    let raw = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("could not read {}", path.display()))?;
+
+    let is_yaml = path
+        .extension()
+        .is_some_and(|extension| extension == "yaml" || extension == "yml");
+
+    let applications: BTreeMap<String, Application> = if is_yaml {
+        serde_yaml::from_str(&raw)
+            .wrap_err_with(|| format!("could not parse {}", path.display()))?
+    } else {
+        serde_json::from_str(&raw)
+            .wrap_err_with(|| format!("could not parse {}", path.display()))?
+    };
+
+    Ok(convert(applications))
+}
+
+fn convert(applications: BTreeMap<String, Application>) -> Vec<MatchingRule> {
+    let mut rules = vec![];
+
+    for (name, application) in applications {
+        for rule in application.ignore.into_iter().flatten() {
+            match rule {
+                Rule::Simple(identifier) => match matching_rule(&identifier) {
+                    Some(rule) => rules.push(rule),
+                    None => tracing::debug!(
+                        "skipping komorebi ignore rule for {name}: {} {} is not supported",
+                        identifier.kind,
+                        identifier.matching_strategy.as_deref().unwrap_or("Legacy")
+                    ),
+                },
+                Rule::Composite(identifiers) => tracing::debug!(
+                    "skipping komorebi ignore rule for {name}: composite rules of {} identifiers are not supported",
+                    identifiers.len()
+                ),
+            }
+        }
+    }
+
+    rules
+}
+
+fn matching_rule(identifier: &Identifier) -> Option<MatchingRule> {
+    let kind = match identifier.kind.as_str() {
+        "Exe" => ApplicationIdentifier::Exe,
+        "Class" => ApplicationIdentifier::Class,
+        "Title" => ApplicationIdentifier::Title,
+        _ => return None,
+    };
+
+    let (id, matching_strategy) = match identifier.matching_strategy.as_deref() {
+        // komorebi's legacy matching compares executables exactly, and classes
+        // and titles by their start or end
+        None | Some("Legacy") if kind == ApplicationIdentifier::Exe => {
+            (identifier.id.clone(), MatchingStrategy::Equals)
+        }
+        None | Some("Legacy") => {
+            let id = regex::escape(&identifier.id);
+            (format!("^{id}|{id}$"), MatchingStrategy::Regex)
+        }
+        Some("Equals") => (identifier.id.clone(), MatchingStrategy::Equals),
+        Some("StartsWith") => (identifier.id.clone(), MatchingStrategy::StartsWith),
+        Some("EndsWith") => (identifier.id.clone(), MatchingStrategy::EndsWith),
+        Some("Contains") => (identifier.id.clone(), MatchingStrategy::Contains),
+        Some("Regex") => (identifier.id.clone(), MatchingStrategy::Regex),
+        _ => return None,
+    };
+
+    Some(MatchingRule {
+        kind,
+        id,
+        matching_strategy,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(rules: &[MatchingRule]) -> Vec<(&str, MatchingStrategy)> {
+        rules
+            .iter()
+            .map(|rule| (rule.id.as_str(), rule.matching_strategy))
+            .collect()
+    }
+
+    #[test]
+    fn imports_supported_ignore_rules() {
+        let raw = r#"{
+            "1Password": {
+                "ignore": [
+                    { "kind": "Exe", "id": "1Password.exe", "matching_strategy": "Equals" }
+                ]
+            },
+            "Steam": {
+                "float": [
+                    { "kind": "Title", "id": "Steam - News" },
+                    { "kind": "Path", "id": "C:\\Steam", "matching_strategy": "Equals" },
+                    { "kind": "Class", "id": "SDL_app", "matching_strategy": "DoesNotEqual" },
+                    [
+                        { "kind": "Exe", "id": "steam.exe", "matching_strategy": "Equals" },
+                        { "kind": "Class", "id": "SDL_app", "matching_strategy": "Equals" }
+                    ]
+                ],
+                "tray_and_multi_window": [
+                    { "kind": "Exe", "id": "steamwebhelper.exe", "matching_strategy": "Equals" }
+                ]
+            }
+        }"#;
+
+        let applications = serde_json::from_str(raw).unwrap();
+        let rules = convert(applications);
+
+        assert_eq!(
+            ids(&rules),
+            vec![
+                ("1Password.exe", MatchingStrategy::Equals),
+                ("^Steam \\- News|Steam \\- News$", MatchingStrategy::Regex),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_windows_behind_monocle_containers_and_floating_windows() {
+        let raw = r#"{
+            "monitors": {
+                "elements": [
+                    {
+                        "workspaces": {
+                            "elements": [
+                                {
+                                    "name": "media",
+                                    "containers": { "elements": [], "focused": 0 },
+                                    "monocle_container": null,
+                                    "floating_windows": { "elements": [{ "hwnd": 6 }], "focused": 0 }
+                                },
+                                {
+                                    "containers": {
+                                        "elements": [
+                                            { "windows": { "elements": [{ "hwnd": 1 }, { "hwnd": 2 }], "focused": 0 } },
+                                            { "windows": { "elements": [{ "hwnd": 3 }], "focused": 0 } }
+                                        ],
+                                        "focused": 0
+                                    },
+                                    "monocle_container": { "windows": { "elements": [{ "hwnd": 4 }], "focused": 0 } }
+                                }
+                            ],
+                            "focused": 1
+                        }
+                    },
+                    {
+                        "workspaces": {
+                            "elements": [
+                                {
+                                    "containers": {
+                                        "elements": [{ "windows": { "elements": [{ "hwnd": 5 }], "focused": 0 } }],
+                                        "focused": 0
+                                    },
+                                    "monocle_container": null,
+                                    "floating_windows": [{ "hwnd": 7 }]
+                                }
+                            ],
+                            "focused": 0
+                        }
+                    }
+                ],
+                "focused": 0
+            },
+            "focus_follows_mouse": "Komorebi"
+        }"#;
+
+        let state = serde_json::from_str(raw).unwrap();
+
+        let snapshot = summarize(&state);
+
+        assert_eq!(snapshot.behind_monocle, vec![Hwnd(1), Hwnd(2), Hwnd(3)]);
+        assert_eq!(snapshot.floating, vec![Hwnd(6), Hwnd(7)]);
+        assert_eq!(snapshot.focus_follows_mouse.as_deref(), Some("Komorebi"));
+        assert_eq!(
+            snapshot.workspaces,
+            HashMap::from([(Hwnd(6), String::from("media"))])
+        );
+    }
+
+    #[test]
+    fn imports_yaml() {
+        let raw = "
+Flow Launcher:
+  ignore:
+    - kind: Class
+      id: HwndWrapper[Flow.Launcher
+      matching_strategy: StartsWith
+";
+
+        let applications = serde_yaml::from_str(raw).unwrap();
+        let rules = convert(applications);
+
+        assert_eq!(
+            ids(&rules),
+            vec![("HwndWrapper[Flow.Launcher", MatchingStrategy::StartsWith)]
+        );
+    }
+}
@@ -0,0 +1,484 @@
+//! The focus follows mouse engine behind masir.
+//!
+//! This crate contains the event loop which listens for mouse movements, the
+//! eligibility rules and caches used to decide whether the window under the
+//! cursor should be raised, and the Win32 wrappers used along the way. It can
+//! be embedded by window managers which want to offer focus follows mouse
+//! without shelling out to the masir binary.
+//!
+//! ```no_run
+//! use masir_core::config::Config;
+//!
+//! # fn main() -> color_eyre::Result<()> {
+//! let settings = Config::load(&Config::path())?.settings(None)?;
+//! masir_core::listen_for_movements(vec![], settings)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use config::Settings;
+use engine::Engine;
+use hot_corner::HotCornerDwell;
+use metrics::Counter;
+use mouse::MouseButtons;
+use shake::ShakeDetector;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use window_system::Win32;
+use winput::message_loop;
+use winput::message_loop::Event;
+use winput::Action;
+use winput::Button;
+
+pub mod cache;
+pub mod capture;
+pub mod command;
+pub mod competitors;
+pub mod config;
+pub mod crash;
+pub mod decision;
+pub mod desktop;
+pub mod display;
+pub mod eligibility;
+pub mod engine;
+pub mod events;
+pub mod foreground_pause;
+pub mod glazewm;
+pub mod hit_test;
+pub mod hot_corner;
+pub mod hwnd;
+pub mod komorebi;
+pub mod metrics;
+pub mod mouse;
+pub mod move_size;
+pub mod notification;
+pub mod pause;
+pub mod plugin;
+pub mod portable;
+pub mod raise;
+pub mod raise_hook;
+pub mod raise_log;
+pub mod reload;
+pub mod schedule;
+pub mod script;
+pub mod session;
+pub mod shake;
+pub mod stall;
+pub mod status;
+pub mod style;
+pub mod virtual_desktop;
+pub mod winapi;
+pub mod window_system;
+
+/// How many inputs can be waiting for the worker before it is considered to
+/// have fallen behind
+const BACKLOG_DEPTH: usize = 64;
+/// How long an input can wait for the worker before it is considered to have
+/// fallen behind
+const BACKLOG_AGE: Duration = Duration::from_millis(100);
+
+/// How many times in a row restarting the message loop can fail before masir
+/// exits
+const MAX_FAILED_RESTARTS: u32 = 5;
+/// How long to wait before retrying a failed message loop restart, doubled
+/// after every further failure
+const RESTART_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Passes requests from other threads to the worker, such as undoing a raise
+static INPUTS: Mutex<Option<mpsc::Sender<(Instant, Input)>>> = Mutex::new(None);
+
+/// What the input thread passes on to the worker thread, in the order in
+/// which it was received
+enum Input {
+    Moved,
+    Button(Action, Button),
+    /// The message loop was restarted after a session change, and button
+    /// releases may have been lost along the way
+    Restarted,
+    /// Focus should go back to the window which had it before the last raise
+    Undo,
+}
+
+/// Asks the event loop to give focus back to the window which had it before
+/// the last raise, which is handled right away rather than at the next
+/// movement
+pub fn undo_last_raise() {
+    if let Some(sender) = &*INPUTS.lock().expect("could not lock inputs") {
+        let _ = sender.send((Instant::now(), Input::Undo));
+    }
+}
+
+/// Spawns a thread which listens for mouse movements and a worker thread which
+/// raises eligible windows under the cursor
+///
+/// The thread draining the mouse hook's events only passes them on, so that
+/// slow eligibility checks never back up input processing. Movements which
+/// queue up while the worker is busy are evaluated once, and if inputs wait
+/// too long for the worker, movements are only sampled for a while.
+///
+/// If any `hwnds` files are given, only windows whose HWNDs are listed in at
+/// least one of them (as written by a tiling window manager) are eligible to
+/// be raised.
+///
+/// Fails if the mouse hook can't be installed.
+pub fn listen_for_movements(hwnds: Vec<PathBuf>, settings: Settings) -> Result<()> {
+    let mut receiver = Some(
+        message_loop::start()
+            .map_err(|error| eyre!("could not start winput message loop: {error:?}"))?,
+    );
+
+    move_size::listen_for_move_size_events();
+    capture::listen_for_capture_overlays();
+    desktop::listen_for_desktop_switches();
+    display::listen_for_display_changes();
+    foreground_pause::listen_for_foreground_changes();
+    session::listen_for_session_changes();
+    style::listen_for_style_changes();
+    virtual_desktop::listen_for_virtual_desktop_switches();
+
+    if settings.komorebi_pause_sync {
+        komorebi::listen_for_pause_changes();
+    }
+
+    let (sender, inputs) = mpsc::channel();
+    *INPUTS.lock().expect("could not lock inputs") = Some(sender.clone());
+    let restart_requested = Arc::new(AtomicBool::new(false));
+
+    std::thread::spawn({
+        let restart_requested = Arc::clone(&restart_requested);
+
+        let mut failed_restarts = 0;
+        let mut session_changed = false;
+
+        move || loop {
+            // the raw input registration often stops receiving events after a
+            // remote desktop reconnection, so it is torn down and registered
+            // again whenever the session changes
+            session_changed |= session::take_changed();
+            if session_changed || restart_requested.swap(false, Ordering::SeqCst) {
+                tracing::info!("restarting the message loop");
+
+                drop(receiver.take());
+                match message_loop::start() {
+                    Ok(restarted) => {
+                        receiver = Some(restarted);
+                        failed_restarts = 0;
+                    }
+                    Err(error) => {
+                        failed_restarts += 1;
+
+                        // carrying on without a message loop would leave masir
+                        // looking alive while never raising anything again
+                        if failed_restarts >= MAX_FAILED_RESTARTS {
+                            tracing::error!(
+                                "could not restart winput message loop after {failed_restarts} attempts, exiting: {error:?}"
+                            );
+                            std::process::exit(1);
+                        }
+
+                        let backoff = RESTART_BACKOFF * 2u32.pow(failed_restarts - 1);
+                        tracing::error!(
+                            "could not restart winput message loop, retrying in {backoff:?}: {error:?}"
+                        );
+
+                        std::thread::sleep(backoff);
+                        restart_requested.store(true, Ordering::SeqCst);
+                        continue;
+                    }
+                }
+
+                if std::mem::take(&mut session_changed)
+                    && sender.send((Instant::now(), Input::Restarted)).is_err()
+                {
+                    break;
+                }
+            }
+
+            let Some(receiver) = &receiver else {
+                continue;
+            };
+
+            // wake up regularly so that session changes are handled even when
+            // no events are being received
+            let Some(event) = receiver.next_event_timeout(Duration::from_secs(1)) else {
+                continue;
+            };
+
+            // a burst of raw movements is forwarded as a single movement, as
+            // only the cursor's latest position is ever evaluated
+            let mut pending = Some(event);
+            let mut moved = false;
+
+            while let Some(event) = pending.take().or_else(|| receiver.try_next_event()) {
+                let input = match event {
+                    Event::MouseMoveRelative { .. } => {
+                        if moved {
+                            metrics::increment(Counter::Coalesced);
+                        }
+
+                        moved = true;
+                        continue;
+                    }
+                    Event::MouseButton { action, button } => Input::Button(action, button),
+                    _ => continue,
+                };
+
+                if std::mem::take(&mut moved)
+                    && sender.send((Instant::now(), Input::Moved)).is_err()
+                {
+                    return;
+                }
+
+                if sender.send((Instant::now(), input)).is_err() {
+                    return;
+                }
+            }
+
+            if moved && sender.send((Instant::now(), Input::Moved)).is_err() {
+                break;
+            }
+        }
+    });
+
+    std::thread::spawn(move || {
+        status::set_rules(&settings);
+        foreground_pause::set_rules(&settings);
+        let mut shake = settings.shake_to_pause.then(ShakeDetector::default);
+        let mut hot_corner = settings.hot_corner.map(HotCornerDwell::new);
+        let mut engine = Engine::new(Win32, hwnds, settings);
+        let mut mouse_buttons = MouseButtons::default();
+
+        // after a desktop switch, whether events are still being delivered is
+        // checked against where the cursor was the last time no events came
+        let mut awaiting_events = false;
+        let mut idle_cursor_pos = None;
+
+        loop {
+            let mut iteration = stall::Iteration::start();
+
+            // the previous engine is dropped along with its caches, pending
+            // raises and remembered raise strategies
+            if let Some((hwnds, settings)) = reload::take() {
+                tracing::info!("reloaded the config");
+                status::set_rules(&settings);
+                foreground_pause::set_rules(&settings);
+                events::emit(events::Event::Reloaded);
+                shake = settings.shake_to_pause.then(ShakeDetector::default);
+                hot_corner = settings.hot_corner.map(HotCornerDwell::new);
+                engine = Engine::new(Win32, hwnds, settings);
+            }
+
+            iteration.stage("reload");
+
+            for hwnd in style::take_changed() {
+                engine.invalidate(hwnd);
+            }
+
+            iteration.stage("style invalidation");
+
+            if cache::take_clear_requested() {
+                tracing::info!("clearing caches on request");
+                engine.clear_caches();
+            }
+
+            iteration.stage("cache clear");
+
+            // which windows are visible changes wholesale, and stale entries
+            // would cause mis-raises right after switching
+            if virtual_desktop::take_switched() {
+                engine.forget_other_virtual_desktops();
+            }
+
+            iteration.stage("virtual desktop switch");
+
+            if display::take_changed() {
+                engine.handle_display_change();
+            }
+
+            iteration.stage("display change");
+
+            if hot_corner
+                .as_mut()
+                .is_some_and(|hot_corner| hot_corner.take_triggered(Instant::now()))
+            {
+                tracing::info!("the cursor rested in the hot corner, toggling the pause state");
+                pause::toggle();
+                notification::confirm(if pause::is_paused() {
+                    "Paused"
+                } else {
+                    "Resumed"
+                });
+            }
+
+            iteration.stage("hot corner");
+
+            // button releases are lost while the secure desktop is up, which
+            // would otherwise leave masir waiting for a drag to end forever
+            if desktop::take_switched() {
+                mouse_buttons = MouseButtons::default();
+                awaiting_events = true;
+                idle_cursor_pos = None;
+            }
+
+            // wake up regularly so that reloads and cache clears are handled
+            // even when no events are being received, or sooner when a
+            // movement was skipped to save battery, the cursor is resting in
+            // the hot corner or a timed pause is about to elapse
+            let timeout = engine
+                .deferred_until()
+                .into_iter()
+                .chain(hot_corner.as_ref().and_then(HotCornerDwell::deadline))
+                .chain(pause::deadline())
+                .min()
+                .map_or(Duration::from_secs(1), |until| {
+                    until.saturating_duration_since(Instant::now())
+                });
+
+            let received = inputs.recv_timeout(timeout);
+            iteration.idle();
+
+            let first = match received {
+                Ok(first) => first,
+                Err(RecvTimeoutError::Timeout) => {
+                    // checking resumes once a timed pause has elapsed
+                    if pause::deadline().is_some_and(|deadline| deadline <= Instant::now()) {
+                        pause::is_paused();
+                    }
+
+                    if engine.deferred_until().is_some() {
+                        if is_suspended(&mut mouse_buttons) {
+                            engine.clear_deferred();
+                        } else {
+                            engine.handle_mouse_move();
+                        }
+                    }
+
+                    iteration.stage("deferred movement");
+
+                    if awaiting_events {
+                        let cursor_pos = winapi::cursor_pos().ok().map(|point| (point.x, point.y));
+
+                        if idle_cursor_pos.is_some() && cursor_pos != idle_cursor_pos {
+                            tracing::warn!(
+                                "the cursor moved without any events after a desktop switch, restarting the message loop"
+                            );
+
+                            restart_requested.store(true, Ordering::SeqCst);
+                            awaiting_events = false;
+                        }
+
+                        idle_cursor_pos = cursor_pos;
+                    }
+
+                    iteration.stage("idle cursor check");
+                    iteration.finish();
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            awaiting_events = false;
+
+            let batch = std::iter::once(first)
+                .chain(inputs.try_iter())
+                .collect::<Vec<_>>();
+
+            // under heavy load, evaluating every movement would only make the
+            // worker fall further behind and the cursor feel heavier
+            let (queued_at, _) = batch[0];
+            let age = queued_at.elapsed();
+            if (batch.len() >= BACKLOG_DEPTH || age >= BACKLOG_AGE) && engine.sample_movements() {
+                tracing::warn!(
+                    "falling behind with {} queued inputs, the oldest after {age:?}, sampling movements for a while",
+                    batch.len()
+                );
+            }
+
+            // everything which queued up while the last movement was being
+            // evaluated is handled in order, where movements between button
+            // changes are evaluated once at the cursor's latest position
+            let mut moved = false;
+
+            for (_, input) in batch {
+                if moved && !matches!(input, Input::Moved) {
+                    handle_mouse_move(&mut engine, &mut mouse_buttons);
+                    moved = false;
+                }
+
+                match input {
+                    Input::Moved => {
+                        if moved {
+                            metrics::increment(Counter::Coalesced);
+                        }
+
+                        moved = true;
+
+                        // gestures are recognized while paused, so that they
+                        // can resume as well
+                        if let Some(hot_corner) = &mut hot_corner {
+                            hot_corner.update(Instant::now());
+                        }
+
+                        // fast drags and aero shake on a title bar look just
+                        // like a shake, but are made with a button held down
+                        if let (Some(shake), Ok(cursor_pos)) = (&mut shake, winapi::cursor_pos()) {
+                            if !mouse_buttons.is_any_down()
+                                && shake.update(Instant::now(), cursor_pos.x)
+                            {
+                                tracing::info!("the cursor was shaken, toggling the pause state");
+                                pause::toggle();
+                                notification::confirm(if pause::is_paused() {
+                                    "Paused"
+                                } else {
+                                    "Resumed"
+                                });
+                            }
+                        }
+                    }
+                    Input::Button(Action::Press, button) => mouse_buttons.press(button),
+                    Input::Button(Action::Release, button) => mouse_buttons.release(button),
+                    Input::Restarted => {
+                        tracing::info!("clearing caches after the message loop was restarted");
+                        engine.clear_caches();
+                        mouse_buttons = MouseButtons::default();
+                    }
+                    Input::Undo => engine.undo_last_raise(),
+                }
+            }
+
+            if moved {
+                handle_mouse_move(&mut engine, &mut mouse_buttons);
+            }
+
+            iteration.stage("evaluation");
+            iteration.finish();
+        }
+    });
+
+    Ok(())
+}
+
+/// Evaluates the latest movement unless movements are suspended
+fn handle_mouse_move(engine: &mut Engine<Win32>, mouse_buttons: &mut MouseButtons) {
+    if !is_suspended(mouse_buttons) {
+        engine.handle_mouse_move();
+    }
+}
+
+/// Returns true if mouse movements shouldn't be evaluated right now, e.g.
+/// while resizing windows or dragging and dropping files
+fn is_suspended(mouse_buttons: &mut MouseButtons) -> bool {
+    mouse_buttons.is_any_down()
+        || move_size::is_in_progress()
+        || pause::is_paused()
+        || capture::is_active()
+}
@@ -0,0 +1,269 @@
+//! Internal counters and evaluation latencies, for users who like to graph
+//! their desktop automation
+
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+/// How many of the most recent latencies are kept for percentiles
+const LATENCY_SAMPLES: usize = 1024;
+/// How far back events are counted for the events per second rate
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+static COUNTERS: [AtomicU64; 7] = [const { AtomicU64::new(0) }; 7];
+// the hits and misses of each cache
+static CACHE_COUNTERS: [[AtomicU64; 2]; 5] = [const { [const { AtomicU64::new(0) }; 2] }; 5];
+static LATENCIES: Mutex<VecDeque<Duration>> = Mutex::new(VecDeque::new());
+static RAISE_LATENCIES: Mutex<VecDeque<Duration>> = Mutex::new(VecDeque::new());
+// the number of events in each second, keyed by when that second started
+static EVENT_BUCKETS: Mutex<VecDeque<(Instant, u64)>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Clone, Copy)]
+pub enum Counter {
+    /// Mouse movements which were evaluated
+    Events = 0,
+    /// Windows which were successfully raised
+    Raises = 1,
+    /// Windows which could not be raised with any strategy
+    RaiseFailures = 2,
+    /// Window properties or eligibility found in the cache
+    CacheHits = 3,
+    /// Window properties or eligibility which had to be looked up
+    CacheMisses = 4,
+    /// Mouse movements which were skipped because a later one was queued
+    Coalesced = 5,
+    /// Iterations of the event loop which took longer than the stall threshold
+    Stalls = 6,
+}
+
+/// The caches which lookups are counted for
+#[derive(Debug, Clone, Copy)]
+pub enum Cache {
+    /// The top-level window of a window
+    Root = 0,
+    /// The class name of a window
+    Class = 1,
+    /// Windows known to belong to the same application
+    Pair = 2,
+    /// Whether a window is eligible to be raised and to lose focus
+    Eligibility = 3,
+    /// Whether a window is visible, not minimized and on screen
+    Visibility = 4,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metrics {
+    pub events: u64,
+    pub raises: u64,
+    pub raise_failures: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub coalesced: u64,
+    pub stalls: u64,
+    /// Percentiles of recent evaluation latencies in microseconds
+    pub evaluation_latency_us: Percentiles,
+    /// Hits and misses of each cache, which add up to the totals above
+    pub caches: CacheMetrics,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheMetrics {
+    pub root: CacheCounts,
+    pub class: CacheCounts,
+    pub pair: CacheCounts,
+    pub eligibility: CacheCounts,
+    pub visibility: CacheCounts,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheCounts {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub p50: u64,
+    pub p90: u64,
+    pub p95: u64,
+    pub p99: u64,
+}
+
+pub fn increment(counter: Counter) {
+    COUNTERS[counter as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts a lookup in the given cache towards both its own hits or misses and
+/// the totals across all caches
+pub fn record_cache_lookup(cache: Cache, hit: bool) {
+    increment(if hit {
+        Counter::CacheHits
+    } else {
+        Counter::CacheMisses
+    });
+
+    CACHE_COUNTERS[cache as usize][usize::from(!hit)].fetch_add(1, Ordering::Relaxed);
+}
+
+/// Records how long evaluating a mouse movement took, which also counts the
+/// movement towards the events per second rate
+pub fn record_evaluation(latency: Duration) {
+    record_sample(&LATENCIES, latency);
+
+    let now = Instant::now();
+    let mut buckets = EVENT_BUCKETS.lock().expect("could not lock metrics");
+
+    match buckets.back_mut() {
+        Some((start, count)) if now.duration_since(*start) < Duration::from_secs(1) => {
+            *count += 1;
+        }
+        _ => buckets.push_back((now, 1)),
+    }
+
+    while buckets
+        .front()
+        .is_some_and(|(start, _)| now.duration_since(*start) > RATE_WINDOW)
+    {
+        buckets.pop_front();
+    }
+}
+
+/// Records how long it took from the cursor entering a window until that
+/// window was raised
+pub fn record_raise(latency: Duration) {
+    record_sample(&RAISE_LATENCIES, latency);
+}
+
+/// Returns percentiles of recent raise latencies in microseconds, from the
+/// cursor entering a window until it was raised
+pub fn raise_latency() -> Percentiles {
+    percentiles(&RAISE_LATENCIES)
+}
+
+/// Returns how many iterations of the event loop took longer than the stall
+/// threshold
+pub fn stalls() -> u64 {
+    COUNTERS[Counter::Stalls as usize].load(Ordering::Relaxed)
+}
+
+/// Returns how many mouse movements were evaluated per second, on average
+/// over the last few seconds
+pub fn events_per_second() -> u64 {
+    let buckets = EVENT_BUCKETS.lock().expect("could not lock metrics");
+    rate(&buckets, Instant::now())
+}
+
+/// Returns a snapshot of the current metrics
+pub fn current() -> Metrics {
+    let count = |counter: Counter| COUNTERS[counter as usize].load(Ordering::Relaxed);
+    let cache = |cache: Cache| {
+        let [hits, misses] = &CACHE_COUNTERS[cache as usize];
+
+        CacheCounts {
+            hits: hits.load(Ordering::Relaxed),
+            misses: misses.load(Ordering::Relaxed),
+        }
+    };
+
+    Metrics {
+        events: count(Counter::Events),
+        raises: count(Counter::Raises),
+        raise_failures: count(Counter::RaiseFailures),
+        cache_hits: count(Counter::CacheHits),
+        cache_misses: count(Counter::CacheMisses),
+        coalesced: count(Counter::Coalesced),
+        stalls: count(Counter::Stalls),
+        evaluation_latency_us: percentiles(&LATENCIES),
+        caches: CacheMetrics {
+            root: cache(Cache::Root),
+            class: cache(Cache::Class),
+            pair: cache(Cache::Pair),
+            eligibility: cache(Cache::Eligibility),
+            visibility: cache(Cache::Visibility),
+        },
+    }
+}
+
+fn record_sample(samples: &Mutex<VecDeque<Duration>>, latency: Duration) {
+    let mut samples = samples.lock().expect("could not lock metrics");
+    if samples.len() == LATENCY_SAMPLES {
+        samples.pop_front();
+    }
+
+    samples.push_back(latency);
+}
+
+fn percentiles(samples: &Mutex<VecDeque<Duration>>) -> Percentiles {
+    let mut latencies: Vec<Duration> = samples
+        .lock()
+        .expect("could not lock metrics")
+        .iter()
+        .copied()
+        .collect();
+
+    latencies.sort();
+
+    Percentiles {
+        p50: percentile(&latencies, 50),
+        p90: percentile(&latencies, 90),
+        p95: percentile(&latencies, 95),
+        p99: percentile(&latencies, 99),
+    }
+}
+
+/// Returns the given percentile of sorted latencies in microseconds, using the
+/// nearest-rank method
+fn percentile(sorted: &[Duration], percentile: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+
+    let rank = (percentile * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1].as_micros() as u64
+}
+
+/// Returns the average number of events per second in the buckets which
+/// started within the rate window
+fn rate(buckets: &VecDeque<(Instant, u64)>, now: Instant) -> u64 {
+    let events: u64 = buckets
+        .iter()
+        .filter(|(start, _)| now.duration_since(*start) <= RATE_WINDOW)
+        .map(|(_, count)| count)
+        .sum();
+
+    events / RATE_WINDOW.as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let latencies: Vec<Duration> = (1..=100).map(Duration::from_micros).collect();
+
+        assert_eq!(percentile(&latencies, 50), 50);
+        assert_eq!(percentile(&latencies, 95), 95);
+        assert_eq!(percentile(&latencies, 99), 99);
+        assert_eq!(percentile(&latencies[..1], 90), 1);
+        assert_eq!(percentile(&[], 50), 0);
+    }
+
+    #[test]
+    fn events_per_second_over_rate_window() {
+        let now = Instant::now();
+        let buckets = VecDeque::from([
+            (now - Duration::from_secs(30), 1000),
+            (now - Duration::from_secs(5), 150),
+            (now, 50),
+        ]);
+
+        assert_eq!(rate(&buckets, now), 20);
+        assert_eq!(rate(&VecDeque::new(), now), 0);
+    }
+}
@@ -0,0 +1,113 @@
+//! Mouse button state as reported by the input hook, resynchronized with the
+//! physical button state in case the hook misses a release
+
+use crate::winapi;
+use std::collections::HashSet;
+use std::time::Duration;
+use std::time::Instant;
+use windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY;
+use windows::Win32::UI::Input::KeyboardAndMouse::VK_LBUTTON;
+use windows::Win32::UI::Input::KeyboardAndMouse::VK_MBUTTON;
+use windows::Win32::UI::Input::KeyboardAndMouse::VK_RBUTTON;
+use windows::Win32::UI::Input::KeyboardAndMouse::VK_XBUTTON1;
+use windows::Win32::UI::Input::KeyboardAndMouse::VK_XBUTTON2;
+use winput::Button;
+
+/// Tracks which mouse buttons are held down
+///
+/// Release events can be missed (e.g. while the secure desktop is shown or if
+/// the hook times out), which would otherwise leave a button stuck down and
+/// stop windows from being raised until restart.
+#[derive(Debug)]
+pub struct MouseButtons {
+    pressed: HashSet<Button>,
+    last_sync: Instant,
+}
+
+impl Default for MouseButtons {
+    fn default() -> Self {
+        Self {
+            pressed: HashSet::new(),
+            last_sync: Instant::now(),
+        }
+    }
+}
+
+impl MouseButtons {
+    /// How often buttons believed to be held down are checked against the
+    /// physical button state
+    const SYNC_INTERVAL: Duration = Duration::from_millis(500);
+
+    pub fn press(&mut self, button: Button) {
+        if self.pressed.is_empty() {
+            self.last_sync = Instant::now();
+        }
+
+        self.pressed.insert(button);
+    }
+
+    pub fn release(&mut self, button: Button) {
+        self.pressed.remove(&button);
+    }
+
+    /// Returns true if any mouse button is held down, periodically dropping
+    /// buttons which are no longer physically held down
+    pub fn is_any_down(&mut self) -> bool {
+        if !self.pressed.is_empty() && self.last_sync.elapsed() >= Self::SYNC_INTERVAL {
+            self.sync(|button| winapi::is_key_down(virtual_key(button)));
+        }
+
+        !self.pressed.is_empty()
+    }
+
+    fn sync(&mut self, is_down: impl Fn(Button) -> bool) {
+        self.last_sync = Instant::now();
+        self.pressed.retain(|button| {
+            let is_down = is_down(*button);
+            if !is_down {
+                tracing::warn!("missed release of mouse button {button:?}, resynchronizing");
+            }
+
+            is_down
+        });
+    }
+}
+
+fn virtual_key(button: Button) -> VIRTUAL_KEY {
+    match button {
+        Button::Left => VK_LBUTTON,
+        Button::Right => VK_RBUTTON,
+        Button::Middle => VK_MBUTTON,
+        Button::X1 => VK_XBUTTON1,
+        Button::X2 => VK_XBUTTON2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_each_button_separately() {
+        let mut buttons = MouseButtons::default();
+
+        buttons.press(Button::Left);
+        buttons.press(Button::Right);
+        buttons.release(Button::Left);
+        assert!(buttons.is_any_down());
+
+        buttons.release(Button::Right);
+        assert!(!buttons.is_any_down());
+    }
+
+    #[test]
+    fn sync_drops_missed_releases() {
+        let mut buttons = MouseButtons::default();
+
+        buttons.press(Button::Left);
+        buttons.press(Button::Middle);
+        buttons.sync(|button| button == Button::Middle);
+
+        assert_eq!(buttons.pressed, HashSet::from([Button::Middle]));
+    }
+}
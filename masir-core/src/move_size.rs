@@ -0,0 +1,66 @@
+//! Tracks whether a window is being moved or resized by the system, which also
+//! covers keyboard-initiated moves and Aero Snap where no mouse button is held
+
+use crate::hwnd::Hwnd;
+use crate::winapi;
+use std::sync::Mutex;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::Accessibility::HWINEVENTHOOK;
+use windows::Win32::UI::WindowsAndMessaging::EVENT_SYSTEM_MOVESIZEEND;
+use windows::Win32::UI::WindowsAndMessaging::EVENT_SYSTEM_MOVESIZESTART;
+
+/// The window which is in a move or resize loop, if any
+static MOVING: Mutex<Option<Hwnd>> = Mutex::new(None);
+
+/// Returns true if a window is currently in a move or resize loop
+pub fn is_in_progress() -> bool {
+    let mut moving = MOVING.lock().expect("could not lock move/size state");
+
+    // the end of a loop can be missed, such as when the window is destroyed
+    // while it is being dragged, which would otherwise suspend masir for good
+    if moving.is_some_and(|hwnd| !winapi::is_window(hwnd)) {
+        *moving = None;
+    }
+
+    moving.is_some()
+}
+
+/// Spawns a thread which hooks the start and end of system move and resize
+/// loops for every window
+pub fn listen_for_move_size_events() {
+    std::thread::spawn(|| {
+        match winapi::set_win_event_hook(
+            EVENT_SYSTEM_MOVESIZESTART,
+            EVENT_SYSTEM_MOVESIZEEND,
+            Some(on_move_size_event),
+        ) {
+            Ok(_hook) => winapi::pump_messages(),
+            Err(error) => tracing::error!("failed to listen for move/size events: {error}"),
+        }
+    });
+}
+
+unsafe extern "system" fn on_move_size_event(
+    _hook: HWINEVENTHOOK,
+    event: u32,
+    hwnd: HWND,
+    _id_object: i32,
+    _id_child: i32,
+    _id_event_thread: u32,
+    _event_time: u32,
+) {
+    let hwnd = Hwnd::from(hwnd);
+    let mut moving = MOVING.lock().expect("could not lock move/size state");
+
+    if event == EVENT_SYSTEM_MOVESIZESTART {
+        tracing::debug!("move/size loop started for hwnd {hwnd}");
+        *moving = Some(hwnd);
+    } else {
+        tracing::debug!("move/size loop ended for hwnd {hwnd}");
+
+        // the end of another window's loop doesn't end the one in progress
+        if *moving == Some(hwnd) {
+            *moving = None;
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Toast notifications shown when masir changes state, so that users know why
+//! focus behaviour has just changed
+
+use color_eyre::Result;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use windows::core::HSTRING;
+use windows::Data::Xml::Dom::XmlDocument;
+use windows::Win32::System::WinRT::RoInitialize;
+use windows::Win32::System::WinRT::RO_INIT_MULTITHREADED;
+use windows::UI::Notifications::ToastNotification;
+use windows::UI::Notifications::ToastNotificationManager;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The application id of PowerShell, which is registered on every Windows
+/// install and allows an unpackaged application to show toasts without
+/// installing a Start menu shortcut, at the cost of the toasts being shown as
+/// coming from PowerShell (as the README notes next to the option)
+const APP_ID: &str =
+    r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Shows a toast with the given message if notifications are enabled
+pub fn notify(message: &str) {
+    if !ENABLED.load(Ordering::SeqCst) {
+        return;
+    }
+
+    // showing a toast can take a while, and we don't want to hold up the
+    // thread which handles mouse movements
+    let message = message.to_string();
+    std::thread::spawn(move || {
+        if let Err(error) = show(&message) {
+            tracing::warn!("failed to show notification: {error}");
+        }
+    });
+}
+
+/// Shows a toast confirming a change the user made without looking at masir,
+/// unless notifications are enabled and it has been announced already
+pub fn confirm(message: &str) {
+    if ENABLED.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let message = message.to_string();
+    std::thread::spawn(move || {
+        if let Err(error) = show(&message) {
+            tracing::warn!("failed to show notification: {error}");
+        }
+    });
+}
+
+fn show(message: &str) -> Result<()> {
+    unsafe { RoInitialize(RO_INIT_MULTITHREADED) }?;
+
+    let xml = XmlDocument::new()?;
+    xml.LoadXml(&HSTRING::from(format!(
+        r#"<toast><visual><binding template="ToastGeneric"><text>masir</text><text>{}</text></binding></visual></toast>"#,
+        escape(message)
+    )))?;
+
+    let toast = ToastNotification::CreateToastNotification(&xml)?;
+    ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(APP_ID))?.Show(&toast)?;
+
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,161 @@
+//! Global pause state consulted by the event loop before raising windows
+
+use crate::events;
+use crate::events::Event;
+use crate::notification;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+static PAUSE: Mutex<Option<PauseState>> = Mutex::new(None);
+/// The id of the pause rule which the foreground window matches, while that
+/// keeps masir paused
+static FOREGROUND_RULE: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy)]
+enum PauseState {
+    Indefinite,
+    Until(Instant),
+}
+
+pub fn pause(duration: Option<Duration>) {
+    let state = match duration {
+        None => {
+            tracing::info!("pausing until resumed");
+            notification::notify("Paused until resumed");
+            PauseState::Indefinite
+        }
+        Some(duration) => match Instant::now().checked_add(duration) {
+            Some(deadline) => {
+                let formatted = humantime::format_duration(duration);
+                tracing::info!("pausing for {formatted}");
+                notification::notify(&format!("Paused for {formatted}"));
+                PauseState::Until(deadline)
+            }
+            // a duration this long would never elapse anyway
+            None => {
+                tracing::info!("pause duration is too long, pausing until resumed");
+                notification::notify("Paused until resumed");
+                PauseState::Indefinite
+            }
+        },
+    };
+
+    *PAUSE.lock().expect("could not lock pause state") = Some(state);
+    events::emit(Event::Paused);
+}
+
+/// Resumes, including while the foreground window matches a pause rule, until
+/// the foreground changes
+pub fn resume() {
+    let paused_by_rule = FOREGROUND_RULE
+        .lock()
+        .expect("could not lock pause state")
+        .take()
+        .is_some();
+
+    let mut pause = PAUSE.lock().expect("could not lock pause state");
+    if pause.take().is_some() || paused_by_rule {
+        tracing::info!("resuming");
+        notification::notify("Resumed");
+        events::emit(Event::Resumed);
+    }
+}
+
+/// Resumes if paused, or otherwise pauses until resumed
+pub fn toggle() {
+    if is_paused() {
+        resume();
+    } else {
+        pause(None);
+    }
+}
+
+/// Pauses while the foreground window matches the pause rule with the given
+/// id, or resumes if there is none
+pub fn set_foreground_rule(rule: Option<String>) {
+    let mut foreground_rule = FOREGROUND_RULE.lock().expect("could not lock pause state");
+    if *foreground_rule == rule {
+        return;
+    }
+
+    let was_paused_by_rule = foreground_rule.is_some();
+    *foreground_rule = rule;
+
+    // a pause or resume is only announced if it changes whether masir is
+    // paused, not when moving between two windows matching pause rules
+    match (&*foreground_rule, was_paused_by_rule) {
+        (Some(rule), false) => {
+            tracing::info!("pausing while a window matching {rule} is in the foreground");
+            if !is_paused_manually() {
+                notification::notify(&format!("Paused while {rule} is in the foreground"));
+                events::emit(Event::Paused);
+            }
+        }
+        (None, true) => {
+            tracing::info!("resuming as the foreground window no longer matches a pause rule");
+            if !is_paused_manually() {
+                notification::notify("Resumed");
+                events::emit(Event::Resumed);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns true if masir is currently paused, either by hand or by a pause
+/// rule
+pub fn is_paused() -> bool {
+    is_paused_manually()
+        || FOREGROUND_RULE
+            .lock()
+            .expect("could not lock pause state")
+            .is_some()
+}
+
+/// Returns when a timed pause will elapse, so that the event loop can wake up
+/// to resume on time rather than on the next mouse movement
+pub fn deadline() -> Option<Instant> {
+    match *PAUSE.lock().expect("could not lock pause state") {
+        Some(PauseState::Until(deadline)) => Some(deadline),
+        _ => None,
+    }
+}
+
+/// Returns true if masir was paused by hand, automatically resuming if the
+/// duration of a timed pause has elapsed
+fn is_paused_manually() -> bool {
+    let mut pause = PAUSE.lock().expect("could not lock pause state");
+
+    match *pause {
+        None => false,
+        Some(PauseState::Indefinite) => true,
+        Some(PauseState::Until(deadline)) => {
+            if Instant::now() < deadline {
+                true
+            } else {
+                tracing::info!("pause duration has elapsed, resuming");
+                notification::notify("Pause duration has elapsed, resumed");
+                *pause = None;
+                events::emit(Event::Resumed);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumes_once_a_timed_pause_elapses() {
+        pause(Some(Duration::from_millis(20)));
+        assert!(is_paused());
+        assert!(deadline().is_some());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(!is_paused());
+        assert_eq!(deadline(), None);
+    }
+}
@@ -0,0 +1,248 @@
+//! WebAssembly plugins which can decide eligibility and choose raise
+//! strategies, so that third parties can extend masir without forking it and
+//! without the safety issues of native DLL plugins
+//!
+//! # ABI (version 1)
+//!
+//! A plugin is a WebAssembly module without imports which exports:
+//!
+//! - `memory`: its linear memory
+//! - `masir_abi_version() -> i32`: must return 1
+//! - `alloc(len: i32) -> i32`: returns a pointer to `len` bytes which masir
+//!   writes a UTF-8 JSON argument into
+//!
+//! and optionally either or both of:
+//!
+//! - `should_raise(ptr: i32, len: i32) -> i32`: receives
+//!   `{"cursor": window, "foreground": window}` and returns 1 to force a
+//!   raise, 0 to veto it, or -1 to leave the decision to the rules
+//! - `raise_strategy(ptr: i32, len: i32) -> i32`: receives the window being
+//!   raised and returns 0 for SendInput, 1 for AltTap, 2 for
+//!   AttachThreadInput, or -1 to use the default order
+//!
+//! where each window is an object with `hwnd`, `class`, `exe` and `title`.
+
+use crate::raise::RaiseStrategy;
+use crate::script::ScriptWindow;
+use color_eyre::eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use serde::Serialize;
+use std::path::Path;
+use wasmi::Engine;
+use wasmi::Linker;
+use wasmi::Memory;
+use wasmi::Module;
+use wasmi::Store;
+use wasmi::TypedFunc;
+
+pub const ABI_VERSION: i32 = 1;
+
+#[derive(Serialize)]
+struct WindowPair<'a> {
+    cursor: &'a ScriptWindow,
+    foreground: &'a ScriptWindow,
+}
+
+pub struct Plugin {
+    name: String,
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    should_raise: Option<TypedFunc<(i32, i32), i32>>,
+    raise_strategy: Option<TypedFunc<(i32, i32), i32>>,
+}
+
+impl Plugin {
+    /// How much fuel a single call may consume, so that a runaway plugin
+    /// can't stall the event loop
+    const FUEL: u64 = 1_000_000;
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .wrap_err_with(|| format!("could not read plugin {}", path.display()))?;
+
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        Self::from_bytes(name, &bytes)
+            .wrap_err_with(|| format!("invalid plugin {}", path.display()))
+    }
+
+    pub fn from_bytes(name: String, bytes: &[u8]) -> Result<Self> {
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, bytes)?;
+        let mut store = Store::new(&engine, ());
+        store.set_fuel(Self::FUEL)?;
+
+        let instance = Linker::<()>::new(&engine)
+            .instantiate(&mut store, &module)?
+            .start(&mut store)?;
+
+        let version = instance
+            .get_typed_func::<(), i32>(&store, "masir_abi_version")?
+            .call(&mut store, ())?;
+
+        if version != ABI_VERSION {
+            return Err(eyre!(
+                "the plugin uses abi version {version}, but only version {ABI_VERSION} is supported"
+            ));
+        }
+
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| eyre!("the plugin does not export its memory"))?;
+
+        let alloc = instance.get_typed_func::<i32, i32>(&store, "alloc")?;
+        let should_raise = instance
+            .get_typed_func::<(i32, i32), i32>(&store, "should_raise")
+            .ok();
+        let raise_strategy = instance
+            .get_typed_func::<(i32, i32), i32>(&store, "raise_strategy")
+            .ok();
+
+        if should_raise.is_none() && raise_strategy.is_none() {
+            return Err(eyre!(
+                "the plugin exports neither should_raise nor raise_strategy"
+            ));
+        }
+
+        Ok(Self {
+            name,
+            store,
+            memory,
+            alloc,
+            should_raise,
+            raise_strategy,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns `Some(true)` if the plugin forces a raise, `Some(false)` if it
+    /// vetoes one, or `None` if the decision is left to the rules
+    pub fn should_raise(
+        &mut self,
+        cursor: &ScriptWindow,
+        foreground: &ScriptWindow,
+    ) -> Result<Option<bool>> {
+        let Some(should_raise) = self.should_raise else {
+            return Ok(None);
+        };
+
+        let argument = serde_json::to_vec(&WindowPair { cursor, foreground })?;
+
+        match self.call(should_raise, &argument)? {
+            -1 => Ok(None),
+            0 => Ok(Some(false)),
+            1 => Ok(Some(true)),
+            other => Err(eyre!("should_raise returned {other}")),
+        }
+    }
+
+    /// Returns the strategy which the plugin prefers for raising the window,
+    /// or `None` if the default order should be used
+    pub fn raise_strategy(&mut self, window: &ScriptWindow) -> Result<Option<RaiseStrategy>> {
+        let Some(raise_strategy) = self.raise_strategy else {
+            return Ok(None);
+        };
+
+        let argument = serde_json::to_vec(window)?;
+
+        match self.call(raise_strategy, &argument)? {
+            -1 => Ok(None),
+            0 => Ok(Some(RaiseStrategy::SendInput)),
+            1 => Ok(Some(RaiseStrategy::AltTap)),
+            2 => Ok(Some(RaiseStrategy::AttachThreadInput)),
+            other => Err(eyre!("raise_strategy returned {other}")),
+        }
+    }
+
+    fn call(&mut self, function: TypedFunc<(i32, i32), i32>, argument: &[u8]) -> Result<i32> {
+        self.store.set_fuel(Self::FUEL)?;
+
+        let len = i32::try_from(argument.len())?;
+        let ptr = self.alloc.call(&mut self.store, len)?;
+        self.memory
+            .write(&mut self.store, ptr as usize, argument)
+            .map_err(|error| eyre!("could not write to plugin memory: {error}"))?;
+
+        Ok(function.call(&mut self.store, (ptr, len))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // vetoes raising away from any foreground window whose json contains
+    // "Zoom", by searching for the byte sequence in the argument
+    const VETO_PLUGIN: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "masir_abi_version") (result i32) i32.const 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 0)
+            (func (export "should_raise") (param $ptr i32) (param $len i32) (result i32)
+                (local $i i32)
+                (block $done
+                    (loop $search
+                        (br_if $done (i32.gt_u (i32.add (local.get $i) (i32.const 4)) (local.get $len)))
+                        (if (i32.eq (i32.load (i32.add (local.get $ptr) (local.get $i))) (i32.const 0x6d6f6f5a))
+                            (then (return (i32.const 0))))
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                        (br $search)))
+                (i32.const -1))
+            (func (export "raise_strategy") (param i32 i32) (result i32) i32.const 2))
+    "#;
+
+    fn window(exe: &str) -> ScriptWindow {
+        ScriptWindow {
+            exe: exe.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn plugin_can_veto_and_choose_strategy() {
+        let mut plugin =
+            Plugin::from_bytes("veto".to_string(), &wat::parse_str(VETO_PLUGIN).unwrap()).unwrap();
+
+        assert_eq!(
+            plugin
+                .should_raise(&window("a.exe"), &window("Zoom.exe"))
+                .unwrap(),
+            Some(false)
+        );
+        assert_eq!(
+            plugin
+                .should_raise(&window("a.exe"), &window("b.exe"))
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            plugin.raise_strategy(&window("a.exe")).unwrap(),
+            Some(RaiseStrategy::AttachThreadInput)
+        );
+    }
+
+    #[test]
+    fn plugin_must_match_abi_version() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "masir_abi_version") (result i32) i32.const 2)
+                (func (export "alloc") (param i32) (result i32) i32.const 0)
+                (func (export "should_raise") (param i32 i32) (result i32) i32.const -1))"#,
+        )
+        .unwrap();
+
+        assert!(Plugin::from_bytes("future".to_string(), &wasm).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+//! Portable mode, where all state is kept next to the executable instead of in
+//! the user's profile, so that masir can be run from a USB stick without being
+//! installed
+//!
+//! Portable mode is enabled explicitly with [`enable`], or automatically when a
+//! `masir.toml` sits next to the executable.
+
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Returns the directory which contains the running executable
+pub fn exe_dir() -> Option<PathBuf> {
+    std::env::current_exe()
+        .ok()?
+        .parent()
+        .map(|dir| dir.to_path_buf())
+}
+
+/// Returns the directory which contains the executable if portable mode is
+/// enabled or a `masir.toml` sits next to the executable
+pub fn dir() -> Option<PathBuf> {
+    let dir = exe_dir()?;
+
+    if ENABLED.load(Ordering::SeqCst) || dir.join("masir.toml").is_file() {
+        Some(dir)
+    } else {
+        None
+    }
+}
+
+/// Returns the directory which the config file is read from
+pub fn config_dir() -> PathBuf {
+    dir().unwrap_or_else(|| {
+        dirs::config_dir()
+            .expect("there is no config directory")
+            .join("masir")
+    })
+}
+
+/// Returns the directory which the ipc socket and any other local state is
+/// written to
+pub fn data_dir() -> PathBuf {
+    dir().unwrap_or_else(|| {
+        dirs::data_local_dir()
+            .expect("there is no local data directory")
+            .join("masir")
+    })
+}
@@ -0,0 +1,279 @@
+//! The different ways of bringing a window to the foreground, since no single
+//! approach gets past the foreground lock for every application
+
+use crate::glazewm;
+use crate::hwnd::Hwnd;
+use crate::komorebi;
+use crate::winapi;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How long a raise may take before the window is treated as unresponsive
+pub const RAISE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A window to raise and where to send the outcome
+type RaiseJob = (Hwnd, RaiseStrategy, Sender<Result<()>>);
+
+static RAISE_WORKER: Mutex<Option<Sender<RaiseJob>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum RaiseStrategy {
+    /// Send an empty input event to our own process to pass the foreground
+    /// lock check, then call SetForegroundWindow
+    SendInput,
+    /// Synthesize a brief Alt key press to pass the foreground lock check,
+    /// then call SetForegroundWindow
+    AltTap,
+    /// Attach to the input queue of the foreground window's thread, then call
+    /// BringWindowToTop and SetForegroundWindow
+    AttachThreadInput,
+    /// Ask komorebi to focus the window over its socket, which keeps its focus
+    /// state, borders and stackbars in sync
+    Komorebi,
+    /// Ask GlazeWM to focus the window over its IPC server, so that it doesn't
+    /// fight masir over focus tracking
+    GlazeWm,
+}
+
+impl RaiseStrategy {
+    /// Returns true if the strategy asks a window manager to focus the window,
+    /// which only works for windows that it manages
+    pub fn is_delegated(self) -> bool {
+        matches!(self, Self::Komorebi | Self::GlazeWm)
+    }
+}
+
+/// A window manager which can be asked to focus the windows it manages instead
+/// of masir raising them itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum FocusDelegate {
+    Komorebi,
+    GlazeWm,
+}
+
+impl From<FocusDelegate> for RaiseStrategy {
+    fn from(delegate: FocusDelegate) -> Self {
+        match delegate {
+            FocusDelegate::Komorebi => Self::Komorebi,
+            FocusDelegate::GlazeWm => Self::GlazeWm,
+        }
+    }
+}
+
+/// The error of a raise which didn't return within [`RAISE_TIMEOUT`], such as
+/// when the window's thread is hung
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RaiseTimedOut;
+
+impl Display for RaiseTimedOut {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the window did not respond within {}ms",
+            RAISE_TIMEOUT.as_millis()
+        )
+    }
+}
+
+impl std::error::Error for RaiseTimedOut {}
+
+/// Raises a window on a dedicated worker thread, giving up after
+/// [`RAISE_TIMEOUT`] so that an unresponsive window can't block the event loop
+pub fn raise_with_timeout(hwnd: Hwnd, strategy: RaiseStrategy) -> Result<()> {
+    let (sender, receiver) = mpsc::channel();
+
+    raise_worker()
+        .send((hwnd, strategy, sender))
+        .map_err(|_| eyre!("the raise worker has stopped"))?;
+
+    match receiver.recv_timeout(RAISE_TIMEOUT) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => {
+            // the worker is stuck until the call returns, if it ever does, so
+            // the next raise gets a fresh one and the stuck one exits after
+            *RAISE_WORKER.lock().expect("could not lock raise worker") = None;
+            Err(RaiseTimedOut.into())
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(eyre!("the raise worker has stopped")),
+    }
+}
+
+/// Returns the worker thread which raises windows, starting it if there is none
+fn raise_worker() -> Sender<RaiseJob> {
+    RAISE_WORKER
+        .lock()
+        .expect("could not lock raise worker")
+        .get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel::<RaiseJob>();
+
+            std::thread::spawn(move || {
+                for (hwnd, strategy, sender) in receiver {
+                    let _ = sender.send(raise(hwnd, strategy));
+                }
+            });
+
+            sender
+        })
+        .clone()
+}
+
+/// Raises and focuses a window using the given strategy
+fn raise(hwnd: Hwnd, strategy: RaiseStrategy) -> Result<()> {
+    match strategy {
+        RaiseStrategy::SendInput => winapi::raise_and_focus_window(hwnd),
+        RaiseStrategy::AltTap => winapi::raise_with_alt_tap(hwnd),
+        RaiseStrategy::AttachThreadInput => winapi::raise_with_attached_thread_input(hwnd),
+        RaiseStrategy::Komorebi => komorebi::focus(hwnd),
+        RaiseStrategy::GlazeWm => glazewm::focus(hwnd),
+    }
+}
+
+/// Sets the system foreground lock timeout to 0 for as long as it is held,
+/// restoring the original value when dropped
+#[derive(Debug)]
+pub struct ForegroundLockTimeoutOverride {
+    original: u32,
+}
+
+impl ForegroundLockTimeoutOverride {
+    pub fn new() -> Result<Self> {
+        let original = winapi::foreground_lock_timeout()?;
+        winapi::set_foreground_lock_timeout(0)?;
+        tracing::info!("foreground lock timeout set to 0 (was {original}ms)");
+
+        Ok(Self { original })
+    }
+}
+
+impl Drop for ForegroundLockTimeoutOverride {
+    fn drop(&mut self) {
+        match winapi::set_foreground_lock_timeout(self.original) {
+            Ok(()) => {
+                tracing::info!("foreground lock timeout restored to {}ms", self.original);
+            }
+            Err(error) => {
+                tracing::error!("failed to restore foreground lock timeout: {error}");
+            }
+        }
+    }
+}
+
+/// Remembers which strategy last succeeded for each window class
+#[derive(Debug)]
+pub struct StrategyMemory {
+    defaults: Vec<RaiseStrategy>,
+    overrides: BTreeMap<String, Vec<RaiseStrategy>>,
+    strategies: HashMap<Arc<str>, RaiseStrategy>,
+}
+
+impl StrategyMemory {
+    /// Creates an empty memory which falls back to trying the given strategies
+    /// in order, or the overriding strategies for a window's class
+    pub fn new(
+        defaults: Vec<RaiseStrategy>,
+        overrides: BTreeMap<String, Vec<RaiseStrategy>>,
+    ) -> Self {
+        Self {
+            defaults,
+            overrides,
+            strategies: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, class: &str) -> Option<RaiseStrategy> {
+        self.strategies.get(class).copied()
+    }
+
+    pub fn remember(&mut self, class: Arc<str>, strategy: RaiseStrategy) {
+        if self.strategies.insert(Arc::clone(&class), strategy) != Some(strategy) {
+            tracing::info!("using raise strategy {strategy:?} for class {class}");
+        }
+    }
+
+    /// Returns the strategies to try for a window class, starting with the
+    /// one which last succeeded for that class
+    pub fn order(&self, class: Option<&str>) -> Vec<RaiseStrategy> {
+        let strategies = class
+            .and_then(|class| self.overrides.get(class))
+            .unwrap_or(&self.defaults);
+
+        // a remembered strategy may have been removed from the overrides by
+        // a reload
+        let remembered = class
+            .and_then(|class| self.get(class))
+            .filter(|remembered| strategies.contains(remembered));
+
+        remembered
+            .into_iter()
+            .chain(
+                strategies
+                    .iter()
+                    .copied()
+                    .filter(|strategy| Some(*strategy) != remembered),
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remembered_strategy_is_tried_first() {
+        let defaults = vec![RaiseStrategy::SendInput, RaiseStrategy::AttachThreadInput];
+        let mut memory = StrategyMemory::new(defaults.clone(), BTreeMap::new());
+        assert_eq!(memory.order(Some("SDL_app")), defaults);
+
+        memory.remember("SDL_app".into(), RaiseStrategy::AttachThreadInput);
+
+        assert_eq!(
+            memory.order(Some("SDL_app")),
+            vec![RaiseStrategy::AttachThreadInput, RaiseStrategy::SendInput]
+        );
+        assert_eq!(memory.order(Some("Notepad")), defaults);
+        assert_eq!(memory.order(None), defaults);
+    }
+
+    #[test]
+    fn overrides_replace_the_defaults_for_their_class() {
+        let defaults = vec![RaiseStrategy::SendInput, RaiseStrategy::AttachThreadInput];
+        let overrides = BTreeMap::from([(
+            String::from("SDL_app"),
+            vec![RaiseStrategy::AltTap, RaiseStrategy::AttachThreadInput],
+        )]);
+        let mut memory = StrategyMemory::new(defaults.clone(), overrides);
+
+        assert_eq!(
+            memory.order(Some("SDL_app")),
+            vec![RaiseStrategy::AltTap, RaiseStrategy::AttachThreadInput]
+        );
+        assert_eq!(memory.order(Some("Notepad")), defaults);
+
+        // a remembered strategy which isn't part of the override is never tried
+        memory.remember("SDL_app".into(), RaiseStrategy::SendInput);
+        assert_eq!(
+            memory.order(Some("SDL_app")),
+            vec![RaiseStrategy::AltTap, RaiseStrategy::AttachThreadInput]
+        );
+
+        memory.remember("SDL_app".into(), RaiseStrategy::AttachThreadInput);
+        assert_eq!(
+            memory.order(Some("SDL_app")),
+            vec![RaiseStrategy::AttachThreadInput, RaiseStrategy::AltTap]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! User commands which are run whenever a window is raised, for chaining
+//! effects such as moving a border, playing a sound or logging elsewhere
+//!
+//! Every argument may contain the placeholders `{hwnd}`, `{class}`, `{exe}`
+//! and `{title}`, which are replaced with the properties of the raised window.
+
+use crate::script::ScriptWindow;
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+use std::process::Stdio;
+use windows::Win32::System::Threading::CREATE_NO_WINDOW;
+
+/// Starts each command for the raised window without waiting for it to exit,
+/// so that a slow command can't hold up the next raise
+pub fn run(commands: &[Vec<String>], window: &ScriptWindow) {
+    for command in commands {
+        let Some((program, args)) = command.split_first() else {
+            continue;
+        };
+
+        let result = Command::new(program)
+            .args(args.iter().map(|arg| expand(arg, window)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            // windowless builds have no console to share, so every command
+            // would otherwise flash a console window of its own
+            .creation_flags(CREATE_NO_WINDOW.0)
+            .spawn();
+
+        if let Err(error) = result {
+            tracing::warn!("failed to run on_raise command {program}: {error}");
+        }
+    }
+}
+
+/// Replaces the placeholders in an argument in a single pass, so that a
+/// window title containing a placeholder is passed through as it is
+fn expand(arg: &str, window: &ScriptWindow) -> String {
+    let hwnd = window.hwnd.to_string();
+    let placeholders = [
+        ("{hwnd}", hwnd.as_str()),
+        ("{class}", window.class.as_str()),
+        ("{exe}", window.exe.as_str()),
+        ("{title}", window.title.as_str()),
+    ];
+
+    let mut expanded = String::with_capacity(arg.len());
+    let mut rest = arg;
+
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        match placeholders
+            .iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+        {
+            Some((placeholder, value)) => {
+                expanded.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                expanded.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hwnd::Hwnd;
+
+    #[test]
+    fn expands_placeholders() {
+        let window = ScriptWindow {
+            hwnd: Hwnd(1234),
+            class: "Chrome_WidgetWin_1".to_string(),
+            exe: "chrome.exe".to_string(),
+            title: "{exe} - {unknown}".to_string(),
+        };
+
+        assert_eq!(
+            expand("{exe}:{hwnd} {class} [{title}]", &window),
+            "chrome.exe:1234 Chrome_WidgetWin_1 [{exe} - {unknown}]"
+        );
+        assert_eq!(expand("no placeholders {", &window), "no placeholders {");
+    }
+}
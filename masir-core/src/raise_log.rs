@@ -0,0 +1,65 @@
+//! An optional log of every raise, for analysing which applications cause
+//! focus churn and building ignore rules from real data
+
+use crate::hwnd::Hwnd;
+use crate::raise::RaiseStrategy;
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use serde::Serialize;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Raised,
+    Failed,
+}
+
+/// A single line of the raise log
+#[derive(Debug, Serialize)]
+pub struct RaiseRecord<'a> {
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u128,
+    pub hwnd: Hwnd,
+    pub class: Option<&'a str>,
+    pub exe: Option<&'a str>,
+    pub outcome: Outcome,
+    /// The strategy which raised the window
+    pub strategy: Option<RaiseStrategy>,
+    /// Why the window could not be raised
+    pub error: Option<String>,
+    /// Microseconds from the mouse movement being handled until the raise
+    /// finished, including any retries
+    pub latency_us: u64,
+}
+
+/// Appends raise records to a file as JSON lines
+#[derive(Debug)]
+pub struct RaiseLog {
+    file: File,
+}
+
+impl RaiseLog {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err_with(|| format!("could not open raise log {}", path.display()))?;
+
+        Ok(Self { file })
+    }
+
+    pub fn record(&mut self, record: &RaiseRecord) -> Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        // a single write per line keeps the file readable while it is tailed
+        self.file.write_all(line.as_bytes())?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,23 @@
+//! Settings and hwnds files waiting to replace those of the running engine,
+//! so that config changes take effect without restarting the mouse hook
+
+use crate::config::Settings;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+static PENDING: Mutex<Option<(Vec<PathBuf>, Settings)>> = Mutex::new(None);
+
+/// Asks the event loop to replace its engine with one using the given hwnds
+/// files and settings, which starts out with empty caches
+pub fn request(hwnds: Vec<PathBuf>, settings: Settings) {
+    *PENDING.lock().expect("could not lock pending reload") = Some((hwnds, settings));
+}
+
+/// Returns the hwnds files and settings of the last reload requested since
+/// this was last called
+pub fn take() -> Option<(Vec<PathBuf>, Settings)> {
+    PENDING
+        .lock()
+        .expect("could not lock pending reload")
+        .take()
+}
@@ -0,0 +1,195 @@
+//! Quiet hours during which windows are never raised, such as a daily standup
+//! or the weekend
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// A day of the week
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum Weekday {
+    #[default]
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    /// Returns the weekday for the given day of the week as numbered by
+    /// Windows, where 0 is Sunday
+    pub fn from_windows(day_of_week: u16) -> Self {
+        Self::ALL[(usize::from(day_of_week) + 6) % 7]
+    }
+
+    fn previous(self) -> Self {
+        Self::ALL[(self as usize + 6) % 7]
+    }
+}
+
+/// The local day and time of day
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LocalTime {
+    pub weekday: Weekday,
+    /// Minutes since midnight
+    pub minutes: u32,
+}
+
+impl LocalTime {
+    pub fn new(weekday: Weekday, hour: u32, minute: u32) -> Self {
+        Self {
+            weekday,
+            minutes: hour * 60 + minute,
+        }
+    }
+}
+
+/// A time window during which windows are never raised
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QuietHoursConfig {
+    /// The days on which the quiet hours start, defaulting to every day
+    pub days: Option<Vec<Weekday>>,
+    /// When the quiet hours start (e.g. "09:00"), defaulting to midnight
+    pub start: Option<String>,
+    /// When the quiet hours end (e.g. "10:00"), defaulting to midnight at the
+    /// end of the day, where an end before the start ends on the next day
+    pub end: Option<String>,
+}
+
+impl QuietHoursConfig {
+    pub(crate) fn compile(&self) -> Result<QuietHours> {
+        let start = match &self.start {
+            Some(start) => parse_time(start)?,
+            None => 0,
+        };
+
+        // 24:00 is only meaningful as the end of the day
+        if start == MINUTES_PER_DAY {
+            return Err(eyre!("start must be before 24:00"));
+        }
+
+        let end = match &self.end {
+            Some(end) => parse_time(end)?,
+            None => MINUTES_PER_DAY,
+        };
+
+        // equal times would otherwise be read as quiet hours lasting all day
+        // and night, which is more likely a mistake than intended
+        if start == end {
+            return Err(eyre!("start and end must differ"));
+        }
+
+        Ok(QuietHours {
+            days: self.days.clone().unwrap_or_else(|| Weekday::ALL.to_vec()),
+            start,
+            end,
+        })
+    }
+}
+
+/// A compiled time window during which windows are never raised
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuietHours {
+    days: Vec<Weekday>,
+    start: u32,
+    end: u32,
+}
+
+impl QuietHours {
+    /// Returns true if the given time falls within these quiet hours
+    pub fn contains(&self, time: LocalTime) -> bool {
+        if self.start < self.end {
+            self.days.contains(&time.weekday) && (self.start..self.end).contains(&time.minutes)
+        } else {
+            // quiet hours which pass midnight belong to the day they started on
+            (self.days.contains(&time.weekday) && time.minutes >= self.start)
+                || (self.days.contains(&time.weekday.previous()) && time.minutes < self.end)
+        }
+    }
+}
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// Parses a time of day given as HH:MM into minutes since midnight, allowing
+/// 24:00 for the end of the day
+fn parse_time(time: &str) -> Result<u32> {
+    let invalid = || eyre!("invalid time, expected HH:MM: {time}");
+
+    let (hour, minute) = time.split_once(':').ok_or_else(invalid)?;
+    let hour: u32 = hour.parse().map_err(|_| invalid())?;
+    let minute: u32 = minute.parse().map_err(|_| invalid())?;
+
+    if minute >= 60 || hour * 60 + minute > MINUTES_PER_DAY {
+        return Err(invalid());
+    }
+
+    Ok(hour * 60 + minute)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiet_hours(days: Option<Vec<Weekday>>, start: &str, end: &str) -> QuietHours {
+        QuietHoursConfig {
+            days,
+            start: Some(start.to_string()),
+            end: Some(end.to_string()),
+        }
+        .compile()
+        .unwrap()
+    }
+
+    #[test]
+    fn contains_times_within_the_window() {
+        let standup = quiet_hours(Some(vec![Weekday::Monday]), "09:00", "10:00");
+
+        assert!(standup.contains(LocalTime::new(Weekday::Monday, 9, 0)));
+        assert!(standup.contains(LocalTime::new(Weekday::Monday, 9, 59)));
+        assert!(!standup.contains(LocalTime::new(Weekday::Monday, 10, 0)));
+        assert!(!standup.contains(LocalTime::new(Weekday::Tuesday, 9, 30)));
+    }
+
+    #[test]
+    fn continues_past_midnight_into_the_next_day() {
+        let night = quiet_hours(Some(vec![Weekday::Sunday]), "22:00", "06:00");
+
+        assert!(night.contains(LocalTime::new(Weekday::Sunday, 23, 0)));
+        assert!(night.contains(LocalTime::new(Weekday::Monday, 5, 0)));
+        assert!(!night.contains(LocalTime::new(Weekday::Sunday, 5, 0)));
+        assert!(!night.contains(LocalTime::new(Weekday::Monday, 23, 0)));
+    }
+
+    #[test]
+    fn rejects_invalid_times() {
+        assert!(parse_time("24:00").is_ok());
+        assert!(parse_time("24:01").is_err());
+        assert!(parse_time("9:60").is_err());
+        assert!(parse_time("nine").is_err());
+
+        let config = |start: &str, end: &str| QuietHoursConfig {
+            days: None,
+            start: Some(start.to_string()),
+            end: Some(end.to_string()),
+        };
+
+        assert!(config("24:00", "09:00").compile().is_err());
+        assert!(config("09:00", "09:00").compile().is_err());
+        assert!(config("00:00", "24:00").compile().is_ok());
+    }
+}
@@ -0,0 +1,139 @@
+//! An optional user script which can veto or force a raise, for policies that
+//! the declarative ignore rules can't express
+//!
+//! The script must define a `should_raise(cursor, foreground)` function which
+//! receives a map of `hwnd`, `class`, `exe` and `title` for each window, and
+//! returns `true` to force a raise, `false` to veto it, or `()` to leave the
+//! decision to the rules.
+
+use crate::hwnd::Hwnd;
+use color_eyre::eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use rhai::Dynamic;
+use rhai::Map;
+use rhai::Scope;
+use rhai::AST;
+use serde::Serialize;
+use std::path::Path;
+
+/// The properties of a window which are passed to the script and to the
+/// eligibility command
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScriptWindow {
+    pub hwnd: Hwnd,
+    pub class: String,
+    pub exe: String,
+    pub title: String,
+}
+
+impl From<ScriptWindow> for Map {
+    fn from(window: ScriptWindow) -> Self {
+        let mut map = Map::new();
+        map.insert("hwnd".into(), (window.hwnd.0 as i64).into());
+        map.insert("class".into(), window.class.into());
+        map.insert("exe".into(), window.exe.into());
+        map.insert("title".into(), window.title.into());
+        map
+    }
+}
+
+pub struct EligibilityScript {
+    engine: rhai::Engine,
+    ast: AST,
+}
+
+impl EligibilityScript {
+    /// How many operations a single call may perform, so that a runaway script
+    /// can't stall the event loop
+    const MAX_OPERATIONS: u64 = 100_000;
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("could not read script {}", path.display()))?;
+
+        Self::compile(&source).wrap_err_with(|| format!("invalid script {}", path.display()))
+    }
+
+    pub fn compile(source: &str) -> Result<Self> {
+        let mut engine = rhai::Engine::new();
+        engine.set_max_operations(Self::MAX_OPERATIONS);
+
+        let ast = engine.compile(source).map_err(|error| eyre!("{error}"))?;
+
+        if !ast
+            .iter_functions()
+            .any(|function| function.name == "should_raise" && function.params.len() == 2)
+        {
+            return Err(eyre!(
+                "the script does not define should_raise(cursor, foreground)"
+            ));
+        }
+
+        Ok(Self { engine, ast })
+    }
+
+    /// Returns `Some(true)` if the script forces a raise, `Some(false)` if it
+    /// vetoes one, or `None` if the decision is left to the rules
+    pub fn verdict(&self, cursor: ScriptWindow, foreground: ScriptWindow) -> Result<Option<bool>> {
+        let result: Dynamic = self
+            .engine
+            .call_fn(
+                &mut Scope::new(),
+                &self.ast,
+                "should_raise",
+                (Map::from(cursor), Map::from(foreground)),
+            )
+            .map_err(|error| eyre!("{error}"))?;
+
+        if result.is_unit() {
+            Ok(None)
+        } else {
+            result
+                .as_bool()
+                .map(Some)
+                .map_err(|kind| eyre!("should_raise returned {kind} instead of a bool or ()"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(class: &str) -> ScriptWindow {
+        ScriptWindow {
+            class: class.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn script_can_force_veto_or_defer() {
+        let script = EligibilityScript::compile(
+            r#"
+            fn should_raise(cursor, foreground) {
+                if cursor.class == "Forced" { return true; }
+                if foreground.class == "Vetoed" { return false; }
+            }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            script.verdict(window("Forced"), window("A")).unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            script.verdict(window("A"), window("Vetoed")).unwrap(),
+            Some(false)
+        );
+        assert_eq!(script.verdict(window("A"), window("B")).unwrap(), None);
+    }
+
+    #[test]
+    fn script_must_define_should_raise() {
+        assert!(EligibilityScript::compile("fn other() { true }").is_err());
+        assert!(EligibilityScript::compile("fn should_raise(").is_err());
+    }
+}
@@ -0,0 +1,62 @@
+//! Tracks remote desktop connections and fast user switching, after which the
+//! raw input registration behind the mouse hook frequently stops receiving
+//! events until it is registered again
+
+use crate::winapi;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::Foundation::LPARAM;
+use windows::Win32::Foundation::LRESULT;
+use windows::Win32::Foundation::WPARAM;
+use windows::Win32::UI::WindowsAndMessaging::DefWindowProcW;
+use windows::Win32::UI::WindowsAndMessaging::WM_WTSSESSION_CHANGE;
+use windows::Win32::UI::WindowsAndMessaging::WTS_CONSOLE_CONNECT;
+use windows::Win32::UI::WindowsAndMessaging::WTS_CONSOLE_DISCONNECT;
+use windows::Win32::UI::WindowsAndMessaging::WTS_REMOTE_CONNECT;
+use windows::Win32::UI::WindowsAndMessaging::WTS_REMOTE_DISCONNECT;
+
+static CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Returns true if the session has been connected or disconnected since this
+/// was last called
+pub fn take_changed() -> bool {
+    CHANGED.swap(false, Ordering::SeqCst)
+}
+
+/// Spawns a thread which receives session change notifications through a
+/// message-only window
+pub fn listen_for_session_changes() {
+    std::thread::spawn(|| {
+        match winapi::create_message_window("masir_session", Some(window_proc))
+            .and_then(winapi::register_session_notification)
+        {
+            Ok(()) => winapi::pump_messages(),
+            Err(error) => tracing::error!("failed to listen for session changes: {error}"),
+        }
+    });
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg != WM_WTSSESSION_CHANGE {
+        return DefWindowProcW(hwnd, msg, wparam, lparam);
+    }
+
+    let change = match wparam.0 as u32 {
+        WTS_CONSOLE_CONNECT => "console connected",
+        WTS_CONSOLE_DISCONNECT => "console disconnected",
+        WTS_REMOTE_CONNECT => "remote desktop connected",
+        WTS_REMOTE_DISCONNECT => "remote desktop disconnected",
+        _ => return LRESULT(0),
+    };
+
+    tracing::info!("session changed: {change}");
+    CHANGED.store(true, Ordering::SeqCst);
+
+    LRESULT(0)
+}
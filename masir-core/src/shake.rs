@@ -0,0 +1,144 @@
+//! Detection of the cursor being shaken from side to side, which toggles the
+//! pause state for those without a free hand for a hotkey
+
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
+
+/// How far the cursor has to travel in one direction, in pixels, before
+/// turning back counts as part of a shake, so that jitter is never a shake
+const MIN_STROKE: i32 = 40;
+/// How many times the cursor has to change direction within `WINDOW`
+const REVERSALS: usize = 4;
+/// How quickly the direction changes have to follow each other
+const WINDOW: Duration = Duration::from_millis(1000);
+/// How long after a shake before another one is recognized, so that shaking
+/// for a little too long doesn't toggle straight back
+const COOLDOWN: Duration = Duration::from_millis(1500);
+
+/// Tracks horizontal cursor movements to recognize a shake
+#[derive(Debug, Default)]
+pub struct ShakeDetector {
+    /// The furthest position reached in the current direction
+    extreme: Option<i32>,
+    /// -1 for left, 1 for right, or 0 until the cursor has moved far enough
+    direction: i32,
+    reversals: VecDeque<Instant>,
+    last_shake: Option<Instant>,
+}
+
+impl ShakeDetector {
+    /// Records the cursor's horizontal position, returning true if it
+    /// completes a shake
+    pub fn update(&mut self, at: Instant, x: i32) -> bool {
+        let Some(extreme) = self.extreme else {
+            self.extreme = Some(x);
+            return false;
+        };
+
+        let travel = x - extreme;
+
+        if travel.signum() == self.direction {
+            self.extreme = Some(x);
+            return false;
+        }
+
+        // turning back only counts once the cursor has clearly gone the other
+        // way, so small wobbles at the end of a stroke are ignored
+        if travel.abs() < MIN_STROKE {
+            return false;
+        }
+
+        if self.direction != 0 {
+            self.reversals.push_back(at);
+        }
+
+        self.direction = travel.signum();
+        self.extreme = Some(x);
+
+        while self
+            .reversals
+            .front()
+            .is_some_and(|reversal| at.duration_since(*reversal) > WINDOW)
+        {
+            self.reversals.pop_front();
+        }
+
+        if self.reversals.len() < REVERSALS
+            || self
+                .last_shake
+                .is_some_and(|last_shake| at.duration_since(last_shake) < COOLDOWN)
+        {
+            return false;
+        }
+
+        self.reversals.clear();
+        self.last_shake = Some(at);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Moves the cursor between the given positions every `interval`,
+    /// returning how many shakes were recognized
+    fn shake(
+        detector: &mut ShakeDetector,
+        start: Instant,
+        interval: Duration,
+        xs: &[i32],
+    ) -> usize {
+        xs.iter()
+            .enumerate()
+            .filter(|(i, x)| detector.update(start + interval * *i as u32, **x))
+            .count()
+    }
+
+    #[test]
+    fn recognizes_a_quick_shake() {
+        let mut detector = ShakeDetector::default();
+        let xs = [500, 600, 500, 600, 500, 600];
+
+        assert_eq!(
+            shake(
+                &mut detector,
+                Instant::now(),
+                Duration::from_millis(100),
+                &xs
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn ignores_slow_or_small_movements() {
+        let start = Instant::now();
+
+        let mut slow = ShakeDetector::default();
+        let xs = [500, 600, 500, 600, 500, 600];
+        assert_eq!(shake(&mut slow, start, Duration::from_millis(500), &xs), 0);
+
+        let mut small = ShakeDetector::default();
+        let xs = [500, 520, 500, 520, 500, 520, 500, 520];
+        assert_eq!(shake(&mut small, start, Duration::from_millis(50), &xs), 0);
+    }
+
+    #[test]
+    fn waits_before_recognizing_another_shake() {
+        let mut detector = ShakeDetector::default();
+        let xs = [500, 600, 500, 600, 500, 600, 500, 600, 500, 600];
+
+        assert_eq!(
+            shake(
+                &mut detector,
+                Instant::now(),
+                Duration::from_millis(50),
+                &xs
+            ),
+            1
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//! Detection of worker loop iterations which take long enough for the cursor
+//! to feel heavy, naming the stage which took the longest
+
+use crate::metrics;
+use crate::metrics::Counter;
+use std::time::Duration;
+use std::time::Instant;
+
+/// How long a single iteration can take before it is considered a stall
+pub const THRESHOLD: Duration = Duration::from_millis(50);
+
+/// Times the stages of a single iteration of the worker loop, leaving out any
+/// time spent waiting for inputs
+pub struct Iteration {
+    stage_started: Instant,
+    busy: Duration,
+    slowest: Option<(&'static str, Duration)>,
+}
+
+impl Iteration {
+    pub fn start() -> Self {
+        Self {
+            stage_started: Instant::now(),
+            busy: Duration::ZERO,
+            slowest: None,
+        }
+    }
+
+    /// Ends the current stage, which is named after what it was doing
+    pub fn stage(&mut self, name: &'static str) {
+        let elapsed = self.stage_started.elapsed();
+        self.busy += elapsed;
+
+        if self.slowest.is_none_or(|(_, slowest)| elapsed > slowest) {
+            self.slowest = Some((name, elapsed));
+        }
+
+        self.stage_started = Instant::now();
+    }
+
+    /// Ends the current stage without counting it, e.g. after waiting for
+    /// inputs
+    pub fn idle(&mut self) {
+        self.stage_started = Instant::now();
+    }
+
+    /// Warns about and counts the iteration if it stalled, returning its
+    /// slowest stage if so
+    pub fn finish(self) -> Option<&'static str> {
+        if self.busy < THRESHOLD {
+            return None;
+        }
+
+        let (name, elapsed) = self.slowest?;
+
+        metrics::increment(Counter::Stalls);
+        tracing::warn!(
+            "the event loop stalled for {:?}, of which {elapsed:?} was spent in {name}",
+            self.busy
+        );
+
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_the_slowest_stage_of_a_stall() {
+        let mut iteration = Iteration::start();
+        iteration.stage("fast");
+        std::thread::sleep(THRESHOLD);
+        iteration.idle();
+        iteration.stage("after waiting");
+        assert_eq!(iteration.finish(), None);
+
+        let mut iteration = Iteration::start();
+        iteration.stage("fast");
+        std::thread::sleep(THRESHOLD);
+        iteration.stage("slow");
+        assert_eq!(iteration.finish(), Some("slow"));
+    }
+}
@@ -0,0 +1,111 @@
+//! Global status shared between the event loop and anything which reports on it
+
+use crate::config::IgnoreRule;
+use crate::config::MatchingRule;
+use crate::config::Settings;
+use crate::decision::Decision;
+use crate::hwnd::Hwnd;
+use crate::metrics;
+use crate::metrics::Percentiles;
+use crate::pause;
+use crate::winapi;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+static LAST_FOCUSED: Mutex<BTreeMap<String, Hwnd>> = Mutex::new(BTreeMap::new());
+static LAST_DECISION: Mutex<Option<Decision>> = Mutex::new(None);
+static RULES: Mutex<Rules> = Mutex::new(Rules {
+    ignore_rules: Vec::new(),
+    protect_rules: Vec::new(),
+    pause_rules: Vec::new(),
+    protect_audio_exceptions: Vec::new(),
+});
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    /// True if focus follows mouse is currently paused
+    pub paused: bool,
+    /// The window which was last focused on each monitor, keyed by monitor
+    /// device name (e.g. `\\.\DISPLAY1`)
+    pub last_focused: BTreeMap<String, Hwnd>,
+    /// Mouse movements evaluated per second, averaged over the last 10 seconds
+    pub events_per_second: u64,
+    /// Percentiles of recent latencies in microseconds from the cursor
+    /// entering a window until that window was raised
+    pub raise_latency_us: Percentiles,
+    /// Iterations of the event loop which took longer than 50ms
+    pub stalls: u64,
+    /// Whether the window under the cursor was raised at the last mouse
+    /// movement, and why
+    pub last_decision: Option<Decision>,
+    /// True if Windows' built-in focus follows mouse setting is enabled, which
+    /// competes with masir over focus
+    pub active_window_tracking: bool,
+}
+
+/// The rules in effect in the running instance, including those imported from
+/// komorebi
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Rules {
+    /// Windows which are never raised or focused away from
+    pub ignore_rules: Vec<MatchingRule>,
+    /// Windows which never lose focus while they are in the foreground
+    pub protect_rules: Vec<MatchingRule>,
+    /// Windows which pause masir while they are in the foreground
+    pub pause_rules: Vec<MatchingRule>,
+    /// Windows which lose focus even while their application is playing audio
+    pub protect_audio_exceptions: Vec<MatchingRule>,
+}
+
+pub fn set_last_focused(monitor: &str, hwnd: Hwnd) {
+    LAST_FOCUSED
+        .lock()
+        .expect("could not lock status")
+        .insert(monitor.to_string(), hwnd);
+}
+
+/// Replaces the window which was last focused on every monitor, such as after
+/// the monitors have changed
+pub fn replace_last_focused(last_focused: BTreeMap<String, Hwnd>) {
+    *LAST_FOCUSED.lock().expect("could not lock status") = last_focused;
+}
+
+pub fn set_last_decision(decision: &Decision) {
+    let mut last_decision = LAST_DECISION.lock().expect("could not lock status");
+    if last_decision.as_ref() != Some(decision) {
+        *last_decision = Some(decision.clone());
+    }
+}
+
+/// Records the rules of the settings which the event loop is using
+pub fn set_rules(settings: &Settings) {
+    let matching_rules =
+        |rules: &[IgnoreRule]| rules.iter().map(IgnoreRule::to_matching_rule).collect();
+
+    *RULES.lock().expect("could not lock status") = Rules {
+        ignore_rules: matching_rules(&settings.ignore_rules),
+        protect_rules: matching_rules(&settings.protect_rules),
+        pause_rules: matching_rules(&settings.pause_rules),
+        protect_audio_exceptions: matching_rules(&settings.protect_audio_exceptions),
+    };
+}
+
+/// Returns the rules in effect in the running instance
+pub fn rules() -> Rules {
+    RULES.lock().expect("could not lock status").clone()
+}
+
+/// Returns a snapshot of the current status
+pub fn current() -> Status {
+    Status {
+        paused: pause::is_paused(),
+        last_focused: LAST_FOCUSED.lock().expect("could not lock status").clone(),
+        events_per_second: metrics::events_per_second(),
+        raise_latency_us: metrics::raise_latency(),
+        stalls: metrics::stalls(),
+        last_decision: LAST_DECISION.lock().expect("could not lock status").clone(),
+        active_window_tracking: winapi::active_window_tracking().unwrap_or_default(),
+    }
+}
@@ -0,0 +1,88 @@
+//! Tracks top-level windows gaining or losing the styles which make them
+//! ineligible (WS_EX_NOACTIVATE and WS_EX_TOOLWINDOW), which many overlays
+//! toggle at runtime, so that their cached eligibility can be invalidated
+//!
+//! There is no event for style changes themselves, so the styles are checked
+//! again whenever a window is shown or its state changes.
+
+use crate::hwnd::Hwnd;
+use crate::winapi;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::Accessibility::HWINEVENTHOOK;
+use windows::Win32::UI::WindowsAndMessaging::CHILDID_SELF;
+use windows::Win32::UI::WindowsAndMessaging::EVENT_OBJECT_DESTROY;
+use windows::Win32::UI::WindowsAndMessaging::EVENT_OBJECT_SHOW;
+use windows::Win32::UI::WindowsAndMessaging::EVENT_OBJECT_STATECHANGE;
+use windows::Win32::UI::WindowsAndMessaging::OBJID_WINDOW;
+
+// whether each window which has been looked at had a filtered style
+static FILTERED: Mutex<BTreeMap<Hwnd, bool>> = Mutex::new(BTreeMap::new());
+static CHANGED: Mutex<Vec<Hwnd>> = Mutex::new(vec![]);
+
+/// Returns the windows whose styles may have changed since this was last
+/// called
+pub fn take_changed() -> Vec<Hwnd> {
+    std::mem::take(&mut *CHANGED.lock().expect("could not lock style changes"))
+}
+
+/// Spawns a thread which hooks windows being shown, destroyed or changing
+/// state to find windows whose styles have changed
+pub fn listen_for_style_changes() {
+    std::thread::spawn(|| {
+        let hooks = [
+            (EVENT_OBJECT_SHOW, EVENT_OBJECT_SHOW),
+            (EVENT_OBJECT_STATECHANGE, EVENT_OBJECT_STATECHANGE),
+            (EVENT_OBJECT_DESTROY, EVENT_OBJECT_DESTROY),
+        ]
+        .into_iter()
+        .map(|(min, max)| winapi::set_win_event_hook(min, max, Some(on_event)))
+        .collect::<Result<Vec<_>, _>>();
+
+        match hooks {
+            Ok(_hooks) => winapi::pump_messages(),
+            Err(error) => tracing::error!("failed to listen for style changes: {error}"),
+        }
+    });
+}
+
+unsafe extern "system" fn on_event(
+    _hook: HWINEVENTHOOK,
+    event: u32,
+    hwnd: HWND,
+    id_object: i32,
+    id_child: i32,
+    _id_event_thread: u32,
+    _event_time: u32,
+) {
+    // these fire for every control, menu and caret, so only top-level windows
+    // are looked at any further
+    if id_object != OBJID_WINDOW.0 || id_child != CHILDID_SELF as i32 {
+        return;
+    }
+
+    let hwnd = Hwnd::from(hwnd);
+    let mut filtered = FILTERED.lock().expect("could not lock window styles");
+
+    if event == EVENT_OBJECT_DESTROY {
+        filtered.remove(&hwnd);
+        return;
+    }
+
+    if hwnd.root().ok() != Some(hwnd) {
+        return;
+    }
+
+    let is_filtered = winapi::has_filtered_style(hwnd);
+
+    // a window seen for the first time may already have been cached with
+    // another style
+    if filtered.insert(hwnd, is_filtered) != Some(is_filtered) {
+        tracing::debug!("hwnd {hwnd} may have changed styles");
+        CHANGED
+            .lock()
+            .expect("could not lock style changes")
+            .push(hwnd);
+    }
+}
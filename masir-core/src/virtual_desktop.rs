@@ -0,0 +1,55 @@
+//! Tracks switches between virtual desktops, after which most of the windows
+//! which were cached are on a desktop which is no longer shown
+//!
+//! There is no documented notification for switches, so the current virtual
+//! desktop which explorer records in the registry is watched instead.
+
+use crate::winapi;
+use color_eyre::Result;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+static SWITCHED: AtomicBool = AtomicBool::new(false);
+
+/// Returns true if the virtual desktop has been switched since this was last
+/// called
+pub fn take_switched() -> bool {
+    SWITCHED.swap(false, Ordering::SeqCst)
+}
+
+/// Spawns a thread which watches for switches of the virtual desktop
+pub fn listen_for_virtual_desktop_switches() {
+    std::thread::spawn(|| {
+        if let Err(error) = watch() {
+            tracing::error!("failed to listen for virtual desktop switches: {error}");
+        }
+    });
+}
+
+fn watch() -> Result<()> {
+    let mut current = winapi::current_virtual_desktop().ok();
+
+    loop {
+        // explorer only starts recording the current virtual desktop once a
+        // second one is created, possibly under another key than the one
+        // which was being watched until then
+        let key = match winapi::virtual_desktops_key() {
+            Ok(key) => key,
+            Err(error) => {
+                tracing::warn!("virtual desktop switches won't be noticed: {error}");
+                return Ok(());
+            }
+        };
+
+        winapi::wait_for_registry_change(&key)?;
+
+        // other values under the same key change too, such as the order of
+        // the virtual desktops
+        let virtual_desktop = winapi::current_virtual_desktop().ok();
+        if virtual_desktop != current {
+            tracing::info!("virtual desktop switched");
+            SWITCHED.store(true, Ordering::SeqCst);
+            current = virtual_desktop;
+        }
+    }
+}
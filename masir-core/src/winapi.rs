@@ -0,0 +1,1268 @@
+//! Thin wrappers around the Win32 APIs used by the event loop
+
+use crate::hwnd::Hwnd;
+use crate::schedule::LocalTime;
+use crate::schedule::Weekday;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use std::cell::RefCell;
+use std::fs::File;
+use std::os::windows::io::AsRawHandle;
+use std::path::Path;
+use windows::core::Interface;
+use windows::core::Result as WindowsCrateResult;
+use windows::core::HSTRING;
+use windows::core::PCWSTR;
+use windows::core::PWSTR;
+use windows::Win32::Foundation::CloseHandle;
+use windows::Win32::Foundation::BOOL;
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::Foundation::HINSTANCE;
+use windows::Win32::Foundation::HMODULE;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::Foundation::LPARAM;
+use windows::Win32::Foundation::LRESULT;
+use windows::Win32::Foundation::POINT;
+use windows::Win32::Foundation::RECT;
+use windows::Win32::Foundation::RPC_E_CHANGED_MODE;
+use windows::Win32::Foundation::S_OK;
+use windows::Win32::Foundation::WPARAM;
+use windows::Win32::Graphics::Gdi::GetMonitorInfoW;
+use windows::Win32::Graphics::Gdi::MonitorFromPoint;
+use windows::Win32::Graphics::Gdi::MonitorFromWindow;
+use windows::Win32::Graphics::Gdi::ScreenToClient;
+use windows::Win32::Graphics::Gdi::MONITORINFO;
+use windows::Win32::Graphics::Gdi::MONITORINFOEXW;
+use windows::Win32::Graphics::Gdi::MONITOR_DEFAULTTONEAREST;
+use windows::Win32::Graphics::Gdi::MONITOR_DEFAULTTONULL;
+use windows::Win32::Media::Audio::eRender;
+use windows::Win32::Media::Audio::AudioSessionStateActive;
+use windows::Win32::Media::Audio::IAudioSessionControl2;
+use windows::Win32::Media::Audio::IAudioSessionManager2;
+use windows::Win32::Media::Audio::IMMDeviceEnumerator;
+use windows::Win32::Media::Audio::MMDeviceEnumerator;
+use windows::Win32::Media::Audio::PlaySoundW;
+use windows::Win32::Media::Audio::DEVICE_STATE_ACTIVE;
+use windows::Win32::Media::Audio::SND_ASYNC;
+use windows::Win32::Media::Audio::SND_FILENAME;
+use windows::Win32::Media::Audio::SND_NODEFAULT;
+use windows::Win32::Security::GetTokenInformation;
+use windows::Win32::Security::TokenElevation;
+use windows::Win32::Security::TOKEN_ELEVATION;
+use windows::Win32::Security::TOKEN_QUERY;
+use windows::Win32::System::Com::CoCreateInstance;
+use windows::Win32::System::Com::CoInitializeEx;
+use windows::Win32::System::Com::CLSCTX_ALL;
+use windows::Win32::System::Com::CLSCTX_INPROC_SERVER;
+use windows::Win32::System::Com::COINIT_MULTITHREADED;
+use windows::Win32::System::Diagnostics::Debug::MiniDumpWithIndirectlyReferencedMemory;
+use windows::Win32::System::Diagnostics::Debug::MiniDumpWithThreadInfo;
+use windows::Win32::System::Diagnostics::Debug::MiniDumpWriteDump;
+use windows::Win32::System::Diagnostics::Debug::SetUnhandledExceptionFilter;
+use windows::Win32::System::Diagnostics::Debug::EXCEPTION_POINTERS;
+use windows::Win32::System::Diagnostics::Debug::LPTOP_LEVEL_EXCEPTION_FILTER;
+use windows::Win32::System::Diagnostics::Debug::MINIDUMP_EXCEPTION_INFORMATION;
+use windows::Win32::System::Diagnostics::ToolHelp::CreateToolhelp32Snapshot;
+use windows::Win32::System::Diagnostics::ToolHelp::Process32FirstW;
+use windows::Win32::System::Diagnostics::ToolHelp::Process32NextW;
+use windows::Win32::System::Diagnostics::ToolHelp::PROCESSENTRY32W;
+use windows::Win32::System::Diagnostics::ToolHelp::TH32CS_SNAPPROCESS;
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Power::GetSystemPowerStatus;
+use windows::Win32::System::Power::SYSTEM_POWER_STATUS;
+use windows::Win32::System::Registry::RegCloseKey;
+use windows::Win32::System::Registry::RegGetValueW;
+use windows::Win32::System::Registry::RegNotifyChangeKeyValue;
+use windows::Win32::System::Registry::RegOpenKeyExW;
+use windows::Win32::System::Registry::HKEY;
+use windows::Win32::System::Registry::HKEY_CURRENT_USER;
+use windows::Win32::System::Registry::KEY_NOTIFY;
+use windows::Win32::System::Registry::REG_NOTIFY_CHANGE_LAST_SET;
+use windows::Win32::System::Registry::RRF_RT_REG_BINARY;
+use windows::Win32::System::RemoteDesktop::ProcessIdToSessionId;
+use windows::Win32::System::RemoteDesktop::WTSRegisterSessionNotification;
+use windows::Win32::System::RemoteDesktop::NOTIFY_FOR_THIS_SESSION;
+use windows::Win32::System::SystemInformation::GetLocalTime;
+use windows::Win32::System::Threading::AttachThreadInput;
+use windows::Win32::System::Threading::GetCurrentProcess;
+use windows::Win32::System::Threading::GetCurrentProcessId;
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::System::Threading::OpenProcess;
+use windows::Win32::System::Threading::OpenProcessToken;
+use windows::Win32::System::Threading::QueryFullProcessImageNameW;
+use windows::Win32::System::Threading::PROCESS_NAME_WIN32;
+use windows::Win32::System::Threading::PROCESS_QUERY_LIMITED_INFORMATION;
+use windows::Win32::UI::Accessibility::CUIAutomation;
+use windows::Win32::UI::Accessibility::IUIAutomation;
+use windows::Win32::UI::Accessibility::IUIAutomationElement;
+use windows::Win32::UI::Accessibility::SetWinEventHook;
+use windows::Win32::UI::Accessibility::HWINEVENTHOOK;
+use windows::Win32::UI::Accessibility::WINEVENTPROC;
+use windows::Win32::UI::Input::KeyboardAndMouse::GetAsyncKeyState;
+use windows::Win32::UI::Input::KeyboardAndMouse::SendInput;
+use windows::Win32::UI::Input::KeyboardAndMouse::SetFocus;
+use windows::Win32::UI::Input::KeyboardAndMouse::INPUT;
+use windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0;
+use windows::Win32::UI::Input::KeyboardAndMouse::INPUT_KEYBOARD;
+use windows::Win32::UI::Input::KeyboardAndMouse::INPUT_MOUSE;
+use windows::Win32::UI::Input::KeyboardAndMouse::KEYBDINPUT;
+use windows::Win32::UI::Input::KeyboardAndMouse::KEYBD_EVENT_FLAGS;
+use windows::Win32::UI::Input::KeyboardAndMouse::KEYEVENTF_KEYUP;
+use windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY;
+use windows::Win32::UI::Input::KeyboardAndMouse::VK_MENU;
+use windows::Win32::UI::Shell::IVirtualDesktopManager;
+use windows::Win32::UI::Shell::VirtualDesktopManager;
+use windows::Win32::UI::WindowsAndMessaging::BringWindowToTop;
+use windows::Win32::UI::WindowsAndMessaging::CallNextHookEx;
+use windows::Win32::UI::WindowsAndMessaging::ChildWindowFromPointEx;
+use windows::Win32::UI::WindowsAndMessaging::CreateWindowExW;
+use windows::Win32::UI::WindowsAndMessaging::DispatchMessageW;
+use windows::Win32::UI::WindowsAndMessaging::EnumWindows;
+use windows::Win32::UI::WindowsAndMessaging::GetAncestor;
+use windows::Win32::UI::WindowsAndMessaging::GetCursorPos;
+use windows::Win32::UI::WindowsAndMessaging::GetDesktopWindow;
+use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+use windows::Win32::UI::WindowsAndMessaging::GetMessageW;
+use windows::Win32::UI::WindowsAndMessaging::GetWindow;
+use windows::Win32::UI::WindowsAndMessaging::GetWindowLongW;
+use windows::Win32::UI::WindowsAndMessaging::GetWindowRect;
+use windows::Win32::UI::WindowsAndMessaging::GetWindowTextW;
+use windows::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId;
+use windows::Win32::UI::WindowsAndMessaging::IsIconic;
+use windows::Win32::UI::WindowsAndMessaging::IsWindow;
+use windows::Win32::UI::WindowsAndMessaging::IsWindowVisible;
+use windows::Win32::UI::WindowsAndMessaging::RealChildWindowFromPoint;
+use windows::Win32::UI::WindowsAndMessaging::RealGetWindowClassW;
+use windows::Win32::UI::WindowsAndMessaging::RegisterClassW;
+use windows::Win32::UI::WindowsAndMessaging::SetForegroundWindow;
+use windows::Win32::UI::WindowsAndMessaging::SetWindowPos;
+use windows::Win32::UI::WindowsAndMessaging::SetWindowsHookExW;
+use windows::Win32::UI::WindowsAndMessaging::SystemParametersInfoW;
+use windows::Win32::UI::WindowsAndMessaging::TranslateMessage;
+use windows::Win32::UI::WindowsAndMessaging::UnhookWindowsHookEx;
+use windows::Win32::UI::WindowsAndMessaging::WindowFromPoint;
+use windows::Win32::UI::WindowsAndMessaging::CWP_ALL;
+use windows::Win32::UI::WindowsAndMessaging::CWP_SKIPINVISIBLE;
+use windows::Win32::UI::WindowsAndMessaging::CWP_SKIPTRANSPARENT;
+use windows::Win32::UI::WindowsAndMessaging::GA_ROOT;
+use windows::Win32::UI::WindowsAndMessaging::GET_ANCESTOR_FLAGS;
+use windows::Win32::UI::WindowsAndMessaging::GWL_EXSTYLE;
+use windows::Win32::UI::WindowsAndMessaging::GWL_STYLE;
+use windows::Win32::UI::WindowsAndMessaging::GW_HWNDNEXT;
+use windows::Win32::UI::WindowsAndMessaging::GW_OWNER;
+use windows::Win32::UI::WindowsAndMessaging::HHOOK;
+use windows::Win32::UI::WindowsAndMessaging::HWND_MESSAGE;
+use windows::Win32::UI::WindowsAndMessaging::HWND_TOP;
+use windows::Win32::UI::WindowsAndMessaging::MSG;
+use windows::Win32::UI::WindowsAndMessaging::SPIF_SENDCHANGE;
+use windows::Win32::UI::WindowsAndMessaging::SPI_GETACTIVEWINDOWTRACKING;
+use windows::Win32::UI::WindowsAndMessaging::SPI_GETFOREGROUNDLOCKTIMEOUT;
+use windows::Win32::UI::WindowsAndMessaging::SPI_SETFOREGROUNDLOCKTIMEOUT;
+use windows::Win32::UI::WindowsAndMessaging::SWP_NOACTIVATE;
+use windows::Win32::UI::WindowsAndMessaging::SWP_NOMOVE;
+use windows::Win32::UI::WindowsAndMessaging::SWP_NOSIZE;
+use windows::Win32::UI::WindowsAndMessaging::SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS;
+use windows::Win32::UI::WindowsAndMessaging::WH_MOUSE_LL;
+use windows::Win32::UI::WindowsAndMessaging::WINDOW_EX_STYLE;
+use windows::Win32::UI::WindowsAndMessaging::WINDOW_STYLE;
+use windows::Win32::UI::WindowsAndMessaging::WINEVENT_OUTOFCONTEXT;
+use windows::Win32::UI::WindowsAndMessaging::WINEVENT_SKIPOWNPROCESS;
+use windows::Win32::UI::WindowsAndMessaging::WNDCLASSW;
+use windows::Win32::UI::WindowsAndMessaging::WNDPROC;
+use windows::Win32::UI::WindowsAndMessaging::WS_CAPTION;
+use windows::Win32::UI::WindowsAndMessaging::WS_EX_NOACTIVATE;
+use windows::Win32::UI::WindowsAndMessaging::WS_EX_TOOLWINDOW;
+use windows::Win32::UI::WindowsAndMessaging::WS_POPUP;
+
+macro_rules! as_ptr {
+    ($value:expr) => {
+        $value as *mut core::ffi::c_void
+    };
+}
+
+enum WindowsResult<T, E> {
+    Err(E),
+    Ok(T),
+}
+
+macro_rules! impl_from_integer_for_windows_result {
+    ( $( $integer_type:ty ),+ ) => {
+        $(
+            impl From<$integer_type> for WindowsResult<$integer_type, color_eyre::eyre::Error> {
+                fn from(return_value: $integer_type) -> Self {
+                    match return_value {
+                        0 => Self::Err(std::io::Error::last_os_error().into()),
+                        _ => Self::Ok(return_value),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_from_integer_for_windows_result!(usize, isize, u16, u32, i32);
+
+impl<T, E> From<WindowsResult<T, E>> for Result<T, E> {
+    fn from(result: WindowsResult<T, E>) -> Self {
+        match result {
+            WindowsResult::Err(error) => Err(error),
+            WindowsResult::Ok(ok) => Ok(ok),
+        }
+    }
+}
+
+trait ProcessWindowsCrateResult<T> {
+    fn process(self) -> Result<T>;
+}
+
+macro_rules! impl_process_windows_crate_integer_wrapper_result {
+    ( $($input:ty => $deref:ty),+ $(,)? ) => (
+        paste::paste! {
+            $(
+                impl ProcessWindowsCrateResult<$deref> for $input {
+                    fn process(self) -> Result<$deref> {
+                        if self == $input(std::ptr::null_mut()) {
+                            Err(std::io::Error::last_os_error().into())
+                        } else {
+                            Ok(<$deref>::from(self))
+                        }
+                    }
+                }
+            )+
+        }
+    );
+}
+
+impl_process_windows_crate_integer_wrapper_result!(
+    HWND => Hwnd,
+);
+
+impl<T> ProcessWindowsCrateResult<T> for WindowsCrateResult<T> {
+    fn process(self) -> Result<T> {
+        match self {
+            Ok(value) => Ok(value),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+/// Returns the extended window styles of the given window
+pub fn get_window_ex_style(hwnd: Hwnd) -> WINDOW_EX_STYLE {
+    unsafe { WINDOW_EX_STYLE(GetWindowLongW(HWND::from(hwnd), GWL_EXSTYLE) as u32) }
+}
+
+/// Returns true if the window has an extended style which indicates that it
+/// should never be activated (tool windows and no-activate windows)
+pub fn has_filtered_style(hwnd: Hwnd) -> bool {
+    let ex_style = get_window_ex_style(hwnd);
+
+    ex_style.contains(WS_EX_TOOLWINDOW) || ex_style.contains(WS_EX_NOACTIVATE)
+}
+
+/// Returns the window styles of the given window
+pub fn get_window_style(hwnd: Hwnd) -> WINDOW_STYLE {
+    unsafe { WINDOW_STYLE(GetWindowLongW(HWND::from(hwnd), GWL_STYLE) as u32) }
+}
+
+/// Returns true if the window looks like a transient surface rather than one
+/// which a user would want to focus: a tooltip, an owned popup without a
+/// caption (e.g. a menu or a dropdown), or a window which has no size or is not
+/// on any monitor
+pub fn is_transient(hwnd: Hwnd) -> bool {
+    if real_window_class_w(hwnd).is_ok_and(|class| class == "tooltips_class32") {
+        return true;
+    }
+
+    // frameless application windows (e.g. Electron apps) are also captionless
+    // popups, but unlike menus and dropdowns they don't have an owner
+    let style = get_window_style(hwnd);
+    let has_owner = unsafe { GetWindow(HWND::from(hwnd), GW_OWNER) }.is_ok();
+    if style.contains(WS_POPUP) && !style.contains(WS_CAPTION) && has_owner {
+        return true;
+    }
+
+    if window_size(hwnd).is_ok_and(|(width, height)| width <= 0 || height <= 0) {
+        return true;
+    }
+
+    !is_on_screen(hwnd)
+}
+
+/// Returns true if any part of the window is on a monitor
+pub fn is_on_screen(hwnd: Hwnd) -> bool {
+    !unsafe { MonitorFromWindow(HWND::from(hwnd), MONITOR_DEFAULTTONULL) }.is_invalid()
+}
+
+/// Returns true if the handle still refers to a window which hasn't been
+/// destroyed
+pub fn is_window(hwnd: Hwnd) -> bool {
+    unsafe { IsWindow(HWND::from(hwnd)) }.as_bool()
+}
+
+/// Returns true if the window is visible, not minimized and at least partly on
+/// screen, and so can meaningfully receive focus
+pub fn is_visible(hwnd: Hwnd) -> bool {
+    let handle = HWND::from(hwnd);
+    let is_shown = unsafe { IsWindowVisible(handle).as_bool() && !IsIconic(handle).as_bool() };
+
+    is_shown && is_on_screen(hwnd)
+}
+
+/// Returns true if the window covers the whole of the monitor it is mostly on,
+/// like a fullscreen application or a screen capture overlay
+pub fn covers_monitor(hwnd: Hwnd) -> bool {
+    let handle = HWND::from(hwnd);
+    let hmonitor = unsafe { MonitorFromWindow(handle, MONITOR_DEFAULTTONEAREST) };
+
+    let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    let mut rect = RECT::default();
+
+    let found = unsafe {
+        GetMonitorInfoW(hmonitor, &mut info).as_bool() && GetWindowRect(handle, &mut rect).is_ok()
+    };
+
+    let monitor = info.rcMonitor;
+    found
+        && rect.left <= monitor.left
+        && rect.top <= monitor.top
+        && rect.right >= monitor.right
+        && rect.bottom >= monitor.bottom
+}
+
+/// Returns the width and height of the given window
+pub fn window_size(hwnd: Hwnd) -> Result<(i32, i32)> {
+    let mut rect = RECT::default();
+    unsafe { GetWindowRect(HWND::from(hwnd), &mut rect) }.process()?;
+
+    Ok((rect.right - rect.left, rect.bottom - rect.top))
+}
+
+/// Returns the ancestor of the given window as specified by `gaflags`
+pub fn get_ancestor(hwnd: Hwnd, gaflags: GET_ANCESTOR_FLAGS) -> Result<Hwnd> {
+    unsafe { GetAncestor(HWND::from(hwnd), gaflags) }.process()
+}
+
+/// Returns the window at the given point on the screen
+pub fn window_from_point(point: POINT) -> Result<Hwnd> {
+    unsafe { WindowFromPoint(point) }.process()
+}
+
+/// Returns the deepest window at the given point on the screen found with
+/// ChildWindowFromPointEx, optionally skipping invisible windows and windows
+/// with the WS_EX_TRANSPARENT style
+pub fn child_window_from_point_ex(
+    point: POINT,
+    skip_invisible: bool,
+    skip_transparent: bool,
+) -> Result<Hwnd> {
+    let mut flags = CWP_ALL;
+    if skip_invisible {
+        flags |= CWP_SKIPINVISIBLE;
+    }
+    if skip_transparent {
+        flags |= CWP_SKIPTRANSPARENT;
+    }
+
+    descend_from_desktop(point, |parent, client_point| unsafe {
+        ChildWindowFromPointEx(parent, client_point, flags)
+    })
+}
+
+/// Returns the deepest window at the given point on the screen found with
+/// RealChildWindowFromPoint
+pub fn real_child_window_from_point(point: POINT) -> Result<Hwnd> {
+    descend_from_desktop(point, |parent, client_point| unsafe {
+        RealChildWindowFromPoint(parent, client_point)
+    })
+}
+
+thread_local! {
+    // ui automation objects belong to the com apartment of the thread which
+    // created them
+    static UI_AUTOMATION: RefCell<Option<IUIAutomation>> = const { RefCell::new(None) };
+    static AUDIO_DEVICES: RefCell<Option<IMMDeviceEnumerator>> = const { RefCell::new(None) };
+    static VIRTUAL_DESKTOPS: RefCell<Option<IVirtualDesktopManager>> = const { RefCell::new(None) };
+}
+
+/// The key under HKEY_CURRENT_USER where explorer records the current virtual
+/// desktop on Windows 11
+const VIRTUAL_DESKTOPS_KEY: &str =
+    r"Software\Microsoft\Windows\CurrentVersion\Explorer\VirtualDesktops";
+
+/// Initialises COM on this thread
+fn initialize_com() -> Result<()> {
+    // com is usable, just not in the apartment we asked for, if another
+    // library on this thread initialised it first
+    let result = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) };
+    if result.is_err() && result != RPC_E_CHANGED_MODE {
+        result.ok()?;
+    }
+
+    Ok(())
+}
+
+/// Returns this thread's UI Automation client, initialising COM and creating
+/// the client on first use
+fn ui_automation() -> Result<IUIAutomation> {
+    UI_AUTOMATION.with_borrow_mut(|ui_automation| {
+        if let Some(ui_automation) = ui_automation {
+            return Ok(ui_automation.clone());
+        }
+
+        initialize_com()?;
+
+        let created: IUIAutomation =
+            unsafe { CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER) }?;
+        *ui_automation = Some(created.clone());
+
+        Ok(created)
+    })
+}
+
+/// Returns this thread's audio device enumerator, initialising COM and
+/// creating the enumerator on first use
+fn audio_devices() -> Result<IMMDeviceEnumerator> {
+    AUDIO_DEVICES.with_borrow_mut(|audio_devices| {
+        if let Some(audio_devices) = audio_devices {
+            return Ok(audio_devices.clone());
+        }
+
+        initialize_com()?;
+
+        let created: IMMDeviceEnumerator =
+            unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL) }?;
+        *audio_devices = Some(created.clone());
+
+        Ok(created)
+    })
+}
+
+/// Returns this thread's virtual desktop manager, initialising COM and
+/// creating the manager on first use
+fn virtual_desktops() -> Result<IVirtualDesktopManager> {
+    VIRTUAL_DESKTOPS.with_borrow_mut(|virtual_desktops| {
+        if let Some(virtual_desktops) = virtual_desktops {
+            return Ok(virtual_desktops.clone());
+        }
+
+        initialize_com()?;
+
+        let created: IVirtualDesktopManager =
+            unsafe { CoCreateInstance(&VirtualDesktopManager, None, CLSCTX_ALL) }?;
+        *virtual_desktops = Some(created.clone());
+
+        Ok(created)
+    })
+}
+
+/// Returns true if the top-level window is on the current virtual desktop
+pub fn is_on_current_virtual_desktop(hwnd: Hwnd) -> Result<bool> {
+    let virtual_desktops = virtual_desktops()?;
+    Ok(unsafe { virtual_desktops.IsWindowOnCurrentVirtualDesktop(HWND::from(hwnd)) }?.as_bool())
+}
+
+/// Returns the keys under HKEY_CURRENT_USER where explorer may record the
+/// current virtual desktop, where Windows 10 records it per session
+fn virtual_desktops_keys() -> Vec<String> {
+    let mut keys = vec![VIRTUAL_DESKTOPS_KEY.to_string()];
+
+    let mut session_id = 0;
+    if unsafe { ProcessIdToSessionId(GetCurrentProcessId(), &mut session_id) }.is_ok() {
+        keys.push(format!(
+            r"Software\Microsoft\Windows\CurrentVersion\Explorer\SessionInfo\{session_id}\VirtualDesktops"
+        ));
+    }
+
+    keys
+}
+
+/// Returns the key under HKEY_CURRENT_USER where explorer records the current
+/// virtual desktop, or if none has been recorded yet, the first of the keys
+/// where it may be recorded which exists
+pub fn virtual_desktops_key() -> Result<String> {
+    let keys = virtual_desktops_keys();
+
+    if let Some(key) = keys
+        .iter()
+        .find(|key| current_virtual_desktop_in(key).is_ok())
+    {
+        return Ok(key.clone());
+    }
+
+    keys.into_iter()
+        .find(|key| registry_key_exists(key))
+        .ok_or_else(|| eyre!("explorer has no virtual desktops key"))
+}
+
+/// Returns the id of the current virtual desktop as explorer records it, which
+/// is only recorded once there has been more than one virtual desktop
+pub fn current_virtual_desktop() -> Result<Vec<u8>> {
+    virtual_desktops_keys()
+        .iter()
+        .find_map(|key| current_virtual_desktop_in(key).ok())
+        .ok_or_else(|| eyre!("explorer hasn't recorded the current virtual desktop"))
+}
+
+fn current_virtual_desktop_in(key: &str) -> Result<Vec<u8>> {
+    let key = HSTRING::from(key);
+    let value = HSTRING::from("CurrentVirtualDesktop");
+
+    let mut id = vec![0u8; 16];
+    let mut len = id.len() as u32;
+
+    unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            &key,
+            &value,
+            RRF_RT_REG_BINARY,
+            None,
+            Some(id.as_mut_ptr().cast()),
+            Some(&mut len),
+        )
+    }
+    .ok()?;
+
+    id.truncate(len as usize);
+    Ok(id)
+}
+
+/// Returns true if the given key exists under HKEY_CURRENT_USER
+fn registry_key_exists(key: &str) -> bool {
+    let key = HSTRING::from(key);
+    let mut handle = HKEY::default();
+
+    let opened =
+        unsafe { RegOpenKeyExW(HKEY_CURRENT_USER, &key, 0, KEY_NOTIFY, &mut handle) }.is_ok();
+    if opened {
+        let _ = unsafe { RegCloseKey(handle) };
+    }
+
+    opened
+}
+
+/// Blocks until a value of the given key under HKEY_CURRENT_USER is set
+pub fn wait_for_registry_change(key: &str) -> Result<()> {
+    let key = HSTRING::from(key);
+    let mut handle = HKEY::default();
+
+    unsafe { RegOpenKeyExW(HKEY_CURRENT_USER, &key, 0, KEY_NOTIFY, &mut handle) }.ok()?;
+
+    let result =
+        unsafe { RegNotifyChangeKeyValue(handle, false, REG_NOTIFY_CHANGE_LAST_SET, None, false) };
+    let _ = unsafe { RegCloseKey(handle) };
+
+    Ok(result.ok()?)
+}
+
+/// Returns the executable names of every process with an audio session which
+/// is currently playing on any output device, leaving out system sounds
+pub fn audio_playing_exes() -> Result<Vec<String>> {
+    let mut exes = vec![];
+
+    unsafe {
+        let devices = audio_devices()?.EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE)?;
+
+        for i in 0..devices.GetCount()? {
+            let manager: IAudioSessionManager2 = devices.Item(i)?.Activate(CLSCTX_ALL, None)?;
+            let sessions = manager.GetSessionEnumerator()?;
+
+            for j in 0..sessions.GetCount()? {
+                let session = sessions.GetSession(j)?;
+                if session.GetState()? != AudioSessionStateActive {
+                    continue;
+                }
+
+                let session: IAudioSessionControl2 = session.cast()?;
+                if session.IsSystemSoundsSession() == S_OK {
+                    continue;
+                }
+
+                // the process may have exited since the session was enumerated
+                if let Ok(exe) = process_exe(session.GetProcessId()?) {
+                    exes.push(exe);
+                }
+            }
+        }
+    }
+
+    Ok(exes)
+}
+
+/// Returns the window hosting the UI Automation element at the given point on
+/// the screen, walking up the element tree past elements without a window of
+/// their own, such as the contents of WebView2 and XAML islands
+pub fn ui_automation_window_from_point(point: POINT) -> Result<Hwnd> {
+    let ui_automation = ui_automation()?;
+    let element = unsafe { ui_automation.ElementFromPoint(point) }?;
+
+    hosting_window(&ui_automation, element)
+}
+
+/// Returns the window hosting the given UI Automation element, walking up the
+/// element tree past elements without a window of their own
+fn hosting_window(
+    ui_automation: &IUIAutomation,
+    mut element: IUIAutomationElement,
+) -> Result<Hwnd> {
+    let walker = unsafe { ui_automation.RawViewWalker() }?;
+
+    loop {
+        let hwnd = unsafe { element.CurrentNativeWindowHandle() }?;
+        if !hwnd.is_invalid() {
+            return Ok(Hwnd::from(hwnd));
+        }
+
+        element = unsafe { walker.GetParentElement(&element) }?;
+    }
+}
+
+/// Gives keyboard focus to the control at the given point on the screen if it
+/// belongs to the given top-level window, using UI Automation so that controls
+/// without a window of their own (such as those in browsers and XAML apps) can
+/// be focused, and otherwise focusing the child window at the point
+pub fn focus_control_at(point: POINT, root: Hwnd) -> Result<()> {
+    match focus_element_at(point, root) {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            tracing::debug!("failed to focus the ui automation element at the cursor: {error}");
+            focus_child_window_at(point, root)
+        }
+    }
+}
+
+fn focus_element_at(point: POINT, root: Hwnd) -> Result<()> {
+    let ui_automation = ui_automation()?;
+    let element = unsafe { ui_automation.ElementFromPoint(point) }?;
+
+    // the element may belong to a window floating above the raised one
+    if get_ancestor(hosting_window(&ui_automation, element.clone())?, GA_ROOT)? != root {
+        return Ok(());
+    }
+
+    // labels, images and other static content can't take focus, which is
+    // left where the window put it
+    if unsafe { element.CurrentIsKeyboardFocusable() }?.as_bool() {
+        unsafe { element.SetFocus() }?;
+    }
+
+    Ok(())
+}
+
+fn focus_child_window_at(point: POINT, root: Hwnd) -> Result<()> {
+    let child = window_from_point(point)?;
+    if child == root || get_ancestor(child, GA_ROOT)? != root {
+        return Ok(());
+    }
+
+    // SetFocus only works for windows attached to the calling thread's input
+    let current_thread = unsafe { GetCurrentThreadId() };
+    let child_thread = window_thread_id(child);
+
+    unsafe {
+        AttachThreadInput(current_thread, child_thread, true)
+            .ok()
+            .process()?;
+
+        let result = SetFocus(HWND::from(child)).map(|_| ()).process();
+
+        AttachThreadInput(current_thread, child_thread, false)
+            .ok()
+            .process()?;
+
+        result
+    }
+}
+
+/// Walks down from the desktop window through the child at the given point
+/// until a window has no child there, since the child window functions only
+/// look one level deep
+fn descend_from_desktop(point: POINT, child_at: impl Fn(HWND, POINT) -> HWND) -> Result<Hwnd> {
+    let desktop = unsafe { GetDesktopWindow() };
+    let mut parent = desktop;
+
+    loop {
+        let mut client_point = point;
+        unsafe { ScreenToClient(parent, &mut client_point) }.ok()?;
+
+        let child = child_at(parent, client_point);
+        if child.is_invalid() || child == parent {
+            break;
+        }
+
+        parent = child;
+    }
+
+    if parent == desktop {
+        return Err(eyre!("there is no window at ({}, {})", point.x, point.y));
+    }
+
+    Ok(Hwnd::from(parent))
+}
+
+/// Returns the current foreground window
+pub fn foreground_window() -> Result<Hwnd> {
+    unsafe { GetForegroundWindow() }.process()
+}
+
+/// Returns the current cursor position
+pub fn cursor_pos() -> Result<POINT> {
+    let mut cursor_pos = POINT::default();
+    unsafe { GetCursorPos(&mut cursor_pos) }.process()?;
+
+    Ok(cursor_pos)
+}
+
+/// Starts playing the given sound file without waiting for it to finish,
+/// stopping any sound which was started before
+pub fn play_sound(path: &Path) -> Result<()> {
+    let path = HSTRING::from(path);
+    Ok(unsafe { PlaySoundW(&path, None, SND_ASYNC | SND_FILENAME | SND_NODEFAULT) }.ok()?)
+}
+
+/// Writes a minidump of this process to the given file, including the
+/// exception which is being handled by the current thread if there is one
+pub fn write_minidump(path: &Path, exception: Option<*const EXCEPTION_POINTERS>) -> Result<()> {
+    let file = File::create(path)?;
+    let exception = exception.map(|pointers| MINIDUMP_EXCEPTION_INFORMATION {
+        ThreadId: unsafe { GetCurrentThreadId() },
+        ExceptionPointers: pointers.cast_mut(),
+        ClientPointers: false.into(),
+    });
+
+    unsafe {
+        MiniDumpWriteDump(
+            GetCurrentProcess(),
+            GetCurrentProcessId(),
+            HANDLE(file.as_raw_handle()),
+            MiniDumpWithThreadInfo | MiniDumpWithIndirectlyReferencedMemory,
+            exception.as_ref().map(|exception| exception as *const _),
+            None,
+            None,
+        )
+    }?;
+
+    Ok(())
+}
+
+/// Sets the filter which is called for exceptions which nothing else handles
+/// before the process is terminated
+pub fn set_unhandled_exception_filter(filter: LPTOP_LEVEL_EXCEPTION_FILTER) {
+    unsafe { SetUnhandledExceptionFilter(filter) };
+}
+
+/// Returns true if the system is running on battery power, which is assumed
+/// not to be the case if the power status can't be determined
+pub fn is_on_battery() -> bool {
+    let mut status = SYSTEM_POWER_STATUS::default();
+
+    // an ac line status of 0 means offline, 1 online and 255 unknown
+    unsafe { GetSystemPowerStatus(&mut status) }.is_ok() && status.ACLineStatus == 0
+}
+
+/// Returns the local day and time of day
+pub fn local_time() -> LocalTime {
+    let time = unsafe { GetLocalTime() };
+
+    LocalTime::new(
+        Weekday::from_windows(time.wDayOfWeek),
+        u32::from(time.wHour),
+        u32::from(time.wMinute),
+    )
+}
+
+/// Returns true if the given key or physical mouse button is currently held down
+pub fn is_key_down(key: VIRTUAL_KEY) -> bool {
+    // the most significant bit is set if the key is down
+    let state = unsafe { GetAsyncKeyState(key.0 as i32) };
+
+    state < 0
+}
+
+/// Brings the given window to the foreground and gives it keyboard focus
+pub fn raise_and_focus_window(hwnd: Hwnd) -> Result<()> {
+    let event = [INPUT {
+        r#type: INPUT_MOUSE,
+        ..Default::default()
+    }];
+
+    unsafe {
+        // Send an input event to our own process first so that we pass the
+        // foreground lock check
+        SendInput(&event, size_of::<INPUT>() as i32);
+        // Error ignored, as the operation is not always necessary.
+
+        SetForegroundWindow(HWND::from(hwnd))
+    }
+    .ok()
+    .process()
+}
+
+/// Brings the given window to the foreground and gives it keyboard focus,
+/// using a synthesized Alt key tap to pass the foreground lock check
+pub fn raise_with_alt_tap(hwnd: Hwnd) -> Result<()> {
+    let key = |flags: KEYBD_EVENT_FLAGS| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: VK_MENU,
+                dwFlags: flags,
+                ..Default::default()
+            },
+        },
+    };
+
+    let events = [key(KEYBD_EVENT_FLAGS(0)), key(KEYEVENTF_KEYUP)];
+
+    unsafe {
+        SendInput(&events, size_of::<INPUT>() as i32);
+        SetForegroundWindow(HWND::from(hwnd))
+    }
+    .ok()
+    .process()
+}
+
+/// Brings the given window to the foreground and gives it keyboard focus by
+/// temporarily attaching to the input queue of the current foreground window
+pub fn raise_with_attached_thread_input(hwnd: Hwnd) -> Result<()> {
+    let current_thread = unsafe { GetCurrentThreadId() };
+    let foreground_thread = window_thread_id(foreground_window()?);
+
+    unsafe {
+        AttachThreadInput(current_thread, foreground_thread, true)
+            .ok()
+            .process()?;
+
+        let result = BringWindowToTop(HWND::from(hwnd))
+            .process()
+            .and_then(|_| SetForegroundWindow(HWND::from(hwnd)).ok().process());
+
+        AttachThreadInput(current_thread, foreground_thread, false)
+            .ok()
+            .process()?;
+
+        result
+    }
+}
+
+/// Returns the time in milliseconds after user input during which applications
+/// cannot force themselves into the foreground
+pub fn foreground_lock_timeout() -> Result<u32> {
+    let mut timeout = 0u32;
+
+    unsafe {
+        SystemParametersInfoW(
+            SPI_GETFOREGROUNDLOCKTIMEOUT,
+            0,
+            Some(&mut timeout as *mut u32 as *mut core::ffi::c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    }
+    .process()?;
+
+    Ok(timeout)
+}
+
+/// Sets the foreground lock timeout for the current session without
+/// persisting it to the user profile
+pub fn set_foreground_lock_timeout(timeout: u32) -> Result<()> {
+    unsafe {
+        SystemParametersInfoW(
+            SPI_SETFOREGROUNDLOCKTIMEOUT,
+            0,
+            Some(as_ptr!(timeout as usize)),
+            SPIF_SENDCHANGE,
+        )
+    }
+    .process()
+}
+
+/// Returns true if the built-in focus follows mouse setting ("activate a
+/// window by hovering over it with the mouse") is enabled
+pub fn active_window_tracking() -> Result<bool> {
+    let mut enabled = BOOL(0);
+
+    unsafe {
+        SystemParametersInfoW(
+            SPI_GETACTIVEWINDOWTRACKING,
+            0,
+            Some(&mut enabled as *mut BOOL as *mut core::ffi::c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    }
+    .process()?;
+
+    Ok(enabled.as_bool())
+}
+
+/// Installs and immediately removes a low-level mouse hook, returning an error
+/// if the hook which masir relies on to receive mouse movements can't be installed
+pub fn check_mouse_hook() -> Result<()> {
+    unsafe extern "system" fn callback(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        CallNextHookEx(HHOOK::default(), code, wparam, lparam)
+    }
+
+    unsafe {
+        let module = GetModuleHandleW(PCWSTR::null())?;
+        let hook = SetWindowsHookExW(WH_MOUSE_LL, Some(callback), module, 0)?;
+        UnhookWindowsHookEx(hook)?;
+    }
+
+    Ok(())
+}
+
+/// Returns true if the current process is running with elevated privileges
+pub fn is_elevated() -> Result<bool> {
+    let mut token = HANDLE::default();
+    let mut elevation = TOKEN_ELEVATION::default();
+    let mut len = 0;
+
+    unsafe {
+        OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token)?;
+
+        let result = GetTokenInformation(
+            token,
+            TokenElevation,
+            Some(&mut elevation as *mut TOKEN_ELEVATION as *mut core::ffi::c_void),
+            size_of::<TOKEN_ELEVATION>() as u32,
+            &mut len,
+        );
+
+        CloseHandle(token)?;
+        result?;
+    }
+
+    Ok(elevation.TokenIsElevated != 0)
+}
+
+/// Returns the executable names of every running process
+pub fn process_names() -> Result<Vec<String>> {
+    let mut names = vec![];
+
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0)?;
+        let mut entry = PROCESSENTRY32W {
+            dwSize: size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+
+        let mut result = Process32FirstW(snapshot, &mut entry);
+        while result.is_ok() {
+            let len = entry
+                .szExeFile
+                .iter()
+                .position(|c| *c == 0)
+                .unwrap_or(entry.szExeFile.len());
+
+            names.push(String::from_utf16_lossy(&entry.szExeFile[..len]));
+            result = Process32NextW(snapshot, &mut entry);
+        }
+
+        CloseHandle(snapshot)?;
+    }
+
+    Ok(names)
+}
+
+/// Moves the given window one step down in the z-order, behind the window
+/// which is currently directly below it, without activating it
+pub fn lower_window(hwnd: Hwnd) -> Result<()> {
+    unsafe {
+        let next = GetWindow(HWND::from(hwnd), GW_HWNDNEXT)?;
+
+        SetWindowPos(
+            HWND::from(hwnd),
+            next,
+            0,
+            0,
+            0,
+            0,
+            SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+        )
+    }
+    .process()
+}
+
+/// Moves the given window to the top of the z-order without activating it
+pub fn bring_to_top_without_activating(hwnd: Hwnd) -> Result<()> {
+    unsafe {
+        SetWindowPos(
+            HWND::from(hwnd),
+            HWND_TOP,
+            0,
+            0,
+            0,
+            0,
+            SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+        )
+    }
+    .process()
+}
+
+/// Returns the visible top-level windows owned by the given window, such as
+/// find dialogs and tool palettes
+pub fn owned_windows(hwnd: Hwnd) -> Result<Vec<Hwnd>> {
+    struct Search {
+        owner: Hwnd,
+        owned: Vec<Hwnd>,
+    }
+
+    unsafe extern "system" fn callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let search = &mut *(lparam.0 as *mut Search);
+
+        if let Ok(owner) = GetWindow(hwnd, GW_OWNER) {
+            if Hwnd::from(owner) == search.owner && IsWindowVisible(hwnd).as_bool() {
+                search.owned.push(Hwnd::from(hwnd));
+            }
+        }
+
+        true.into()
+    }
+
+    let mut search = Search {
+        owner: hwnd,
+        owned: vec![],
+    };
+
+    unsafe { EnumWindows(Some(callback), LPARAM(&mut search as *mut Search as isize)) }
+        .process()?;
+
+    Ok(search.owned)
+}
+
+/// Returns the visible top-level windows from the top of the z-order to the
+/// bottom
+pub fn top_level_windows() -> Result<Vec<Hwnd>> {
+    unsafe extern "system" fn callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let windows = &mut *(lparam.0 as *mut Vec<Hwnd>);
+
+        if IsWindowVisible(hwnd).as_bool() {
+            windows.push(Hwnd::from(hwnd));
+        }
+
+        true.into()
+    }
+
+    let mut windows: Vec<Hwnd> = vec![];
+
+    unsafe {
+        EnumWindows(
+            Some(callback),
+            LPARAM(&mut windows as *mut Vec<Hwnd> as isize),
+        )
+    }
+    .process()?;
+
+    Ok(windows)
+}
+
+/// Installs an out-of-context hook which calls `callback` for every event from
+/// `event_min` to `event_max` raised by other processes
+///
+/// The callback runs on the calling thread, which must pump messages with
+/// [`pump_messages`] for it to be called.
+pub fn set_win_event_hook(
+    event_min: u32,
+    event_max: u32,
+    callback: WINEVENTPROC,
+) -> Result<HWINEVENTHOOK> {
+    let hook = unsafe {
+        SetWinEventHook(
+            event_min,
+            event_max,
+            HMODULE::default(),
+            callback,
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+        )
+    };
+
+    if hook.is_invalid() {
+        Err(eyre!(
+            "could not set hook for events {event_min}-{event_max}"
+        ))
+    } else {
+        Ok(hook)
+    }
+}
+
+/// Retrieves and dispatches messages for the calling thread until it receives
+/// WM_QUIT
+pub fn pump_messages() {
+    let mut msg = MSG::default();
+
+    unsafe {
+        while GetMessageW(&mut msg, HWND::default(), 0, 0).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}
+
+/// Creates a message-only window of a newly registered class, whose messages
+/// are passed to `window_proc` by [`pump_messages`] on the calling thread
+pub fn create_message_window(class: &str, window_proc: WNDPROC) -> Result<Hwnd> {
+    create_window(class, window_proc, HWND_MESSAGE)
+}
+
+/// Creates a top-level window of a newly registered class which is never
+/// shown, for messages which are only broadcast to top-level windows (such as
+/// `WM_DISPLAYCHANGE`), whose messages are passed to `window_proc` by
+/// [`pump_messages`] on the calling thread
+pub fn create_hidden_window(class: &str, window_proc: WNDPROC) -> Result<Hwnd> {
+    create_window(class, window_proc, HWND::default())
+}
+
+fn create_window(class: &str, window_proc: WNDPROC, parent: HWND) -> Result<Hwnd> {
+    let class = HSTRING::from(class);
+    let instance = HINSTANCE(unsafe { GetModuleHandleW(None) }.process()?.0);
+
+    let wnd_class = WNDCLASSW {
+        lpfnWndProc: window_proc,
+        hInstance: instance,
+        lpszClassName: PCWSTR(class.as_ptr()),
+        ..Default::default()
+    };
+
+    Result::from(WindowsResult::from(unsafe { RegisterClassW(&wnd_class) }))?;
+
+    unsafe {
+        CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            PCWSTR(class.as_ptr()),
+            PCWSTR(class.as_ptr()),
+            WINDOW_STYLE::default(),
+            0,
+            0,
+            0,
+            0,
+            parent,
+            None,
+            instance,
+            None,
+        )
+    }
+    .process()
+    .map(Hwnd::from)
+}
+
+/// Registers the given window to receive `WM_WTSSESSION_CHANGE` messages for
+/// the current session
+pub fn register_session_notification(hwnd: Hwnd) -> Result<()> {
+    unsafe { WTSRegisterSessionNotification(HWND::from(hwnd), NOTIFY_FOR_THIS_SESSION) }.process()
+}
+
+/// Returns the class name of the given window
+pub fn real_window_class_w(hwnd: Hwnd) -> Result<String> {
+    const BUF_SIZE: usize = 512;
+    let mut class: [u16; BUF_SIZE] = [0; BUF_SIZE];
+
+    let len = Result::from(WindowsResult::from(unsafe {
+        RealGetWindowClassW(HWND::from(hwnd), &mut class)
+    }))?;
+
+    Ok(String::from_utf16(&class[0..len as usize])?)
+}
+
+/// Returns the title of the given window
+pub fn window_title(hwnd: Hwnd) -> Result<String> {
+    const BUF_SIZE: usize = 512;
+    let mut title: [u16; BUF_SIZE] = [0; BUF_SIZE];
+
+    let len = Result::from(WindowsResult::from(unsafe {
+        GetWindowTextW(HWND::from(hwnd), &mut title)
+    }))?;
+
+    Ok(String::from_utf16(&title[0..len as usize])?)
+}
+
+/// Returns the bounds of the monitor which the given point is on, or the
+/// nearest one if it is off screen
+pub fn monitor_rect(point: POINT) -> Result<RECT> {
+    let hmonitor = unsafe { MonitorFromPoint(point, MONITOR_DEFAULTTONEAREST) };
+
+    let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+
+    unsafe { GetMonitorInfoW(hmonitor, &mut info) }.ok()?;
+
+    Ok(info.rcMonitor)
+}
+
+/// Returns the device name (e.g. `\\.\DISPLAY1`) of the monitor which the
+/// given window is mostly on
+pub fn monitor_device_name(hwnd: Hwnd) -> Result<String> {
+    let hmonitor = unsafe { MonitorFromWindow(HWND::from(hwnd), MONITOR_DEFAULTTONEAREST) };
+
+    let mut info = MONITORINFOEXW {
+        monitorInfo: MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFOEXW>() as u32,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    unsafe { GetMonitorInfoW(hmonitor, &mut info.monitorInfo) }.ok()?;
+
+    let len = info
+        .szDevice
+        .iter()
+        .position(|c| *c == 0)
+        .unwrap_or(info.szDevice.len());
+
+    Ok(String::from_utf16(&info.szDevice[..len])?)
+}
+
+/// Returns the id of the thread which created the given window
+pub fn window_thread_id(hwnd: Hwnd) -> u32 {
+    unsafe { GetWindowThreadProcessId(HWND::from(hwnd), None) }
+}
+
+/// Returns the id of the process which created the given window
+pub fn window_process_id(hwnd: Hwnd) -> u32 {
+    let mut process_id = 0;
+    unsafe { GetWindowThreadProcessId(HWND::from(hwnd), Some(&mut process_id)) };
+
+    process_id
+}
+
+/// Returns the executable name (e.g. `firefox.exe`) of the process which
+/// created the given window
+pub fn window_exe(hwnd: Hwnd) -> Result<String> {
+    process_exe(window_process_id(hwnd))
+}
+
+/// Returns the executable name (e.g. `firefox.exe`) of the given process
+fn process_exe(process_id: u32) -> Result<String> {
+    const BUF_SIZE: usize = 512;
+    let mut path: [u16; BUF_SIZE] = [0; BUF_SIZE];
+    let mut len = BUF_SIZE as u32;
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id)?;
+
+        let result = QueryFullProcessImageNameW(
+            handle,
+            PROCESS_NAME_WIN32,
+            PWSTR(path.as_mut_ptr()),
+            &mut len,
+        );
+
+        CloseHandle(handle)?;
+        result?;
+    }
+
+    let path = String::from_utf16(&path[0..len as usize])?;
+
+    Ok(path
+        .rsplit('\\')
+        .next()
+        .map(ToString::to_string)
+        .unwrap_or(path))
+}
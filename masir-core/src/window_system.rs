@@ -0,0 +1,271 @@
+//! An abstraction over the window queries and actions used by the engine, so
+//! that the decision logic can be exercised without a live desktop
+
+use crate::hit_test::HitTest;
+use crate::hwnd::Hwnd;
+use crate::raise;
+use crate::raise::RaiseStrategy;
+use crate::schedule::LocalTime;
+use crate::winapi;
+use color_eyre::Result;
+
+#[cfg(any(test, feature = "test-util"))]
+mod mock;
+
+#[cfg(any(test, feature = "test-util"))]
+pub use mock::MockWindow;
+#[cfg(any(test, feature = "test-util"))]
+pub use mock::MockWindowSystem;
+
+pub trait WindowSystem {
+    /// Returns the window at the current cursor position using the given
+    /// hit-testing strategy
+    fn window_at_cursor_pos(&self, hit_test: HitTest) -> Result<Hwnd>;
+    /// Returns the current cursor position in screen coordinates
+    fn cursor_pos(&self) -> Result<(i32, i32)>;
+    /// Returns the current foreground window
+    fn foreground_window(&self) -> Result<Hwnd>;
+    /// Returns the top-level window which the given window belongs to
+    fn root_window(&self, hwnd: Hwnd) -> Result<Hwnd>;
+    /// Returns the class name of the given window
+    fn class(&self, hwnd: Hwnd) -> Result<String>;
+    /// Returns the executable name of the process which created the given window
+    fn exe(&self, hwnd: Hwnd) -> Result<String>;
+    /// Returns the title of the given window
+    fn title(&self, hwnd: Hwnd) -> Result<String>;
+    /// Returns true if the window has a style which indicates that it should
+    /// never be activated
+    fn has_filtered_style(&self, hwnd: Hwnd) -> bool;
+    /// Returns the id of the process which created the given window
+    fn process_id(&self, hwnd: Hwnd) -> u32;
+    /// Returns the width and height of the given window
+    fn size(&self, hwnd: Hwnd) -> Result<(i32, i32)>;
+    /// Returns true if the window is a transient surface such as a tooltip or
+    /// a popup menu, which should never be raised
+    fn is_transient(&self, hwnd: Hwnd) -> bool;
+    /// Returns true if the window is visible, not minimized and on screen
+    fn is_visible(&self, hwnd: Hwnd) -> bool;
+    /// Returns true if the given top-level window is on the current virtual
+    /// desktop
+    fn is_on_current_virtual_desktop(&self, hwnd: Hwnd) -> bool;
+    /// Returns the device name of the monitor which the given window is mostly on
+    fn monitor(&self, hwnd: Hwnd) -> Result<String>;
+    /// Brings the given window to the foreground and gives it keyboard focus
+    /// using the given strategy
+    fn raise(&self, hwnd: Hwnd, strategy: RaiseStrategy) -> Result<()>;
+    /// Moves the given window one step down in the z-order
+    fn lower(&self, hwnd: Hwnd) -> Result<()>;
+    /// Returns the visible windows owned by the given window
+    fn owned_windows(&self, hwnd: Hwnd) -> Result<Vec<Hwnd>>;
+    /// Moves the given window to the top of the z-order without activating it
+    fn bring_to_top(&self, hwnd: Hwnd) -> Result<()>;
+    /// Gives keyboard focus to the control under the cursor if it belongs to
+    /// the given top-level window
+    fn focus_control_at_cursor(&self, hwnd: Hwnd) -> Result<()>;
+    /// Returns true if the system is running on battery power
+    fn is_on_battery(&self) -> bool;
+    /// Returns the executable names of processes which are playing audio
+    fn audio_playing_exes(&self) -> Result<Vec<String>>;
+    /// Returns the local day and time of day
+    fn local_time(&self) -> LocalTime;
+}
+
+impl<T: WindowSystem> WindowSystem for &T {
+    fn window_at_cursor_pos(&self, hit_test: HitTest) -> Result<Hwnd> {
+        (*self).window_at_cursor_pos(hit_test)
+    }
+
+    fn cursor_pos(&self) -> Result<(i32, i32)> {
+        (*self).cursor_pos()
+    }
+
+    fn foreground_window(&self) -> Result<Hwnd> {
+        (*self).foreground_window()
+    }
+
+    fn root_window(&self, hwnd: Hwnd) -> Result<Hwnd> {
+        (*self).root_window(hwnd)
+    }
+
+    fn class(&self, hwnd: Hwnd) -> Result<String> {
+        (*self).class(hwnd)
+    }
+
+    fn exe(&self, hwnd: Hwnd) -> Result<String> {
+        (*self).exe(hwnd)
+    }
+
+    fn title(&self, hwnd: Hwnd) -> Result<String> {
+        (*self).title(hwnd)
+    }
+
+    fn has_filtered_style(&self, hwnd: Hwnd) -> bool {
+        (*self).has_filtered_style(hwnd)
+    }
+
+    fn process_id(&self, hwnd: Hwnd) -> u32 {
+        (*self).process_id(hwnd)
+    }
+
+    fn size(&self, hwnd: Hwnd) -> Result<(i32, i32)> {
+        (*self).size(hwnd)
+    }
+
+    fn is_transient(&self, hwnd: Hwnd) -> bool {
+        (*self).is_transient(hwnd)
+    }
+
+    fn is_visible(&self, hwnd: Hwnd) -> bool {
+        (*self).is_visible(hwnd)
+    }
+
+    fn is_on_current_virtual_desktop(&self, hwnd: Hwnd) -> bool {
+        (*self).is_on_current_virtual_desktop(hwnd)
+    }
+
+    fn monitor(&self, hwnd: Hwnd) -> Result<String> {
+        (*self).monitor(hwnd)
+    }
+
+    fn raise(&self, hwnd: Hwnd, strategy: RaiseStrategy) -> Result<()> {
+        (*self).raise(hwnd, strategy)
+    }
+
+    fn lower(&self, hwnd: Hwnd) -> Result<()> {
+        (*self).lower(hwnd)
+    }
+
+    fn owned_windows(&self, hwnd: Hwnd) -> Result<Vec<Hwnd>> {
+        (*self).owned_windows(hwnd)
+    }
+
+    fn bring_to_top(&self, hwnd: Hwnd) -> Result<()> {
+        (*self).bring_to_top(hwnd)
+    }
+
+    fn focus_control_at_cursor(&self, hwnd: Hwnd) -> Result<()> {
+        (*self).focus_control_at_cursor(hwnd)
+    }
+
+    fn is_on_battery(&self) -> bool {
+        (*self).is_on_battery()
+    }
+
+    fn audio_playing_exes(&self) -> Result<Vec<String>> {
+        (*self).audio_playing_exes()
+    }
+
+    fn local_time(&self) -> LocalTime {
+        (*self).local_time()
+    }
+}
+
+/// The real window system, backed by Win32 API calls
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Win32;
+
+impl WindowSystem for Win32 {
+    fn window_at_cursor_pos(&self, hit_test: HitTest) -> Result<Hwnd> {
+        let point = winapi::cursor_pos()?;
+
+        match hit_test {
+            HitTest::WindowFromPoint => winapi::window_from_point(point),
+            HitTest::ChildWindowFromPointEx {
+                skip_invisible,
+                skip_transparent,
+            } => winapi::child_window_from_point_ex(point, skip_invisible, skip_transparent),
+            HitTest::RealChildWindowFromPoint => winapi::real_child_window_from_point(point),
+            HitTest::UiAutomation => {
+                winapi::ui_automation_window_from_point(point).or_else(|error| {
+                    tracing::debug!("falling back to WindowFromPoint: {error}");
+                    winapi::window_from_point(point)
+                })
+            }
+        }
+    }
+
+    fn cursor_pos(&self) -> Result<(i32, i32)> {
+        winapi::cursor_pos().map(|point| (point.x, point.y))
+    }
+
+    fn foreground_window(&self) -> Result<Hwnd> {
+        winapi::foreground_window()
+    }
+
+    fn root_window(&self, hwnd: Hwnd) -> Result<Hwnd> {
+        hwnd.root()
+    }
+
+    fn class(&self, hwnd: Hwnd) -> Result<String> {
+        hwnd.class()
+    }
+
+    fn exe(&self, hwnd: Hwnd) -> Result<String> {
+        winapi::window_exe(hwnd)
+    }
+
+    fn title(&self, hwnd: Hwnd) -> Result<String> {
+        winapi::window_title(hwnd)
+    }
+
+    fn has_filtered_style(&self, hwnd: Hwnd) -> bool {
+        winapi::has_filtered_style(hwnd)
+    }
+
+    fn process_id(&self, hwnd: Hwnd) -> u32 {
+        winapi::window_process_id(hwnd)
+    }
+
+    fn size(&self, hwnd: Hwnd) -> Result<(i32, i32)> {
+        winapi::window_size(hwnd)
+    }
+
+    fn is_transient(&self, hwnd: Hwnd) -> bool {
+        winapi::is_transient(hwnd)
+    }
+
+    fn is_visible(&self, hwnd: Hwnd) -> bool {
+        winapi::is_visible(hwnd)
+    }
+
+    fn is_on_current_virtual_desktop(&self, hwnd: Hwnd) -> bool {
+        // a window which can't be looked up is assumed to still be around
+        winapi::is_on_current_virtual_desktop(hwnd).unwrap_or(true)
+    }
+
+    fn monitor(&self, hwnd: Hwnd) -> Result<String> {
+        winapi::monitor_device_name(hwnd)
+    }
+
+    fn raise(&self, hwnd: Hwnd, strategy: RaiseStrategy) -> Result<()> {
+        raise::raise_with_timeout(hwnd, strategy)
+    }
+
+    fn lower(&self, hwnd: Hwnd) -> Result<()> {
+        winapi::lower_window(hwnd)
+    }
+
+    fn owned_windows(&self, hwnd: Hwnd) -> Result<Vec<Hwnd>> {
+        winapi::owned_windows(hwnd)
+    }
+
+    fn bring_to_top(&self, hwnd: Hwnd) -> Result<()> {
+        winapi::bring_to_top_without_activating(hwnd)
+    }
+
+    fn focus_control_at_cursor(&self, hwnd: Hwnd) -> Result<()> {
+        winapi::focus_control_at(winapi::cursor_pos()?, hwnd)
+    }
+
+    fn is_on_battery(&self) -> bool {
+        winapi::is_on_battery()
+    }
+
+    fn audio_playing_exes(&self) -> Result<Vec<String>> {
+        winapi::audio_playing_exes()
+    }
+
+    fn local_time(&self) -> LocalTime {
+        winapi::local_time()
+    }
+}
@@ -0,0 +1,337 @@
+//! An in-memory window system for exercising the engine in tests, which is
+//! only built for this crate's tests or with the `test-util` feature
+
+use super::WindowSystem;
+use crate::hit_test::HitTest;
+use crate::hwnd::Hwnd;
+use crate::raise::RaiseStrategy;
+use crate::raise::RaiseTimedOut;
+use crate::schedule::LocalTime;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// A window known to [`MockWindowSystem`]
+#[derive(Debug, Clone, Default)]
+pub struct MockWindow {
+    pub root: Option<Hwnd>,
+    pub owner: Option<Hwnd>,
+    pub class: String,
+    pub exe: String,
+    pub title: String,
+    pub filtered_style: bool,
+    pub transient: bool,
+    pub hidden: bool,
+    pub process_id: u32,
+    pub size: (i32, i32),
+    pub monitor: String,
+    pub virtual_desktop: u32,
+    /// Raise strategies which fail for this window
+    pub failing_strategies: Vec<RaiseStrategy>,
+    /// Raise strategies which time out for this window
+    pub hung_strategies: Vec<RaiseStrategy>,
+    /// Raising this window times out as though its thread were hung
+    pub unresponsive: bool,
+}
+
+impl MockWindow {
+    pub fn new(class: &str) -> Self {
+        Self {
+            class: class.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_root(mut self, root: Hwnd) -> Self {
+        self.root = Some(root);
+        self
+    }
+
+    pub fn with_owner(mut self, owner: Hwnd) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    pub fn with_exe(mut self, exe: &str) -> Self {
+        self.exe = exe.to_string();
+        self
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn with_filtered_style(mut self) -> Self {
+        self.filtered_style = true;
+        self
+    }
+
+    pub fn with_transient(mut self) -> Self {
+        self.transient = true;
+        self
+    }
+
+    pub fn with_hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    pub fn with_process_id(mut self, process_id: u32) -> Self {
+        self.process_id = process_id;
+        self
+    }
+
+    pub fn with_size(mut self, width: i32, height: i32) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    pub fn with_monitor(mut self, monitor: &str) -> Self {
+        self.monitor = monitor.to_string();
+        self
+    }
+
+    pub fn with_virtual_desktop(mut self, virtual_desktop: u32) -> Self {
+        self.virtual_desktop = virtual_desktop;
+        self
+    }
+
+    pub fn with_failing_strategy(mut self, strategy: RaiseStrategy) -> Self {
+        self.failing_strategies.push(strategy);
+        self
+    }
+
+    pub fn with_hung_strategy(mut self, strategy: RaiseStrategy) -> Self {
+        self.hung_strategies.push(strategy);
+        self
+    }
+
+    pub fn unresponsive(mut self) -> Self {
+        self.unresponsive = true;
+        self
+    }
+}
+
+/// An in-memory window system for testing, where raising a window makes it
+/// the foreground window
+#[derive(Debug, Default)]
+pub struct MockWindowSystem {
+    windows: HashMap<Hwnd, MockWindow>,
+    cursor_window: Cell<Hwnd>,
+    cursor_pos: Cell<(i32, i32)>,
+    foreground_window: Cell<Hwnd>,
+    raised: RefCell<Vec<Hwnd>>,
+    raise_attempts: Cell<usize>,
+    lowered: RefCell<Vec<Hwnd>>,
+    brought_to_top: RefCell<Vec<Hwnd>>,
+    focused_controls: RefCell<Vec<Hwnd>>,
+    on_battery: Cell<bool>,
+    audio_playing_exes: RefCell<Vec<String>>,
+    current_virtual_desktop: Cell<u32>,
+    local_time: Cell<LocalTime>,
+}
+
+impl MockWindowSystem {
+    pub fn new(cursor_window: Hwnd, foreground_window: Hwnd) -> Self {
+        Self {
+            cursor_window: Cell::new(cursor_window),
+            foreground_window: Cell::new(foreground_window),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_window(mut self, hwnd: Hwnd, window: MockWindow) -> Self {
+        self.windows.insert(hwnd, window);
+        self
+    }
+
+    pub fn set_cursor_window(&self, hwnd: Hwnd) {
+        self.cursor_window.set(hwnd);
+    }
+
+    pub fn set_cursor_pos(&self, x: i32, y: i32) {
+        self.cursor_pos.set((x, y));
+    }
+
+    pub fn set_foreground_window(&self, hwnd: Hwnd) {
+        self.foreground_window.set(hwnd);
+    }
+
+    pub fn set_on_battery(&self, on_battery: bool) {
+        self.on_battery.set(on_battery);
+    }
+
+    pub fn set_audio_playing_exes(&self, exes: &[&str]) {
+        *self.audio_playing_exes.borrow_mut() = exes.iter().map(|exe| exe.to_string()).collect();
+    }
+
+    pub fn set_local_time(&self, local_time: LocalTime) {
+        self.local_time.set(local_time);
+    }
+
+    pub fn set_current_virtual_desktop(&self, virtual_desktop: u32) {
+        self.current_virtual_desktop.set(virtual_desktop);
+    }
+
+    /// Returns every window which has been raised, in order
+    pub fn raised(&self) -> Vec<Hwnd> {
+        self.raised.borrow().clone()
+    }
+
+    /// Returns every child window which has been given keyboard focus, in
+    /// order
+    pub fn focused_controls(&self) -> Vec<Hwnd> {
+        self.focused_controls.borrow().clone()
+    }
+
+    /// Returns every window which has been lowered, in order
+    pub fn lowered(&self) -> Vec<Hwnd> {
+        self.lowered.borrow().clone()
+    }
+
+    /// Returns every window which has been brought to the top without being
+    /// activated, in order
+    pub fn brought_to_top(&self) -> Vec<Hwnd> {
+        self.brought_to_top.borrow().clone()
+    }
+
+    /// Returns how many times raising a window has been attempted
+    pub fn raise_attempts(&self) -> usize {
+        self.raise_attempts.get()
+    }
+
+    fn window(&self, hwnd: Hwnd) -> Result<&MockWindow> {
+        self.windows
+            .get(&hwnd)
+            .ok_or_else(|| eyre!("invalid window handle: {hwnd}"))
+    }
+}
+
+impl WindowSystem for MockWindowSystem {
+    fn window_at_cursor_pos(&self, _hit_test: HitTest) -> Result<Hwnd> {
+        Ok(self.cursor_window.get())
+    }
+
+    fn cursor_pos(&self) -> Result<(i32, i32)> {
+        Ok(self.cursor_pos.get())
+    }
+
+    fn foreground_window(&self) -> Result<Hwnd> {
+        Ok(self.foreground_window.get())
+    }
+
+    fn root_window(&self, hwnd: Hwnd) -> Result<Hwnd> {
+        Ok(self.window(hwnd)?.root.unwrap_or(hwnd))
+    }
+
+    fn class(&self, hwnd: Hwnd) -> Result<String> {
+        Ok(self.window(hwnd)?.class.clone())
+    }
+
+    fn exe(&self, hwnd: Hwnd) -> Result<String> {
+        Ok(self.window(hwnd)?.exe.clone())
+    }
+
+    fn title(&self, hwnd: Hwnd) -> Result<String> {
+        Ok(self.window(hwnd)?.title.clone())
+    }
+
+    fn has_filtered_style(&self, hwnd: Hwnd) -> bool {
+        self.window(hwnd).is_ok_and(|window| window.filtered_style)
+    }
+
+    fn process_id(&self, hwnd: Hwnd) -> u32 {
+        self.window(hwnd).map_or(0, |window| window.process_id)
+    }
+
+    fn size(&self, hwnd: Hwnd) -> Result<(i32, i32)> {
+        Ok(self.window(hwnd)?.size)
+    }
+
+    fn is_transient(&self, hwnd: Hwnd) -> bool {
+        self.window(hwnd).is_ok_and(|window| window.transient)
+    }
+
+    fn is_visible(&self, hwnd: Hwnd) -> bool {
+        self.window(hwnd).is_ok_and(|window| !window.hidden)
+    }
+
+    fn is_on_current_virtual_desktop(&self, hwnd: Hwnd) -> bool {
+        self.window(hwnd)
+            .is_ok_and(|window| window.virtual_desktop == self.current_virtual_desktop.get())
+    }
+
+    fn monitor(&self, hwnd: Hwnd) -> Result<String> {
+        Ok(self.window(hwnd)?.monitor.clone())
+    }
+
+    fn raise(&self, hwnd: Hwnd, strategy: RaiseStrategy) -> Result<()> {
+        self.raise_attempts.set(self.raise_attempts.get() + 1);
+
+        let window = self.window(hwnd)?;
+
+        if window.unresponsive || window.hung_strategies.contains(&strategy) {
+            return Err(RaiseTimedOut.into());
+        }
+
+        if window.failing_strategies.contains(&strategy) {
+            return Err(eyre!("could not raise {hwnd} with {strategy:?}"));
+        }
+
+        self.raised.borrow_mut().push(hwnd);
+        self.foreground_window.set(hwnd);
+
+        Ok(())
+    }
+
+    fn lower(&self, hwnd: Hwnd) -> Result<()> {
+        self.window(hwnd)?;
+        self.lowered.borrow_mut().push(hwnd);
+
+        Ok(())
+    }
+
+    fn owned_windows(&self, hwnd: Hwnd) -> Result<Vec<Hwnd>> {
+        let mut owned: Vec<Hwnd> = self
+            .windows
+            .iter()
+            .filter(|(_, window)| window.owner == Some(hwnd))
+            .map(|(owned_hwnd, _)| *owned_hwnd)
+            .collect();
+
+        owned.sort();
+
+        Ok(owned)
+    }
+
+    fn bring_to_top(&self, hwnd: Hwnd) -> Result<()> {
+        self.window(hwnd)?;
+        self.brought_to_top.borrow_mut().push(hwnd);
+
+        Ok(())
+    }
+
+    fn focus_control_at_cursor(&self, hwnd: Hwnd) -> Result<()> {
+        let control = self.cursor_window.get();
+        if control != hwnd && self.root_window(control)? == hwnd {
+            self.focused_controls.borrow_mut().push(control);
+        }
+
+        Ok(())
+    }
+
+    fn is_on_battery(&self) -> bool {
+        self.on_battery.get()
+    }
+
+    fn audio_playing_exes(&self) -> Result<Vec<String>> {
+        Ok(self.audio_playing_exes.borrow().clone())
+    }
+
+    fn local_time(&self) -> LocalTime {
+        self.local_time.get()
+    }
+}
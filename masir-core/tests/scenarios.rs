@@ -0,0 +1,187 @@
+//! Golden tests which replay recorded scenarios against the engine with a mock
+//! window system and assert every decision, so that changes in behaviour for
+//! real-world applications are caught before a release.
+//!
+//! Each file in `tests/scenarios` describes the windows involved and a
+//! sequence of steps, where each step moves the cursor over a window, and
+//! optionally gives another window focus first as a click or the keyboard
+//! would:
+//!
+//! ```toml
+//! description = "what the scenario covers"
+//!
+//! # optional, in the same format as masir.toml
+//! [config]
+//! delay = 0
+//!
+//! [[windows]]
+//! hwnd = 1
+//! class = "MozillaWindowClass"
+//! exe = "firefox.exe"
+//! # also title, root, filtered_style, transient, hidden, process_id and size
+//!
+//! [[steps]]
+//! cursor = 1
+//! foreground = 2
+//! # the type of the decision reason, as in masir status
+//! reason = "Eligible"
+//! # the window which is raised, if any
+//! raised = 1
+//! ```
+
+use masir_core::config::Config;
+use masir_core::engine::Engine;
+use masir_core::hwnd::Hwnd;
+use masir_core::window_system::MockWindow;
+use masir_core::window_system::MockWindowSystem;
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Scenario {
+    #[allow(dead_code)]
+    description: String,
+    #[serde(default)]
+    config: Config,
+    windows: Vec<WindowSnapshot>,
+    steps: Vec<Step>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WindowSnapshot {
+    hwnd: Hwnd,
+    class: String,
+    #[serde(default)]
+    exe: String,
+    #[serde(default)]
+    title: String,
+    root: Option<Hwnd>,
+    #[serde(default)]
+    filtered_style: bool,
+    #[serde(default)]
+    transient: bool,
+    #[serde(default)]
+    hidden: bool,
+    #[serde(default)]
+    process_id: u32,
+    #[serde(default)]
+    size: (i32, i32),
+}
+
+impl WindowSnapshot {
+    fn to_mock_window(&self) -> MockWindow {
+        MockWindow {
+            root: self.root,
+            class: self.class.clone(),
+            exe: self.exe.clone(),
+            title: self.title.clone(),
+            filtered_style: self.filtered_style,
+            transient: self.transient,
+            hidden: self.hidden,
+            process_id: self.process_id,
+            size: self.size,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Step {
+    cursor: Hwnd,
+    foreground: Option<Hwnd>,
+    reason: String,
+    raised: Option<Hwnd>,
+}
+
+/// Replays a scenario, returning a description of each step whose decision
+/// differs from the recorded one
+fn replay(scenario: &Scenario) -> Vec<String> {
+    let (Some(first), Some(first_window)) = (scenario.steps.first(), scenario.windows.first())
+    else {
+        return vec![String::from("the scenario has no windows or no steps")];
+    };
+
+    let window_system = scenario.windows.iter().fold(
+        MockWindowSystem::new(first.cursor, first.foreground.unwrap_or(first_window.hwnd)),
+        |window_system, window| window_system.with_window(window.hwnd, window.to_mock_window()),
+    );
+
+    let settings = match scenario.config.settings(None) {
+        Ok(settings) => settings,
+        Err(error) => return vec![format!("invalid config: {error:#}")],
+    };
+
+    let mut engine = Engine::new(&window_system, vec![], settings);
+    let mut mismatches = vec![];
+
+    for (i, step) in scenario.steps.iter().enumerate() {
+        if let Some(foreground) = step.foreground {
+            window_system.set_foreground_window(foreground);
+        }
+
+        window_system.set_cursor_window(step.cursor);
+
+        let raised_before = window_system.raised().len();
+        engine.handle_mouse_move();
+        let raised = window_system.raised().get(raised_before).copied();
+
+        let reason = engine
+            .last_decision()
+            .and_then(|decision| serde_json::to_value(&decision.reason).ok())
+            .and_then(|reason| reason["type"].as_str().map(String::from))
+            .unwrap_or_default();
+
+        if reason != step.reason || raised != step.raised {
+            mismatches.push(format!(
+                "step {i}: expected {} raising {:?}, got {reason} raising {raised:?}",
+                step.reason, step.raised
+            ));
+        }
+    }
+
+    mismatches
+}
+
+#[test]
+fn decisions_match_recorded_scenarios() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios");
+
+    let mut paths: Vec<_> = std::fs::read_dir(&directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .collect();
+
+    paths.sort();
+    assert!(!paths.is_empty(), "no scenarios in {}", directory.display());
+
+    let failures: Vec<String> = paths
+        .iter()
+        .flat_map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+
+            let scenario = std::fs::read_to_string(path)
+                .map_err(|error| error.to_string())
+                .and_then(|contents| {
+                    toml::from_str::<Scenario>(&contents).map_err(|error| error.to_string())
+                });
+
+            let mismatches = match scenario {
+                Ok(scenario) => replay(&scenario),
+                Err(error) => vec![format!("could not be parsed: {error}")],
+            };
+
+            mismatches
+                .into_iter()
+                .map(move |mismatch| format!("{name}: {mismatch}"))
+        })
+        .collect();
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
@@ -0,0 +1,346 @@
+//! Integration tests which create real Win32 windows with specific classes and
+//! styles, move the cursor over them and assert which window the engine
+//! focuses.
+//!
+//! These tests need an interactive desktop and take control of the cursor, so
+//! they are ignored by default and must be run one at a time:
+//!
+//! ```shell
+//! cargo test -p masir-core --test windows -- --ignored --test-threads=1
+//! ```
+
+use color_eyre::Result;
+use masir_core::config::ApplicationIdentifier;
+use masir_core::config::Config;
+use masir_core::config::MatchingRule;
+use masir_core::config::MatchingStrategy;
+use masir_core::config::ProfileConfig;
+use masir_core::config::Settings;
+use masir_core::engine::Engine;
+use masir_core::hit_test::HitTest;
+use masir_core::hwnd::Hwnd;
+use masir_core::raise::RaiseStrategy;
+use masir_core::schedule::LocalTime;
+use masir_core::window_system::Win32;
+use masir_core::window_system::WindowSystem;
+use std::path::PathBuf;
+use windows::core::HSTRING;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::HINSTANCE;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::Foundation::LPARAM;
+use windows::Win32::Foundation::LRESULT;
+use windows::Win32::Foundation::RECT;
+use windows::Win32::Foundation::WPARAM;
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::WindowsAndMessaging::CreateWindowExW;
+use windows::Win32::UI::WindowsAndMessaging::DefWindowProcW;
+use windows::Win32::UI::WindowsAndMessaging::DestroyWindow;
+use windows::Win32::UI::WindowsAndMessaging::DispatchMessageW;
+use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+use windows::Win32::UI::WindowsAndMessaging::GetWindowRect;
+use windows::Win32::UI::WindowsAndMessaging::PeekMessageW;
+use windows::Win32::UI::WindowsAndMessaging::RegisterClassW;
+use windows::Win32::UI::WindowsAndMessaging::SetCursorPos;
+use windows::Win32::UI::WindowsAndMessaging::SetForegroundWindow;
+use windows::Win32::UI::WindowsAndMessaging::TranslateMessage;
+use windows::Win32::UI::WindowsAndMessaging::MSG;
+use windows::Win32::UI::WindowsAndMessaging::PM_REMOVE;
+use windows::Win32::UI::WindowsAndMessaging::WINDOW_EX_STYLE;
+use windows::Win32::UI::WindowsAndMessaging::WNDCLASSW;
+use windows::Win32::UI::WindowsAndMessaging::WS_EX_TOOLWINDOW;
+use windows::Win32::UI::WindowsAndMessaging::WS_OVERLAPPEDWINDOW;
+use windows::Win32::UI::WindowsAndMessaging::WS_VISIBLE;
+
+struct TestWindow {
+    hwnd: HWND,
+}
+
+impl TestWindow {
+    fn new(class: &str, ex_style: WINDOW_EX_STYLE, x: i32) -> Self {
+        let class = HSTRING::from(class);
+
+        unsafe {
+            let instance = HINSTANCE(GetModuleHandleW(None).unwrap().0);
+
+            // registration fails harmlessly if the class is already registered
+            RegisterClassW(&WNDCLASSW {
+                lpfnWndProc: Some(window_proc),
+                hInstance: instance,
+                lpszClassName: PCWSTR(class.as_ptr()),
+                ..Default::default()
+            });
+
+            let hwnd = CreateWindowExW(
+                ex_style,
+                PCWSTR(class.as_ptr()),
+                PCWSTR(class.as_ptr()),
+                WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+                x,
+                100,
+                400,
+                300,
+                None,
+                None,
+                instance,
+                None,
+            )
+            .expect("could not create test window");
+
+            pump_messages();
+
+            Self { hwnd }
+        }
+    }
+
+    fn handle(&self) -> Hwnd {
+        Hwnd::from(self.hwnd)
+    }
+
+    fn hover(&self) {
+        let mut rect = RECT::default();
+
+        unsafe {
+            GetWindowRect(self.hwnd, &mut rect).unwrap();
+            SetCursorPos((rect.left + rect.right) / 2, (rect.top + rect.bottom) / 2).unwrap();
+        }
+
+        pump_messages();
+    }
+
+    fn focus(&self) {
+        unsafe {
+            let _ = SetForegroundWindow(self.hwnd);
+        }
+
+        pump_messages();
+    }
+}
+
+impl Drop for TestWindow {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DestroyWindow(self.hwnd);
+        }
+    }
+}
+
+extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+}
+
+fn pump_messages() {
+    let mut msg = MSG::default();
+
+    unsafe {
+        while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}
+
+fn foreground_window() -> Hwnd {
+    Hwnd::from(unsafe { GetForegroundWindow() })
+}
+
+/// The real window system, except that windows created by the test process
+/// are not treated as masir's own windows, which are never raised
+struct TestWindowSystem;
+
+impl WindowSystem for TestWindowSystem {
+    fn window_at_cursor_pos(&self, hit_test: HitTest) -> Result<Hwnd> {
+        Win32.window_at_cursor_pos(hit_test)
+    }
+
+    fn cursor_pos(&self) -> Result<(i32, i32)> {
+        Win32.cursor_pos()
+    }
+
+    fn foreground_window(&self) -> Result<Hwnd> {
+        Win32.foreground_window()
+    }
+
+    fn root_window(&self, hwnd: Hwnd) -> Result<Hwnd> {
+        Win32.root_window(hwnd)
+    }
+
+    fn class(&self, hwnd: Hwnd) -> Result<String> {
+        Win32.class(hwnd)
+    }
+
+    fn exe(&self, hwnd: Hwnd) -> Result<String> {
+        Win32.exe(hwnd)
+    }
+
+    fn title(&self, hwnd: Hwnd) -> Result<String> {
+        Win32.title(hwnd)
+    }
+
+    fn has_filtered_style(&self, hwnd: Hwnd) -> bool {
+        Win32.has_filtered_style(hwnd)
+    }
+
+    fn process_id(&self, _hwnd: Hwnd) -> u32 {
+        0
+    }
+
+    fn size(&self, hwnd: Hwnd) -> Result<(i32, i32)> {
+        Win32.size(hwnd)
+    }
+
+    fn is_transient(&self, hwnd: Hwnd) -> bool {
+        Win32.is_transient(hwnd)
+    }
+
+    fn is_visible(&self, hwnd: Hwnd) -> bool {
+        Win32.is_visible(hwnd)
+    }
+
+    fn is_on_current_virtual_desktop(&self, hwnd: Hwnd) -> bool {
+        Win32.is_on_current_virtual_desktop(hwnd)
+    }
+
+    fn monitor(&self, hwnd: Hwnd) -> Result<String> {
+        Win32.monitor(hwnd)
+    }
+
+    fn raise(&self, hwnd: Hwnd, strategy: RaiseStrategy) -> Result<()> {
+        Win32.raise(hwnd, strategy)
+    }
+
+    fn lower(&self, hwnd: Hwnd) -> Result<()> {
+        Win32.lower(hwnd)
+    }
+
+    fn owned_windows(&self, hwnd: Hwnd) -> Result<Vec<Hwnd>> {
+        Win32.owned_windows(hwnd)
+    }
+
+    fn bring_to_top(&self, hwnd: Hwnd) -> Result<()> {
+        Win32.bring_to_top(hwnd)
+    }
+
+    fn focus_control_at_cursor(&self, hwnd: Hwnd) -> Result<()> {
+        Win32.focus_control_at_cursor(hwnd)
+    }
+
+    fn is_on_battery(&self) -> bool {
+        Win32.is_on_battery()
+    }
+
+    fn audio_playing_exes(&self) -> Result<Vec<String>> {
+        Win32.audio_playing_exes()
+    }
+
+    fn local_time(&self) -> LocalTime {
+        Win32.local_time()
+    }
+}
+
+fn engine(hwnds: Vec<PathBuf>, settings: Settings) -> Engine<TestWindowSystem> {
+    Engine::new(TestWindowSystem, hwnds, settings)
+}
+
+#[test]
+#[ignore = "creates real windows and moves the cursor"]
+fn raises_window_under_cursor() {
+    let target = TestWindow::new("masir_test_target", WINDOW_EX_STYLE::default(), 100);
+    let foreground = TestWindow::new("masir_test_foreground", WINDOW_EX_STYLE::default(), 600);
+
+    foreground.focus();
+    target.hover();
+
+    engine(vec![], Settings::default()).handle_mouse_move();
+    pump_messages();
+
+    assert_eq!(foreground_window(), target.handle());
+}
+
+#[test]
+#[ignore = "creates real windows and moves the cursor"]
+fn hit_test_strategies_find_window_under_cursor() {
+    let target = TestWindow::new("masir_test_hit_test", WINDOW_EX_STYLE::default(), 100);
+    target.hover();
+
+    for hit_test in [
+        HitTest::WindowFromPoint,
+        HitTest::ChildWindowFromPointEx {
+            skip_invisible: true,
+            skip_transparent: true,
+        },
+        HitTest::RealChildWindowFromPoint,
+        HitTest::UiAutomation,
+    ] {
+        let hwnd = Win32.window_at_cursor_pos(hit_test).unwrap();
+        assert_eq!(
+            Win32.root_window(hwnd).unwrap(),
+            target.handle(),
+            "{hit_test:?}"
+        );
+    }
+}
+
+#[test]
+#[ignore = "creates real windows and moves the cursor"]
+fn does_not_raise_tool_windows() {
+    let target = TestWindow::new("masir_test_tool", WS_EX_TOOLWINDOW, 100);
+    let foreground = TestWindow::new("masir_test_foreground", WINDOW_EX_STYLE::default(), 600);
+
+    foreground.focus();
+    target.hover();
+
+    engine(vec![], Settings::default()).handle_mouse_move();
+    pump_messages();
+
+    assert_eq!(foreground_window(), foreground.handle());
+}
+
+#[test]
+#[ignore = "creates real windows and moves the cursor"]
+fn does_not_raise_windows_matching_ignore_rules() {
+    let target = TestWindow::new("masir_test_ignored", WINDOW_EX_STYLE::default(), 100);
+    let foreground = TestWindow::new("masir_test_foreground", WINDOW_EX_STYLE::default(), 600);
+
+    let settings = Config {
+        base: ProfileConfig {
+            ignore_rules: Some(vec![MatchingRule {
+                kind: ApplicationIdentifier::Class,
+                id: "masir_test_ignored".to_string(),
+                matching_strategy: MatchingStrategy::Equals,
+            }]),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+    .settings(None)
+    .unwrap();
+
+    foreground.focus();
+    target.hover();
+
+    engine(vec![], settings).handle_mouse_move();
+    pump_messages();
+
+    assert_eq!(foreground_window(), foreground.handle());
+}
+
+#[test]
+#[ignore = "creates real windows and moves the cursor"]
+fn does_not_raise_windows_missing_from_hwnds_file() {
+    let target = TestWindow::new("masir_test_unmanaged", WINDOW_EX_STYLE::default(), 100);
+    let foreground = TestWindow::new("masir_test_foreground", WINDOW_EX_STYLE::default(), 600);
+
+    let hwnds = std::env::temp_dir().join("masir_test.hwnd.json");
+    std::fs::write(&hwnds, format!("[{}]", foreground.handle())).unwrap();
+
+    foreground.focus();
+    target.hover();
+
+    engine(vec![hwnds.clone()], Settings::default()).handle_mouse_move();
+    pump_messages();
+
+    std::fs::remove_file(hwnds).unwrap();
+
+    assert_eq!(foreground_window(), foreground.handle());
+}
@@ -0,0 +1,29 @@
+//! Shell completions for the masir command line
+
+use clap::Command;
+use clap::ValueEnum;
+use clap_complete::generate;
+use clap_complete_nushell::Nushell;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Shell {
+    Powershell,
+    Bash,
+    Zsh,
+    Fish,
+    Nushell,
+}
+
+/// Writes the completion script for the given shell to stdout
+pub fn print(shell: Shell, command: &mut Command) {
+    let name = command.get_name().to_string();
+    let stdout = &mut std::io::stdout();
+
+    match shell {
+        Shell::Powershell => generate(clap_complete::Shell::PowerShell, command, name, stdout),
+        Shell::Bash => generate(clap_complete::Shell::Bash, command, name, stdout),
+        Shell::Zsh => generate(clap_complete::Shell::Zsh, command, name, stdout),
+        Shell::Fish => generate(clap_complete::Shell::Fish, command, name, stdout),
+        Shell::Nushell => generate(Nushell, command, name, stdout),
+    }
+}
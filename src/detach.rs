@@ -0,0 +1,55 @@
+//! Relaunching masir as a background process without a console, so that it
+//! can be started from a terminal or a script without leaving a console
+//! window behind
+
+use crate::exit::Failure;
+use color_eyre::eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+use std::process::Stdio;
+use std::time::Duration;
+use std::time::Instant;
+
+// process creation flags, see
+// https://learn.microsoft.com/en-us/windows/win32/procthread/process-creation-flags
+const DETACHED_PROCESS: u32 = 0x0000_0008;
+const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+
+/// How long to watch the background process for failing to start, such as
+/// because another instance is already running
+const STARTUP_GRACE: Duration = Duration::from_secs(1);
+
+/// Starts this executable again in the background with the same arguments
+/// except `--detach`, returning its process id once it has survived startup
+pub fn relaunch() -> Result<u32> {
+    let exe = std::env::current_exe()?;
+    let args = std::env::args_os().skip(1).filter(|arg| arg != "--detach");
+
+    let mut child = Command::new(&exe)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP)
+        .spawn()
+        .wrap_err_with(|| format!("could not start {} in the background", exe.display()))?;
+
+    let started = Instant::now();
+    while started.elapsed() < STARTUP_GRACE {
+        if let Some(status) = child.try_wait()? {
+            let error = eyre!("masir exited with {status} while starting in the background");
+
+            // the same exit code is passed on to whatever started masir
+            return Err(match status.code().and_then(Failure::from_code) {
+                Some(failure) => error.wrap_err(failure),
+                None => error,
+            });
+        }
+
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    Ok(child.id())
+}
@@ -0,0 +1,243 @@
+use crate::ipc;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use masir_core::competitors;
+use masir_core::config::Config;
+use masir_core::komorebi;
+use masir_core::winapi;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+use uds_windows::UnixStream;
+
+enum Outcome {
+    Ok,
+    Warning,
+    Error,
+}
+
+struct Check {
+    name: &'static str,
+    outcome: Outcome,
+    message: String,
+}
+
+impl Check {
+    fn ok(name: &'static str, message: impl Into<String>) -> Self {
+        Self::new(name, Outcome::Ok, message.into())
+    }
+
+    fn warning(name: &'static str, message: impl Into<String>) -> Self {
+        Self::new(name, Outcome::Warning, message.into())
+    }
+
+    fn error(name: &'static str, message: impl Into<String>) -> Self {
+        Self::new(name, Outcome::Error, message.into())
+    }
+
+    fn new(name: &'static str, outcome: Outcome, message: String) -> Self {
+        Self {
+            name,
+            outcome,
+            message,
+        }
+    }
+}
+
+/// Checks the environment for common reasons that masir does not raise
+/// windows, printing the result of each check along with how to fix it
+pub fn run(config_path: &Path, hwnds: &[PathBuf]) -> Result<()> {
+    let komorebi_socket = komorebi::socket_path();
+
+    let mut checks = vec![
+        mouse_hook(),
+        elevation(),
+        active_window_tracking(),
+        other_tools(),
+        running_instances(),
+        config(config_path),
+    ];
+
+    if !hwnds.is_empty() {
+        let komorebi_is_reachable = UnixStream::connect(&komorebi_socket).is_ok();
+        checks.push(komorebi_process(komorebi_is_reachable));
+
+        for hwnds in hwnds {
+            checks.push(hwnds_file(hwnds, komorebi_is_reachable));
+        }
+    }
+
+    let mut errors = 0;
+    for check in &checks {
+        let label = match check.outcome {
+            Outcome::Ok => "ok",
+            Outcome::Warning => "warning",
+            Outcome::Error => {
+                errors += 1;
+                "error"
+            }
+        };
+
+        println!("[{label}] {}: {}", check.name, check.message);
+    }
+
+    if errors > 0 {
+        return Err(eyre!("found {errors} problem(s)"));
+    }
+
+    Ok(())
+}
+
+fn mouse_hook() -> Check {
+    match winapi::check_mouse_hook() {
+        Ok(()) => Check::ok("mouse hook", "a low-level mouse hook can be installed"),
+        Err(error) => Check::error(
+            "mouse hook",
+            format!("could not be installed, masir will not receive mouse movements: {error}"),
+        ),
+    }
+}
+
+fn elevation() -> Check {
+    match winapi::is_elevated() {
+        Ok(true) => Check::ok("elevation", "running as administrator"),
+        Ok(false) => Check::warning(
+            "elevation",
+            "not running as administrator, so windows of elevated applications \
+             (e.g. Task Manager) cannot be raised",
+        ),
+        Err(error) => Check::warning("elevation", format!("could not be determined: {error}")),
+    }
+}
+
+fn active_window_tracking() -> Check {
+    const NAME: &str = "windows focus follows mouse";
+
+    match winapi::active_window_tracking() {
+        Ok(false) => Check::ok(NAME, "the built-in setting is disabled"),
+        Ok(true) => Check::warning(
+            NAME,
+            "the built-in setting is enabled and will compete with masir, disable \
+             \"Activate a window by hovering over it with the mouse\" in the mouse \
+             accessibility settings",
+        ),
+        Err(error) => Check::warning(NAME, format!("could not be determined: {error}")),
+    }
+}
+
+fn other_tools() -> Check {
+    const NAME: &str = "other focus follows mouse tools";
+
+    match competitors::detect() {
+        Ok(warnings) if warnings.is_empty() => Check::ok(NAME, "none are running"),
+        Ok(warnings) => Check::warning(
+            NAME,
+            format!(
+                "{}, since they will compete with masir",
+                warnings.join("; ")
+            ),
+        ),
+        Err(error) => Check::warning(NAME, format!("could not list processes: {error}")),
+    }
+}
+
+fn running_instances() -> Check {
+    const NAME: &str = "running instances";
+
+    let socket = ipc::socket_path();
+    let is_listening = UnixStream::connect(&socket).is_ok();
+
+    let names = match winapi::process_names() {
+        Ok(names) => names,
+        Err(error) => return Check::warning(NAME, format!("could not list processes: {error}")),
+    };
+
+    // this process is also called masir.exe
+    let instances = names
+        .iter()
+        .filter(|name| name.eq_ignore_ascii_case("masir.exe"))
+        .count()
+        .saturating_sub(1);
+
+    match (instances, is_listening) {
+        (0, _) => Check::warning(NAME, "masir is not running"),
+        (1, true) => Check::ok(NAME, "masir is running and accepting commands"),
+        (1, false) => Check::warning(
+            NAME,
+            format!("masir is running but not listening on {}", socket.display()),
+        ),
+        (instances, _) => Check::error(
+            NAME,
+            format!(
+                "{instances} instances of masir are competing with each other, stop all but one"
+            ),
+        ),
+    }
+}
+
+fn config(path: &Path) -> Check {
+    if !path.is_file() {
+        return Check::ok("config", "there is no config file, using defaults");
+    }
+
+    match Config::load(path).map(|config| config.validate()) {
+        Ok(errors) if errors.is_empty() => {
+            Check::ok("config", format!("{} is valid", path.display()))
+        }
+        Ok(errors) => Check::error(
+            "config",
+            format!(
+                "{} has {} error(s), run masir validate-config for details",
+                path.display(),
+                errors.len()
+            ),
+        ),
+        Err(error) => Check::error("config", format!("{error:#}")),
+    }
+}
+
+fn komorebi_process(is_reachable: bool) -> Check {
+    if is_reachable {
+        Check::ok("komorebi", "komorebi is running")
+    } else {
+        Check::warning(
+            "komorebi",
+            "komorebi is not running, so the hwnds file will not be kept up to date",
+        )
+    }
+}
+
+fn hwnds_file(path: &Path, komorebi_is_reachable: bool) -> Check {
+    let Ok(modified) = std::fs::metadata(path).and_then(|metadata| metadata.modified()) else {
+        return Check::error(
+            "hwnds file",
+            format!(
+                "{} does not exist, masir will run without checking managed windows",
+                path.display()
+            ),
+        );
+    };
+
+    let age = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or_default();
+
+    // sub-second precision is just noise here
+    let age = humantime::format_duration(Duration::from_secs(age.as_secs()));
+
+    if komorebi_is_reachable {
+        Check::ok(
+            "hwnds file",
+            format!("{} was last updated {age} ago", path.display()),
+        )
+    } else {
+        Check::warning(
+            "hwnds file",
+            format!(
+                "{} was last updated {age} ago and may be stale, so new windows will not be raised",
+                path.display()
+            ),
+        )
+    }
+}
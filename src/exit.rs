@@ -0,0 +1,67 @@
+//! Exit codes of the masir process, so that scripts, services and schedulers
+//! can tell failures apart instead of seeing a generic non-zero code
+
+use color_eyre::Report;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::process::ExitCode;
+
+/// A kind of failure with its own exit code, attached to an error with
+/// `wrap_err` or created with `eyre!`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// The config file could not be read, parsed or validated
+    Config,
+    /// There is no running masir instance listening on its socket
+    IpcUnavailable,
+    /// Another masir instance is already running
+    AlreadyRunning,
+    /// The mouse hook could not be installed
+    Hook,
+}
+
+impl Failure {
+    // 1 is left for any other error and 2 for command line usage errors
+    pub const fn code(self) -> u8 {
+        match self {
+            Self::Config => 3,
+            Self::IpcUnavailable => 4,
+            Self::AlreadyRunning => 5,
+            Self::Hook => 6,
+        }
+    }
+
+    /// Returns the failure which a masir process exited with, if any
+    pub fn from_code(code: i32) -> Option<Self> {
+        [
+            Self::Config,
+            Self::IpcUnavailable,
+            Self::AlreadyRunning,
+            Self::Hook,
+        ]
+        .into_iter()
+        .find(|failure| i32::from(failure.code()) == code)
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Config => "the config file is invalid",
+            Self::IpcUnavailable => "could not reach masir, is it running?",
+            Self::AlreadyRunning => "masir is already running",
+            Self::Hook => "could not start listening for mouse movements",
+        })
+    }
+}
+
+/// Returns the exit code for an error, which is 1 unless a [`Failure`] was
+/// attached to it
+pub fn code(error: &Report) -> ExitCode {
+    ExitCode::from(
+        error
+            .downcast_ref::<Failure>()
+            .map_or(1, |failure| failure.code()),
+    )
+}
@@ -0,0 +1,79 @@
+use color_eyre::eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use masir_core::config::Settings;
+use masir_core::decision::DecisionReason;
+use masir_core::engine::Engine;
+use masir_core::hwnd::Hwnd;
+use masir_core::winapi;
+use masir_core::window_system::Win32;
+use masir_core::window_system::WindowSystem;
+use regex::Regex;
+use std::path::PathBuf;
+
+/// What a window must match to be focused, where every given property must
+/// match
+pub struct Query {
+    pub exe: Option<String>,
+    pub class: Option<String>,
+    pub title_regex: Option<String>,
+}
+
+/// Focuses the topmost window which matches the query and is eligible to be
+/// raised with the given settings
+pub fn run(query: &Query, settings: Settings, hwnds: Vec<PathBuf>) -> Result<()> {
+    let title_regex = query
+        .title_regex
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .wrap_err("invalid title regex")?;
+
+    let raise_strategies = settings.raise_strategies.clone();
+    // the engine decides eligibility, so that this never raises a window
+    // which masir itself wouldn't
+    let mut engine = Engine::new(Win32, hwnds, settings);
+
+    let mut matches = |hwnd: Hwnd| {
+        let Ok(class) = Win32.class(hwnd) else {
+            return false;
+        };
+
+        let exe = || Win32.exe(hwnd).unwrap_or_default();
+        let title = || Win32.title(hwnd).unwrap_or_default();
+
+        query
+            .exe
+            .as_ref()
+            .is_none_or(|query| exe().eq_ignore_ascii_case(query))
+            && query.class.as_ref().is_none_or(|query| class == *query)
+            && title_regex
+                .as_ref()
+                .is_none_or(|regex| regex.is_match(&title()))
+            && {
+                // focusing the window which already has focus does no harm
+                let reason = engine.evaluate_window(hwnd);
+                reason.is_raise() || reason == DecisionReason::AlreadyFocused
+            }
+    };
+
+    // windows are listed from the top of the z-order, so the first match is
+    // the one which was used most recently
+    let hwnd = winapi::top_level_windows()?
+        .into_iter()
+        .filter(|hwnd| Win32.root_window(*hwnd).is_ok_and(|root| root == *hwnd))
+        .find(|hwnd| matches(*hwnd))
+        .ok_or_else(|| eyre!("no eligible window matches the query"))?;
+
+    let mut last_error = None;
+    for strategy in &raise_strategies {
+        match Win32.raise(hwnd, *strategy) {
+            Ok(()) => return Ok(()),
+            Err(error) => last_error = Some(error),
+        }
+    }
+
+    Err(last_error
+        .unwrap_or_else(|| eyre!("there are no raise strategies to try"))
+        .wrap_err(format!("could not focus hwnd {hwnd}")))
+}
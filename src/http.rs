@@ -0,0 +1,238 @@
+//! A localhost HTTP API mirroring the socket messages, for tools which can't
+//! talk to the named pipe such as Stream Deck plugins and PowerShell scripts
+
+use crate::ipc;
+use crate::ipc::LogLevelHandle;
+use crate::ipc::Reload;
+use crate::ipc::SocketMessage;
+use color_eyre::eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+use std::net::Ipv4Addr;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// How long a client has to send its request before it is dropped, so that a
+/// stuck client can't block everyone else
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// The largest request body which is accepted, which is plenty for a log
+/// level filter
+const MAX_BODY_LENGTH: usize = 4096;
+/// Every path which is served, to tell a wrong method from a wrong path
+const PATHS: [&str; 9] = [
+    "/status",
+    "/metrics",
+    "/rules",
+    "/pause",
+    "/resume",
+    "/undo",
+    "/reload",
+    "/clear-caches",
+    "/log-level",
+];
+
+struct Request {
+    method: String,
+    path: String,
+    query: Option<String>,
+    body: String,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(body: String) -> Self {
+        Self {
+            status: "200 OK",
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    fn text(status: &'static str, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into(),
+        }
+    }
+
+    fn no_content() -> Self {
+        Self::text("204 No Content", "")
+    }
+}
+
+/// Serves the HTTP API on the given port of the loopback interface
+pub fn listen(port: u16, log_level: LogLevelHandle, reload: Reload) -> Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .wrap_err_with(|| format!("could not listen on 127.0.0.1:{port}"))?;
+
+    tracing::info!("serving the http api on http://127.0.0.1:{port}");
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(error) = handle_stream(stream, &log_level, &reload) {
+                        tracing::error!("failed to handle http request: {error}");
+                    }
+                }
+                Err(error) => {
+                    tracing::error!("failed to accept http connection: {error}");
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn handle_stream(mut stream: TcpStream, log_level: &LogLevelHandle, reload: &Reload) -> Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let response = match read_request(&stream) {
+        Ok(Some(request)) => {
+            tracing::debug!("received http request: {} {}", request.method, request.path);
+            route(request, log_level, reload)
+        }
+        Ok(None) => Response::text("403 Forbidden", "requests from web pages are not accepted"),
+        Err(error) => Response::text("400 Bad Request", format!("{error:#}")),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+
+    Ok(())
+}
+
+/// Reads a request, returning nothing for requests which were sent by a web
+/// page, since any page open in a browser can send requests to localhost
+fn read_request(stream: &TcpStream) -> Result<Option<Request>> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(eyre!("invalid request line: {}", request_line.trim_end()));
+    };
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, Some(query.to_string())),
+        None => (target, None),
+    };
+
+    let mut content_length = 0;
+    let mut from_web_page = false;
+
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(eyre!("invalid header: {header}"));
+        };
+
+        let value = value.trim();
+
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .parse()
+                .wrap_err_with(|| format!("invalid content length: {value}"))?;
+        } else if name.eq_ignore_ascii_case("origin") {
+            from_web_page = true;
+        } else if name.eq_ignore_ascii_case("host") {
+            // a page can reach localhost through a domain which resolves to it
+            let host = value.rsplit_once(':').map_or(value, |(host, _)| host);
+            from_web_page |= !matches!(host, "127.0.0.1" | "localhost");
+        }
+    }
+
+    if from_web_page {
+        return Ok(None);
+    }
+
+    if content_length > MAX_BODY_LENGTH {
+        return Err(eyre!(
+            "the request body is larger than {MAX_BODY_LENGTH} bytes"
+        ));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query,
+        body: String::from_utf8(body).wrap_err("the request body is not utf-8")?,
+    }))
+}
+
+/// Maps a request onto the socket message with the same meaning
+fn route(request: Request, log_level: &LogLevelHandle, reload: &Reload) -> Response {
+    let message = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/status") => SocketMessage::Status,
+        ("GET", "/metrics") => SocketMessage::Metrics,
+        ("GET", "/rules") => SocketMessage::Rules,
+        ("POST", "/pause") => match pause_duration(request.query.as_deref()) {
+            Ok(duration) => SocketMessage::Pause(duration),
+            Err(error) => return Response::text("400 Bad Request", format!("{error:#}")),
+        },
+        ("POST", "/resume") => SocketMessage::Resume,
+        ("POST", "/undo") => SocketMessage::Undo,
+        ("POST", "/reload") => SocketMessage::Reload,
+        ("POST", "/clear-caches") => SocketMessage::ClearCaches,
+        ("POST", "/log-level") => SocketMessage::LogLevel(request.body.trim().to_string()),
+        (_, path) if PATHS.contains(&path) => {
+            return Response::text("405 Method Not Allowed", "method not allowed");
+        }
+        _ => return Response::text("404 Not Found", "not found"),
+    };
+
+    match ipc::respond(message, log_level, reload) {
+        Ok(Some(json)) => Response::json(json),
+        Ok(None) => Response::no_content(),
+        Err(error) => {
+            tracing::error!("failed to handle http request: {error:#}");
+            Response::text("500 Internal Server Error", format!("{error:#}"))
+        }
+    }
+}
+
+/// Returns the duration of a pause given as ?for=30m, pausing until resumed
+/// if there is none
+fn pause_duration(query: Option<&str>) -> Result<Option<Duration>> {
+    let Some(duration) = query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .find_map(|pair| pair.strip_prefix("for="))
+    else {
+        return Ok(None);
+    };
+
+    humantime::parse_duration(duration)
+        .map(Some)
+        .wrap_err_with(|| format!("invalid duration: {duration}"))
+}
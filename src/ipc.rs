@@ -0,0 +1,257 @@
+use crate::exit::Failure;
+use color_eyre::eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use masir_core::cache;
+use masir_core::engine::PROFILE_TARGET;
+use masir_core::events;
+use masir_core::metrics;
+use masir_core::pause;
+use masir_core::portable;
+use masir_core::status;
+use serde::Deserialize;
+use serde::Serialize;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::ErrorKind;
+use std::io::Lines;
+use std::io::Read;
+use std::io::Write;
+use std::net::Shutdown;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing_subscriber::fmt::format::DefaultFields;
+use tracing_subscriber::fmt::format::Format;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::fmt::Formatter;
+use tracing_subscriber::reload::Handle;
+use tracing_subscriber::EnvFilter;
+use uds_windows::UnixListener;
+use uds_windows::UnixStream;
+
+/// Replaces the filter of the global tracing subscriber, keeping the directive
+/// which --profiling adds
+#[derive(Clone)]
+pub struct LogLevelHandle {
+    handle: Handle<EnvFilter, Formatter<DefaultFields, Format, BoxMakeWriter>>,
+    profiling: bool,
+}
+
+impl LogLevelHandle {
+    pub fn new(
+        handle: Handle<EnvFilter, Formatter<DefaultFields, Format, BoxMakeWriter>>,
+        profiling: bool,
+    ) -> Self {
+        Self { handle, profiling }
+    }
+
+    /// Replaces the filter with the given level or directives
+    pub fn set(&self, filter: &str) -> Result<()> {
+        self.handle
+            .reload(with_profiling(EnvFilter::try_new(filter)?, self.profiling)?)?;
+
+        Ok(())
+    }
+}
+
+/// Adds the directive which enables the profiling spans to a filter if
+/// profiling is enabled
+pub fn with_profiling(filter: EnvFilter, profiling: bool) -> Result<EnvFilter> {
+    if !profiling {
+        return Ok(filter);
+    }
+
+    Ok(filter.add_directive(format!("{PROFILE_TARGET}=trace").parse()?))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "content")]
+pub enum SocketMessage {
+    Pause(Option<Duration>),
+    Resume,
+    Status,
+    Metrics,
+    Rules,
+    LogLevel(String),
+    Reload,
+    ClearCaches,
+    /// Gives focus back to the window which had it before the last raise
+    Undo,
+    /// Keeps the connection open and writes every event to it as a line of JSON
+    Subscribe,
+}
+
+pub fn socket_path() -> PathBuf {
+    portable::data_dir().join("masir.sock")
+}
+
+pub fn send_message(message: &SocketMessage) -> Result<()> {
+    let mut stream = connect()?;
+
+    stream.write_all(serde_json::to_string(message)?.as_bytes())?;
+    stream.shutdown(Shutdown::Write)?;
+
+    Ok(())
+}
+
+/// Sends a message and returns the response from the running masir instance
+pub fn send_query(message: &SocketMessage) -> Result<String> {
+    let mut stream = connect()?;
+
+    stream.write_all(serde_json::to_string(message)?.as_bytes())?;
+    stream.shutdown(Shutdown::Write)?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+
+    Ok(response)
+}
+
+/// Subscribes to the events of the running masir instance, returning each of
+/// them as a line of JSON
+pub fn subscribe() -> Result<Lines<BufReader<UnixStream>>> {
+    let mut stream = connect()?;
+
+    stream.write_all(serde_json::to_string(&SocketMessage::Subscribe)?.as_bytes())?;
+    stream.shutdown(Shutdown::Write)?;
+
+    Ok(BufReader::new(stream).lines())
+}
+
+/// Re-reads the config and hwnds files and hands them to the event loop
+pub type Reload = Arc<dyn Fn() -> Result<()> + Send + Sync>;
+
+/// Connects to the socket of the running masir instance
+fn connect() -> Result<UnixStream> {
+    let socket = socket_path();
+
+    UnixStream::connect(&socket)
+        .wrap_err_with(|| format!("could not connect to {}", socket.display()))
+        .wrap_err(Failure::IpcUnavailable)
+}
+
+pub fn listen_for_commands(log_level: LogLevelHandle, reload: Reload) -> Result<()> {
+    let socket = socket_path();
+
+    if let Some(parent) = socket.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // a socket which is still accepted belongs to an instance which is running
+    // rather than one which didn't clean up after itself
+    if UnixStream::connect(&socket).is_ok() {
+        return Err(eyre!(Failure::AlreadyRunning));
+    }
+
+    // a previous instance may not have cleaned up after itself, and its
+    // socket is a reparse point rather than a file
+    if let Err(error) = std::fs::remove_file(&socket) {
+        if error.kind() != ErrorKind::NotFound {
+            return Err(error.into());
+        }
+    }
+
+    let listener = UnixListener::bind(&socket)?;
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(error) = handle_stream(stream, &log_level, &reload) {
+                        tracing::error!("failed to handle socket message: {error}");
+                    }
+                }
+                Err(error) => {
+                    tracing::error!("failed to accept socket connection: {error}");
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn handle_stream(
+    mut stream: UnixStream,
+    log_level: &LogLevelHandle,
+    reload: &Reload,
+) -> Result<()> {
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw)?;
+
+    // connections which only check whether masir is running send nothing
+    if raw.is_empty() {
+        return Ok(());
+    }
+
+    let message: SocketMessage = serde_json::from_str(&raw)?;
+    tracing::debug!("received socket message: {message:?}");
+
+    // subscribers are served on their own thread so that they don't hold up
+    // other messages
+    if let SocketMessage::Subscribe = message {
+        std::thread::spawn(move || write_events(stream));
+        return Ok(());
+    }
+
+    match respond(message, log_level, reload) {
+        Ok(Some(response)) => stream.write_all(response.as_bytes())?,
+        Ok(None) => {}
+        // the error is sent back so that the user sees what is wrong with
+        // their config, while the running instance carries on with the old one
+        Err(error) => {
+            tracing::error!("failed to handle socket message: {error:#}");
+            stream.write_all(format!("{error:#}").as_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Carries out a message, returning the JSON response of messages which query
+/// the running instance
+pub fn respond(
+    message: SocketMessage,
+    log_level: &LogLevelHandle,
+    reload: &Reload,
+) -> Result<Option<String>> {
+    match message {
+        SocketMessage::Pause(duration) => pause::pause(duration),
+        SocketMessage::Resume => pause::resume(),
+        SocketMessage::Status => {
+            return Ok(Some(serde_json::to_string_pretty(&status::current())?));
+        }
+        SocketMessage::Metrics => {
+            return Ok(Some(serde_json::to_string_pretty(&metrics::current())?));
+        }
+        SocketMessage::Rules => {
+            return Ok(Some(serde_json::to_string_pretty(&status::rules())?));
+        }
+        SocketMessage::LogLevel(filter) => {
+            log_level.set(&filter)?;
+            tracing::info!("log level changed to {filter}");
+        }
+        SocketMessage::ClearCaches => cache::request_clear(),
+        SocketMessage::Undo => masir_core::undo_last_raise(),
+        SocketMessage::Reload => reload()?,
+        SocketMessage::Subscribe => {
+            return Err(eyre!("subscriptions are only served over the socket"));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Writes every event to a subscriber as a line of JSON until it disconnects
+fn write_events(mut stream: UnixStream) {
+    for event in events::subscribe() {
+        let Ok(line) = serde_json::to_string(&event) else {
+            continue;
+        };
+
+        if writeln!(stream, "{line}").is_err() {
+            break;
+        }
+    }
+}
@@ -1,490 +1,499 @@
+// windowless builds never open a console, which would otherwise flash when
+// masir is started at login
+#![cfg_attr(feature = "windowless", windows_subsystem = "windows")]
+
+use clap::Args;
+use clap::CommandFactory;
 use clap::Parser;
+use clap::Subcommand;
+use color_eyre::eyre::eyre;
+use color_eyre::eyre::WrapErr;
 use color_eyre::Result;
-use std::collections::HashMap;
+use exit::Failure;
+use ipc::SocketMessage;
+use masir_core::competitors;
+use masir_core::config::Config;
+use masir_core::config::Settings;
+use masir_core::crash;
+use masir_core::listen_for_movements;
+use masir_core::notification;
+use masir_core::portable;
+use masir_core::raise::ForegroundLockTimeoutOverride;
+use masir_core::reload;
+use masir_core::winapi;
+use pid_file::PidFile;
+use std::collections::HashSet;
+use std::fs::OpenOptions;
+use std::path::Path;
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
-use std::time::Instant;
-use windows::core::Result as WindowsCrateResult;
-use windows::Win32::Foundation::HWND;
-use windows::Win32::Foundation::POINT;
-use windows::Win32::UI::Input::KeyboardAndMouse::SendInput;
-use windows::Win32::UI::Input::KeyboardAndMouse::INPUT;
-use windows::Win32::UI::Input::KeyboardAndMouse::INPUT_MOUSE;
-use windows::Win32::UI::WindowsAndMessaging::GetAncestor;
-use windows::Win32::UI::WindowsAndMessaging::GetCursorPos;
-use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
-use windows::Win32::UI::WindowsAndMessaging::GetWindowLongW;
-use windows::Win32::UI::WindowsAndMessaging::RealGetWindowClassW;
-use windows::Win32::UI::WindowsAndMessaging::SetForegroundWindow;
-use windows::Win32::UI::WindowsAndMessaging::WindowFromPoint;
-use windows::Win32::UI::WindowsAndMessaging::GA_ROOT;
-use windows::Win32::UI::WindowsAndMessaging::GET_ANCESTOR_FLAGS;
-use windows::Win32::UI::WindowsAndMessaging::GWL_EXSTYLE;
-use windows::Win32::UI::WindowsAndMessaging::WINDOW_EX_STYLE;
-use windows::Win32::UI::WindowsAndMessaging::WS_EX_NOACTIVATE;
-use windows::Win32::UI::WindowsAndMessaging::WS_EX_TOOLWINDOW;
-use winput::message_loop;
-use winput::message_loop::Event;
-use winput::Action;
-
-const CLASS_IGNORELIST: [(&str, MatchingStrategy); 9] = [
-    ("SHELLDLL_DefView", MatchingStrategy::Equals), // desktop window
-    ("Shell_TrayWnd", MatchingStrategy::Equals),    // tray
-    ("TrayNotifyWnd", MatchingStrategy::Equals),    // tray
-    ("MSTaskSwWClass", MatchingStrategy::Equals),   // start bar icons
-    ("Windows.UI.Core.CoreWindow", MatchingStrategy::Equals), // start menu
-    ("XamlExplorerHostIslandWindow", MatchingStrategy::Equals), // task switcher
-    ("ForegroundStaging", MatchingStrategy::Equals), // also task switcher
-    ("Flow.Launcher", MatchingStrategy::Contains),
-    ("PowerToys.PowerLauncher", MatchingStrategy::Contains),
-];
-
-#[derive(Debug, PartialEq, Eq)]
-enum MatchingStrategy {
-    Contains,
-    Equals,
-}
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::EnvFilter;
+
+mod completions;
+mod detach;
+mod doctor;
+mod exit;
+mod focus;
+#[cfg(feature = "http")]
+mod http;
+mod ipc;
+mod pid_file;
+mod query;
+mod self_update;
+mod websocket;
 
 #[derive(Parser)]
 #[clap(author, about, version)]
 struct Opts {
+    #[clap(subcommand)]
+    subcmd: Option<SubCommand>,
     /// Enable komorebi integration to avoid raising unmanaged windows
     #[clap(long)]
     komorebi: bool,
-    /// Path to a file with known focus-able HWNDs (e.g. komorebi.hwnd.json)
+    /// Path to a file with known focus-able HWNDs (e.g. komorebi.hwnd.json), can be given more than once
+    #[clap(long)]
+    hwnds: Vec<PathBuf>,
+    /// Name of a profile in the config file to apply
+    #[clap(long)]
+    profile: Option<String>,
+    /// Path to the config file, instead of %APPDATA%\masir\masir.toml
+    #[clap(long, env = "MASIR_CONFIG")]
+    config: Option<PathBuf>,
+    /// Keep the config file and all other state next to the executable
+    #[clap(long)]
+    portable: bool,
+    /// Log how long each stage of evaluating a mouse movement takes
+    #[clap(long)]
+    profiling: bool,
+    /// Start in the background without a console and return immediately
+    #[clap(long)]
+    detach: bool,
+    /// Append logs to this file instead of writing them to the console, which windowless builds do by default
+    #[clap(long)]
+    log_file: Option<PathBuf>,
+    /// Path to write the process id to while running, instead of masir.pid in the data directory
     #[clap(long)]
-    hwnds: Option<PathBuf>,
+    pid_file: Option<PathBuf>,
+    /// Serve an HTTP API mirroring the socket commands on this port of 127.0.0.1
+    #[cfg(feature = "http")]
+    #[clap(long)]
+    http_port: Option<u16>,
+    /// Stream events over a WebSocket on this port of 127.0.0.1, in addition to `masir subscribe`
+    #[clap(long)]
+    websocket_port: Option<u16>,
 }
 
-fn main() -> Result<()> {
-    let opts: Opts = Opts::parse();
+#[derive(Subcommand)]
+enum SubCommand {
+    /// Pause focus follows mouse in the running masir instance
+    Pause(Pause),
+    /// Resume focus follows mouse in the running masir instance
+    Resume,
+    /// Give focus back to the window which had it before the last raise of the running masir instance
+    Undo,
+    /// Print the status of the running masir instance as JSON
+    Status,
+    /// Print internal counters and evaluation latencies of the running masir instance as JSON
+    Metrics,
+    /// Print the ignore and protect rules in effect in the running masir instance as JSON
+    Rules,
+    /// Print every event of the running masir instance as a line of JSON until it exits
+    Subscribe,
+    /// Print what masir sees for a window as JSON, including whether it is eligible to be raised
+    Query(Query),
+    /// Focus the topmost eligible window which matches every given property
+    Focus(Focus),
+    /// Check the environment for common problems which stop windows from being raised
+    Doctor,
+    /// Check the config file for errors, exiting with a non-zero code if any are found
+    ValidateConfig,
+    /// Change the log level of the running masir instance without restarting it
+    LogLevel(LogLevel),
+    /// Print a JSON Schema of the config file format
+    Schema,
+    /// Update masir to the latest GitHub release, which takes effect the next time it starts
+    SelfUpdate,
+    /// Re-read the config and hwnds files in the running masir instance and clear its caches
+    Reload,
+    /// Clear the cached window properties and eligibility of the running masir instance
+    ClearCaches,
+    /// Print a completion script for the given shell
+    Completions(Completions),
+}
 
-    let hwnds = match opts.hwnds {
-        None => {
-            // TODO: We can add checks for other window managers here
-            let hwnds_option = if opts.komorebi {
-                Some(
-                    dirs::data_local_dir()
-                        .expect("there is no local data directory")
-                        .join("komorebi")
-                        .join("komorebi.hwnd.json"),
-                )
-            } else {
-                None
-            };
+#[derive(Args)]
+struct Query {
+    #[clap(value_enum)]
+    target: query::Target,
+}
 
-            hwnds_option.filter(|hwnds| hwnds.is_file())
-        }
-        Some(hwnds) => {
-            if hwnds.is_file() {
-                Some(hwnds)
-            } else {
-                None
-            }
-        }
-    };
+#[derive(Args)]
+#[clap(group = clap::ArgGroup::new("query").required(true).multiple(true))]
+struct Focus {
+    /// The name of the executable (e.g. firefox.exe), ignoring case
+    #[clap(long, group = "query")]
+    exe: Option<String>,
+    /// The window class (e.g. MozillaWindowClass)
+    #[clap(long, group = "query")]
+    class: Option<String>,
+    /// A regular expression which the window title must match
+    #[clap(long, group = "query")]
+    title_regex: Option<String>,
+}
 
-    if std::env::var("RUST_LIB_BACKTRACE").is_err() {
-        std::env::set_var("RUST_LIB_BACKTRACE", "1");
+#[derive(Args)]
+struct Completions {
+    #[clap(value_enum)]
+    shell: completions::Shell,
+}
+
+#[derive(Args)]
+struct LogLevel {
+    /// A level (e.g. debug) or a filter directive (e.g. masir_core=trace)
+    filter: String,
+}
+
+#[derive(Args)]
+struct Pause {
+    /// Automatically resume after the given duration (e.g. 30s, 30m, 1h)
+    #[clap(long = "for", value_parser = humantime::parse_duration)]
+    duration: Option<Duration>,
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            // there is no console to report errors to in windowless builds
+            #[cfg(feature = "windowless")]
+            tracing::error!("{error:?}");
+
+            eprintln!("Error: {error:?}");
+            exit::code(&error)
+        }
     }
+}
+
+fn run() -> Result<()> {
+    let opts: Opts = Opts::parse();
 
     color_eyre::install()?;
 
-    if std::env::var("RUST_LOG").is_err() {
-        std::env::set_var("RUST_LOG", "info");
+    if opts.portable {
+        portable::enable();
     }
 
-    tracing::subscriber::set_global_default(
-        tracing_subscriber::fmt::Subscriber::builder()
-            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-            .finish(),
-    )?;
+    let config_path = opts.config.clone().unwrap_or_else(Config::path);
+
+    if let Some(subcmd) = &opts.subcmd {
+        return match subcmd {
+            SubCommand::Pause(args) => ipc::send_message(&SocketMessage::Pause(args.duration)),
+            SubCommand::Resume => ipc::send_message(&SocketMessage::Resume),
+            SubCommand::Undo => ipc::send_message(&SocketMessage::Undo),
+            SubCommand::ClearCaches => ipc::send_message(&SocketMessage::ClearCaches),
+            SubCommand::Status => {
+                println!("{}", ipc::send_query(&SocketMessage::Status)?);
+                Ok(())
+            }
+            SubCommand::Metrics => {
+                println!("{}", ipc::send_query(&SocketMessage::Metrics)?);
+                Ok(())
+            }
+            SubCommand::Rules => {
+                println!("{}", ipc::send_query(&SocketMessage::Rules)?);
+                Ok(())
+            }
+            SubCommand::Subscribe => {
+                for line in ipc::subscribe()? {
+                    println!("{}", line?);
+                }
 
-    listen_for_movements(hwnds.clone());
+                Ok(())
+            }
+            SubCommand::Query(args) => {
+                let settings = load_settings(&config_path, opts.profile.as_deref())?;
+                let hwnds = existing(hwnds_paths(&opts.hwnds, opts.komorebi, &settings.hwnds));
+                query::run(args.target, settings, hwnds)
+            }
+            SubCommand::Focus(args) => {
+                let settings = load_settings(&config_path, opts.profile.as_deref())?;
+                let hwnds = existing(hwnds_paths(&opts.hwnds, opts.komorebi, &settings.hwnds));
+                let query = focus::Query {
+                    exe: args.exe.clone(),
+                    class: args.class.clone(),
+                    title_regex: args.title_regex.clone(),
+                };
+
+                focus::run(&query, settings, hwnds)
+            }
+            SubCommand::Doctor => {
+                // the config may be broken, which doctor will report
+                let configured_hwnds = Config::load(&config_path)
+                    .and_then(|config| config.settings(opts.profile.as_deref()))
+                    .map(|settings| settings.hwnds)
+                    .unwrap_or_default();
+
+                doctor::run(
+                    &config_path,
+                    &hwnds_paths(&opts.hwnds, opts.komorebi, &configured_hwnds),
+                )
+            }
+            SubCommand::LogLevel(args) => {
+                // fail here instead of silently in the running instance
+                EnvFilter::try_new(&args.filter)?;
+                ipc::send_message(&SocketMessage::LogLevel(args.filter.clone()))
+            }
+            SubCommand::ValidateConfig => validate_config(&config_path),
+            SubCommand::Schema => {
+                let schema = schemars::schema_for!(Config);
+                println!("{}", serde_json::to_string_pretty(&schema)?);
+                Ok(())
+            }
+            SubCommand::SelfUpdate => self_update::run(),
+            SubCommand::Completions(args) => {
+                completions::print(args.shell, &mut Opts::command());
+                Ok(())
+            }
+            SubCommand::Reload => {
+                let error = ipc::send_query(&SocketMessage::Reload)?;
+                if !error.is_empty() {
+                    return Err(eyre!("could not reload the config: {error}"));
+                }
 
-    match hwnds {
-        None => tracing::info!("masir is now running"),
-        Some(hwnds) => tracing::info!(
-            "masir is now running, and additionally checking hwnds against {}",
-            hwnds.display()
-        ),
+                println!("reloaded {}", config_path.display());
+                Ok(())
+            }
+        };
     }
 
-    let (ctrlc_sender, ctrlc_receiver) = std::sync::mpsc::channel();
-    ctrlc::set_handler(move || {
-        ctrlc_sender
-            .send(())
-            .expect("could not send signal on ctrl-c channel");
-    })?;
+    let settings = load_settings(&config_path, opts.profile.as_deref())?;
+    let hwnds = existing(hwnds_paths(&opts.hwnds, opts.komorebi, &settings.hwnds));
 
-    ctrlc_receiver
-        .recv()
-        .expect("could not receive signal on ctrl-c channel");
+    // the config is loaded first so that errors in it are still reported in
+    // the terminal
+    if opts.detach {
+        let pid = detach::relaunch()?;
+        println!("masir is now running in the background (pid {pid})");
+        return Ok(());
+    }
 
-    tracing::info!("received ctrl-c, exiting");
+    if std::env::var("RUST_LIB_BACKTRACE").is_err() {
+        std::env::set_var("RUST_LIB_BACKTRACE", "1");
+    }
 
-    Ok(())
-}
+    if std::env::var("RUST_LOG").is_err() {
+        std::env::set_var("RUST_LOG", "info");
+    }
 
-fn listen_for_movements(hwnds: Option<PathBuf>) {
-    std::thread::spawn(move || {
-        let receiver = message_loop::start().expect("could not start winput message loop");
+    let filter = ipc::with_profiling(EnvFilter::from_default_env(), opts.profiling)?;
+
+    let log_file = opts.log_file.clone().or_else(default_log_file);
+
+    // each span logs its busy and idle time when it closes
+    let subscriber = tracing_subscriber::fmt::Subscriber::builder()
+        .with_env_filter(filter)
+        .with_writer(log_writer(log_file.as_deref())?)
+        .with_ansi(log_file.is_none())
+        .with_span_events(if opts.profiling {
+            FmtSpan::CLOSE
+        } else {
+            FmtSpan::NONE
+        })
+        .with_filter_reloading();
+    let log_level = ipc::LogLevelHandle::new(subscriber.reload_handle(), opts.profiling);
+
+    tracing::subscriber::set_global_default(subscriber.finish())?;
+
+    let reload: ipc::Reload = {
+        let config_path = config_path.clone();
+        let profile = opts.profile.clone();
+        let cli_hwnds = opts.hwnds.clone();
+        let komorebi = opts.komorebi;
+
+        Arc::new(move || {
+            let settings = load_settings(&config_path, profile.as_deref())?;
+            let hwnds = existing(hwnds_paths(&cli_hwnds, komorebi, &settings.hwnds));
+            reload::request(hwnds, settings);
+
+            Ok(())
+        })
+    };
 
-        let mut eligibility_cache = HashMap::new();
-        let mut class_cache: HashMap<isize, String> = HashMap::new();
-        let mut hwnd_pair_cache: HashMap<isize, isize> = HashMap::new();
-        let mut root_hwnd_cache: HashMap<isize, isize> = HashMap::new();
+    crash::install(portable::data_dir().join("crashes"));
+    ipc::listen_for_commands(log_level.clone(), reload.clone())?;
 
-        let mut cache_instantiation_time = Instant::now();
-        let max_cache_age = Duration::from_secs(60) * 10; // 10 minutes
+    #[cfg(feature = "http")]
+    if let Some(port) = opts.http_port {
+        http::listen(port, log_level, reload)?;
+    }
 
-        let mut is_mouse_down = false;
+    if let Some(port) = opts.websocket_port {
+        websocket::listen(port)?;
+    }
 
-        loop {
-            // clear our caches every 10 minutes
-            if cache_instantiation_time.elapsed() > max_cache_age {
-                tracing::info!("clearing caches, cache age is >10 minutes");
+    // written once this is known to be the only running instance, and removed
+    // when dropped at the end of main
+    let _pid_file = PidFile::write(opts.pid_file.clone().unwrap_or_else(PidFile::default_path))?;
+    self_update::remove_previous();
 
-                eligibility_cache = HashMap::new();
-                class_cache = HashMap::new();
-                hwnd_pair_cache = HashMap::new();
-                root_hwnd_cache = HashMap::new();
+    if let Some(profile) = &opts.profile {
+        tracing::info!("using profile: {profile}");
+    }
 
-                cache_instantiation_time = Instant::now();
-            }
+    if let Some(log_file) = &log_file {
+        tracing::info!("logging to {}", log_file.display());
+    }
 
-            match receiver.next_event() {
-                Event::MouseMoveRelative { .. } => {
-                    // resizing windows / dragging and dropping files fix
-                    if is_mouse_down {
-                        continue;
-                    }
-
-                    if let (Ok(cursor_pos_hwnd), Ok(foreground_hwnd)) =
-                        (window_at_cursor_pos(), foreground_window())
-                    {
-                        if cursor_pos_hwnd == foreground_hwnd {
-                            continue;
-                        }
-
-                        let mut cursor_root_hwnd = root_hwnd_cache.get(&cursor_pos_hwnd).cloned();
-
-                        // make syscalls if necessary and populate the root hwnd cache
-                        match &cursor_root_hwnd {
-                            None => {
-                                if let Ok(root_hwnd) = get_ancestor(cursor_pos_hwnd, GA_ROOT) {
-                                    root_hwnd_cache.insert(cursor_pos_hwnd, root_hwnd);
-                                    cursor_root_hwnd = Some(root_hwnd);
-                                }
-                            }
-                            Some(root_hwnd) => {
-                                tracing::debug!(
-                                    "hwnd {cursor_pos_hwnd} root hwnd was found in the cache: {root_hwnd}"
-                                );
-                            }
-                        }
-
-                        if let Some(cursor_root_hwnd) = cursor_root_hwnd {
-                            if cursor_root_hwnd == foreground_hwnd {
-                                continue;
-                            }
-
-                            if let Some(paired_hwnd) = hwnd_pair_cache.get(&cursor_root_hwnd) {
-                                if *paired_hwnd == foreground_hwnd {
-                                    tracing::trace!("hwnds {cursor_root_hwnd} and {foreground_hwnd} are known to refer to the same application, skipping");
-                                    continue;
-                                }
-                            }
-
-                            let mut should_raise = false;
-
-                            // check our class cache to avoid syscalls
-                            let mut cursor_root_class = class_cache.get(&cursor_root_hwnd).cloned();
-                            let mut foreground_class = class_cache.get(&foreground_hwnd).cloned();
-
-                            // make syscalls if necessary and populate the class cache
-                            match &cursor_root_class {
-                                None => {
-                                    if let Ok(class) = real_window_class_w(cursor_root_hwnd) {
-                                        class_cache.insert(cursor_root_hwnd, class.clone());
-                                        cursor_root_class = Some(class);
-                                    }
-                                }
-                                Some(class) => {
-                                    tracing::debug!(
-                                        "hwnd {cursor_root_hwnd} class was found in the cache: {class}"
-                                    );
-                                }
-                            }
-
-                            // make syscalls if necessary and populate the class cache
-                            match &foreground_class {
-                                None => {
-                                    if let Ok(class) = real_window_class_w(foreground_hwnd) {
-                                        class_cache.insert(foreground_hwnd, class.clone());
-                                        foreground_class = Some(class);
-                                    }
-                                }
-                                Some(class) => {
-                                    tracing::debug!(
-                                        "hwnd {foreground_hwnd} class was found in the cache: {class}"
-                                    );
-                                }
-                            }
-
-                            if let (Some(cursor_root_class), Some(foreground_class)) =
-                                (&cursor_root_class, &foreground_class)
-                            {
-                                // steam fixes - populate the hwnd pair cache if necessary
-                                if cursor_root_class == "Chrome_RenderWidgetHostHWND"
-                                    && foreground_class == "SDL_app"
-                                {
-                                    hwnd_pair_cache.insert(cursor_root_hwnd, foreground_hwnd);
-                                    continue;
-                                }
-                            }
-
-                            // check our eligibility caches
-                            if let (Some(cursor_root_is_eligible), Some(foreground_is_eligible)) = (
-                                eligibility_cache.get(&cursor_root_hwnd),
-                                eligibility_cache.get(&foreground_hwnd),
-                            ) {
-                                if *cursor_root_is_eligible && *foreground_is_eligible {
-                                    should_raise = true;
-                                    tracing::debug!(
-                                        "hwnds {cursor_root_hwnd} and {foreground_hwnd} were found as eligible in the cache"
-                                    );
-                                }
-                            } else if let Some(hwnds) = &hwnds {
-                                // use the hwnds file if twm integration is enabled
-                                if let Ok(raw_hwnds) = std::fs::read_to_string(hwnds) {
-                                    let mut cursor_root_is_eligible = true;
-                                    let mut foreground_is_eligible = true;
-
-                                    // step one: test against the hwnds in the twm hwnds file
-                                    cursor_root_is_eligible &=
-                                        raw_hwnds.contains(&cursor_root_hwnd.to_string());
-                                    foreground_is_eligible &=
-                                        raw_hwnds.contains(&foreground_hwnd.to_string());
-
-                                    // step two: test against known classes
-                                    if let (Some(cursor_root_class), Some(foreground_class)) =
-                                        (&cursor_root_class, &foreground_class)
-                                    {
-                                        for (class, strategy) in CLASS_IGNORELIST.iter() {
-                                            let cursor_root_has_match =
-                                                has_match(cursor_root_class, class, strategy);
-                                            let foreground_has_match =
-                                                has_match(foreground_class, class, strategy);
-
-                                            cursor_root_is_eligible &= !cursor_root_has_match;
-                                            foreground_is_eligible &= !foreground_has_match;
-                                        }
-                                    }
-
-                                    // TODO: right now we just ignore the non-eligible case due to
-                                    // potential delays with the twm writing to the hwnds file
-                                    if cursor_root_is_eligible {
-                                        eligibility_cache.insert(cursor_root_hwnd, true);
-                                    }
-                                    if foreground_is_eligible {
-                                        eligibility_cache.insert(foreground_hwnd, true);
-                                    }
-
-                                    should_raise =
-                                        cursor_root_is_eligible && foreground_is_eligible;
-                                }
-                            } else {
-                                let mut cursor_root_is_eligible = true;
-                                let mut foreground_is_eligible = true;
-
-                                // step one: test against known window styles
-                                cursor_root_is_eligible &= !has_filtered_style(cursor_root_hwnd);
-                                foreground_is_eligible &= !has_filtered_style(foreground_hwnd);
-
-                                // step two: test against known classes
-                                if let (Some(cursor_root_class), Some(foreground_class)) =
-                                    (&cursor_root_class, &foreground_class)
-                                {
-                                    for (class, strategy) in CLASS_IGNORELIST.iter() {
-                                        let cursor_root_has_match =
-                                            has_match(cursor_root_class, class, strategy);
-                                        let foreground_has_match =
-                                            has_match(foreground_class, class, strategy);
-
-                                        cursor_root_is_eligible &= !cursor_root_has_match;
-                                        foreground_is_eligible &= !foreground_has_match;
-                                    }
-                                }
-
-                                eligibility_cache.insert(cursor_root_hwnd, cursor_root_is_eligible);
-                                eligibility_cache.insert(foreground_hwnd, foreground_is_eligible);
-
-                                should_raise = cursor_root_is_eligible && foreground_is_eligible;
-                            }
-
-                            if should_raise {
-                                match raise_and_focus_window(cursor_root_hwnd) {
-                                    Ok(_) => {
-                                        tracing::info!("raised hwnd: {cursor_root_hwnd}");
-                                    }
-                                    Err(error) => {
-                                        tracing::error!(
-                                            "failed to raise hwnd {cursor_root_hwnd}: {error}"
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                Event::MouseButton { action, .. } => match action {
-                    Action::Press => is_mouse_down = true,
-                    Action::Release => is_mouse_down = false,
-                },
-                _ => {}
-            }
-        }
-    });
-}
+    if let Some(dir) = portable::dir() {
+        tracing::info!("running in portable mode from {}", dir.display());
+    }
 
-macro_rules! as_ptr {
-    ($value:expr) => {
-        $value as *mut core::ffi::c_void
-    };
-}
+    if settings.notifications {
+        notification::enable();
+    }
 
-enum WindowsResult<T, E> {
-    Err(E),
-    Ok(T),
-}
+    // the two fight over focus, which looks like masir flickering
+    if winapi::active_window_tracking().unwrap_or_default() {
+        tracing::warn!(
+            "windows' built-in focus follows mouse is enabled and will compete with masir, disable \
+             \"Activate a window by hovering over it with the mouse\" in the mouse accessibility settings"
+        );
+    }
 
-macro_rules! impl_from_integer_for_windows_result {
-    ( $( $integer_type:ty ),+ ) => {
-        $(
-            impl From<$integer_type> for WindowsResult<$integer_type, color_eyre::eyre::Error> {
-                fn from(return_value: $integer_type) -> Self {
-                    match return_value {
-                        0 => Self::Err(std::io::Error::last_os_error().into()),
-                        _ => Self::Ok(return_value),
-                    }
-                }
+    match competitors::detect() {
+        Ok(warnings) => {
+            for warning in warnings {
+                tracing::warn!("{warning}, since it will compete with masir");
             }
-        )+
+        }
+        Err(error) => {
+            tracing::debug!("failed to look for other focus follows mouse tools: {error}")
+        }
+    }
+
+    // restores the original value when dropped at the end of main
+    let _foreground_lock_timeout_override = if settings.disable_foreground_lock_timeout {
+        Some(ForegroundLockTimeoutOverride::new()?)
+    } else {
+        None
     };
-}
 
-impl_from_integer_for_windows_result!(usize, isize, u16, u32, i32);
+    listen_for_movements(hwnds.clone(), settings).wrap_err(Failure::Hook)?;
 
-impl<T, E> From<WindowsResult<T, E>> for Result<T, E> {
-    fn from(result: WindowsResult<T, E>) -> Self {
-        match result {
-            WindowsResult::Err(error) => Err(error),
-            WindowsResult::Ok(ok) => Ok(ok),
-        }
+    if hwnds.is_empty() {
+        tracing::info!("masir is now running");
+    } else {
+        let hwnds: Vec<String> = hwnds
+            .iter()
+            .map(|hwnds| hwnds.display().to_string())
+            .collect();
+        tracing::info!(
+            "masir is now running, and additionally checking hwnds against {}",
+            hwnds.join(", ")
+        );
     }
-}
 
-trait ProcessWindowsCrateResult<T> {
-    fn process(self) -> Result<T>;
-}
+    let (ctrlc_sender, ctrlc_receiver) = std::sync::mpsc::channel();
+    ctrlc::set_handler(move || {
+        ctrlc_sender
+            .send(())
+            .expect("could not send signal on ctrl-c channel");
+    })?;
 
-macro_rules! impl_process_windows_crate_integer_wrapper_result {
-    ( $($input:ty => $deref:ty),+ $(,)? ) => (
-        paste::paste! {
-            $(
-                impl ProcessWindowsCrateResult<$deref> for $input {
-                    fn process(self) -> Result<$deref> {
-                        if self == $input(std::ptr::null_mut()) {
-                            Err(std::io::Error::last_os_error().into())
-                        } else {
-                            Ok(self.0 as $deref)
-                        }
-                    }
-                }
-            )+
-        }
-    );
-}
+    ctrlc_receiver
+        .recv()
+        .expect("could not receive signal on ctrl-c channel");
 
-impl_process_windows_crate_integer_wrapper_result!(
-    HWND => isize,
-);
+    tracing::info!("received ctrl-c, exiting");
 
-impl<T> ProcessWindowsCrateResult<T> for WindowsCrateResult<T> {
-    fn process(self) -> Result<T> {
-        match self {
-            Ok(value) => Ok(value),
-            Err(error) => Err(error.into()),
-        }
-    }
+    Ok(())
 }
 
-fn has_match(str1: &str, str2: &str, matching_strategy: &MatchingStrategy) -> bool {
-    match matching_strategy {
-        MatchingStrategy::Equals => str1 == str2,
-        MatchingStrategy::Contains => str1.contains(str2),
+/// Returns the paths of the hwnds files to check windows against, from the
+/// command line followed by those in the config
+fn hwnds_paths(cli_hwnds: &[PathBuf], komorebi: bool, configured: &[PathBuf]) -> Vec<PathBuf> {
+    let mut paths = cli_hwnds.to_vec();
+    paths.extend_from_slice(configured);
+
+    // TODO: We can add checks for other window managers here
+    if komorebi {
+        paths.push(
+            dirs::data_local_dir()
+                .expect("there is no local data directory")
+                .join("komorebi")
+                .join("komorebi.hwnd.json"),
+        );
     }
-}
 
-fn get_window_ex_style(hwnd: isize) -> WINDOW_EX_STYLE {
-    unsafe { WINDOW_EX_STYLE(GetWindowLongW(HWND(as_ptr!(hwnd)), GWL_EXSTYLE) as u32) }
+    // a path given more than once would be read on every check, so only its
+    // first occurrence is kept
+    let mut seen = HashSet::new();
+    paths.retain(|path| seen.insert(path.clone()));
+    paths
 }
 
-fn has_filtered_style(hwnd: isize) -> bool {
-    let ex_style = get_window_ex_style(hwnd);
-
-    ex_style.contains(WS_EX_TOOLWINDOW) || ex_style.contains(WS_EX_NOACTIVATE)
+fn existing(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    paths.into_iter().filter(|hwnds| hwnds.is_file()).collect()
 }
 
-fn get_ancestor(hwnd: isize, gaflags: GET_ANCESTOR_FLAGS) -> Result<isize> {
-    unsafe { GetAncestor(HWND(as_ptr!(hwnd)), gaflags) }.process()
+/// Returns the file which windowless builds log to when no other is given
+fn default_log_file() -> Option<PathBuf> {
+    cfg!(feature = "windowless").then(|| portable::data_dir().join("masir.log"))
 }
 
-fn window_from_point(point: POINT) -> Result<isize> {
-    unsafe { WindowFromPoint(point) }.process()
-}
+/// Returns a writer which appends to the given log file, or writes to stdout
+/// if there is none
+fn log_writer(log_file: Option<&Path>) -> Result<BoxMakeWriter> {
+    let Some(path) = log_file else {
+        return Ok(BoxMakeWriter::new(std::io::stdout));
+    };
 
-fn window_at_cursor_pos() -> Result<isize> {
-    window_from_point(cursor_pos()?)
-}
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
 
-fn foreground_window() -> Result<isize> {
-    unsafe { GetForegroundWindow() }.process()
-}
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .wrap_err_with(|| format!("could not open the log file {}", path.display()))?;
 
-fn cursor_pos() -> Result<POINT> {
-    let mut cursor_pos = POINT::default();
-    unsafe { GetCursorPos(&mut cursor_pos) }.process()?;
+    Ok(BoxMakeWriter::new(Mutex::new(file)))
+}
 
-    Ok(cursor_pos)
+/// Loads the config file and returns the settings of the given profile
+fn load_settings(path: &Path, profile: Option<&str>) -> Result<Settings> {
+    Config::load(path)
+        .and_then(|config| config.settings(profile))
+        .wrap_err(Failure::Config)
 }
 
-fn raise_and_focus_window(hwnd: isize) -> Result<()> {
-    let event = [INPUT {
-        r#type: INPUT_MOUSE,
-        ..Default::default()
-    }];
+fn validate_config(path: &Path) -> Result<()> {
+    if !path.is_file() {
+        return Err(eyre!("there is no config file at {}", path.display()))
+            .wrap_err(Failure::Config);
+    }
 
-    unsafe {
-        // Send an input event to our own process first so that we pass the
-        // foreground lock check
-        SendInput(&event, size_of::<INPUT>() as i32);
-        // Error ignored, as the operation is not always necessary.
+    let errors = Config::load(path).wrap_err(Failure::Config)?.validate();
+    if !errors.is_empty() {
+        for error in &errors {
+            eprintln!("{error}");
+        }
 
-        SetForegroundWindow(HWND(as_ptr!(hwnd)))
+        return Err(eyre!(
+            "found {} error(s) in {}",
+            errors.len(),
+            path.display()
+        ))
+        .wrap_err(Failure::Config);
     }
-    .ok()
-    .process()
-}
-
-fn real_window_class_w(hwnd: isize) -> Result<String> {
-    const BUF_SIZE: usize = 512;
-    let mut class: [u16; BUF_SIZE] = [0; BUF_SIZE];
 
-    let len = Result::from(WindowsResult::from(unsafe {
-        RealGetWindowClassW(HWND(as_ptr!(hwnd)), &mut class)
-    }))?;
+    println!("{} is valid", path.display());
 
-    Ok(String::from_utf16(&class[0..len as usize])?)
+    Ok(())
 }
@@ -0,0 +1,43 @@
+//! A file holding the process id of the running instance, so that supervision
+//! scripts and other tools can find and manage it
+
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use masir_core::portable;
+use std::path::PathBuf;
+
+/// Removes the PID file when dropped at the end of a clean exit
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Returns the default location of the PID file
+    pub fn default_path() -> PathBuf {
+        portable::data_dir().join("masir.pid")
+    }
+
+    /// Writes the process id of this instance to the given path, replacing any
+    /// file left behind by an instance which didn't exit cleanly
+    pub fn write(path: PathBuf) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(&path, std::process::id().to_string())
+            .wrap_err_with(|| format!("could not write the PID file {}", path.display()))?;
+
+        Ok(Self { path })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(error) = std::fs::remove_file(&self.path) {
+            tracing::warn!(
+                "could not remove the PID file {}: {error}",
+                self.path.display()
+            );
+        }
+    }
+}
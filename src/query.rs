@@ -0,0 +1,64 @@
+use clap::ValueEnum;
+use color_eyre::Result;
+use masir_core::config::Settings;
+use masir_core::decision::DecisionReason;
+use masir_core::engine::Engine;
+use masir_core::hwnd::Hwnd;
+use masir_core::window_system::Win32;
+use masir_core::window_system::WindowSystem;
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Target {
+    /// The window under the cursor
+    Cursor,
+    /// The current foreground window
+    Foreground,
+}
+
+/// What masir sees when evaluating a window, where the properties are those of
+/// the top-level window which would actually be raised
+#[derive(Serialize)]
+struct Report {
+    hwnd: Hwnd,
+    root: Hwnd,
+    class: Option<String>,
+    exe: Option<String>,
+    title: Option<String>,
+    ex_style: String,
+    /// Whether the engine would raise the window, and why
+    verdict: DecisionReason,
+    eligible: bool,
+}
+
+/// Prints a JSON description of the target window and whether it is eligible
+/// to be raised with the given settings
+pub fn run(target: Target, settings: Settings, hwnds: Vec<PathBuf>) -> Result<()> {
+    let hwnd = match target {
+        Target::Cursor => Win32.window_at_cursor_pos(settings.hit_test)?,
+        Target::Foreground => Win32.foreground_window()?,
+    };
+
+    let root = Win32.root_window(hwnd)?;
+    let class = Win32.class(root).ok();
+
+    // the engine itself decides, so that the verdict never contradicts what
+    // a running instance would do with the window
+    let verdict = Engine::new(Win32, hwnds, settings).evaluate_window(root);
+
+    let report = Report {
+        hwnd,
+        root,
+        exe: Win32.exe(root).ok(),
+        title: Win32.title(root).ok(),
+        ex_style: format!("{:#010x}", root.ex_style().0),
+        class,
+        eligible: verdict.is_raise(),
+        verdict,
+    };
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    Ok(())
+}
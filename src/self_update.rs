@@ -0,0 +1,186 @@
+//! Updating masir in place to the binary published with the latest GitHub
+//! release, verified against the release's checksums
+
+use color_eyre::eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use semver::Version;
+use serde::Deserialize;
+use sha2::Digest;
+use sha2::Sha256;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use ureq::Agent;
+
+const REPOSITORY: &str = "LGUG2Z/masir";
+const CHECKSUMS: &str = "checksums.txt";
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    fn asset(&self, name: &str) -> Result<&Asset> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| eyre!("release {} has no {name} asset", self.tag_name))
+    }
+}
+
+/// Replaces the running executable with the binary from the latest GitHub
+/// release if it is newer, after verifying its checksum
+///
+/// A running executable can't be overwritten on Windows, but it can be renamed,
+/// so the current binary is moved aside and the new one takes effect the next
+/// time masir starts.
+pub fn run() -> Result<()> {
+    // only the default build is published, and replacing another flavor with
+    // it would silently drop the features it was built with
+    if cfg!(feature = "windowless") || cfg!(feature = "http") {
+        return Err(eyre!(
+            "self-update only supports the default build of masir, update this build from source instead"
+        ));
+    }
+
+    let agent = agent()?;
+
+    let release: Release = agent
+        .get(&format!(
+            "https://api.github.com/repos/{REPOSITORY}/releases/latest"
+        ))
+        .call()
+        .wrap_err("could not fetch the latest release")?
+        .into_json()?;
+
+    let current = Version::parse(env!("CARGO_PKG_VERSION"))?;
+    let latest = Version::parse(release.tag_name.trim_start_matches('v'))
+        .wrap_err_with(|| format!("could not parse release tag {}", release.tag_name))?;
+
+    if latest <= current {
+        println!("masir {current} is up to date");
+        return Ok(());
+    }
+
+    let name = asset_name();
+    let binary = download(&agent, release.asset(&name)?)?;
+    let checksums = String::from_utf8(download(&agent, release.asset(CHECKSUMS)?)?)?;
+
+    let expected = expected_checksum(&checksums, &name)
+        .ok_or_else(|| eyre!("{CHECKSUMS} has no checksum for {name}"))?;
+    let actual: String = Sha256::digest(&binary)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(eyre!(
+            "the checksum of {name} is {actual}, but {CHECKSUMS} lists {expected}"
+        ));
+    }
+
+    replace_current_exe(&binary)?;
+
+    println!("updated masir from {current} to {latest}, restart masir to use the new version");
+
+    Ok(())
+}
+
+/// Removes the binary left behind by a previous update, which can only be
+/// deleted once the process that was running it has exited
+pub fn remove_previous() {
+    if let Ok(exe) = std::env::current_exe() {
+        let _ = std::fs::remove_file(previous_path(exe));
+    }
+}
+
+fn agent() -> Result<Agent> {
+    Ok(ureq::AgentBuilder::new()
+        .tls_connector(Arc::new(native_tls::TlsConnector::new()?))
+        .user_agent(&format!("masir/{}", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(60))
+        .build())
+}
+
+fn asset_name() -> String {
+    format!("masir-{}-pc-windows-msvc.exe", std::env::consts::ARCH)
+}
+
+fn download(agent: &Agent, asset: &Asset) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    agent
+        .get(&asset.browser_download_url)
+        .call()
+        .wrap_err_with(|| format!("could not download {}", asset.name))?
+        .into_reader()
+        .read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// Finds the checksum of the named file in the output of sha256sum
+fn expected_checksum<'a>(checksums: &'a str, name: &str) -> Option<&'a str> {
+    checksums.lines().find_map(|line| {
+        let (checksum, file) = line.split_once(char::is_whitespace)?;
+        (file.trim_start().trim_start_matches('*') == name).then_some(checksum)
+    })
+}
+
+fn previous_path(exe: PathBuf) -> PathBuf {
+    exe.with_extension("exe.old")
+}
+
+fn replace_current_exe(binary: &[u8]) -> Result<()> {
+    let exe = std::env::current_exe()?;
+    let update = exe.with_extension("exe.update");
+    let previous = previous_path(exe.clone());
+
+    std::fs::write(&update, binary)
+        .wrap_err_with(|| format!("could not write {}", update.display()))?;
+
+    // an earlier update may have left this behind if masir was still running
+    let _ = std::fs::remove_file(&previous);
+    std::fs::rename(&exe, &previous)
+        .wrap_err_with(|| format!("could not move {} aside", exe.display()))?;
+
+    if let Err(error) = std::fs::rename(&update, &exe) {
+        let _ = std::fs::rename(&previous, &exe);
+        return Err(error).wrap_err_with(|| format!("could not replace {}", exe.display()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_checksums_in_text_and_binary_mode() {
+        let checksums = "\
+0123abcd  masir-x86_64-pc-windows-msvc.exe
+4567ef01 *masir-aarch64-pc-windows-msvc.exe
+89abcdef  masir-0.1.1-x86_64-pc-windows-msvc.zip
+";
+
+        assert_eq!(
+            expected_checksum(checksums, "masir-x86_64-pc-windows-msvc.exe"),
+            Some("0123abcd")
+        );
+        assert_eq!(
+            expected_checksum(checksums, "masir-aarch64-pc-windows-msvc.exe"),
+            Some("4567ef01")
+        );
+        assert_eq!(expected_checksum(checksums, "masir.exe"), None);
+    }
+}
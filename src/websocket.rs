@@ -0,0 +1,142 @@
+//! A localhost WebSocket streaming the same events as `masir subscribe`, for
+//! web-based status bars and dashboards such as yasb and zebar
+
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use masir_core::events;
+use std::io::ErrorKind;
+use std::net::Ipv4Addr;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+use tungstenite::handshake::server::ErrorResponse;
+use tungstenite::handshake::server::Request;
+use tungstenite::handshake::server::Response;
+use tungstenite::http::header::HeaderName;
+use tungstenite::http::header::HOST;
+use tungstenite::http::header::ORIGIN;
+use tungstenite::http::StatusCode;
+use tungstenite::Message;
+
+/// How long to wait for an event before checking whether the client has
+/// closed the connection
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Streams events to WebSocket clients on the given port of the loopback
+/// interface
+pub fn listen(port: u16) -> Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .wrap_err_with(|| format!("could not listen on 127.0.0.1:{port}"))?;
+
+    tracing::info!("streaming events on ws://127.0.0.1:{port}");
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    // each client waits for events on its own thread
+                    std::thread::spawn(move || {
+                        if let Err(error) = handle_stream(stream) {
+                            tracing::debug!("websocket client disconnected: {error}");
+                        }
+                    });
+                }
+                Err(error) => {
+                    tracing::error!("failed to accept websocket connection: {error}");
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn handle_stream(stream: TcpStream) -> Result<()> {
+    // subscribe before the handshake so that no event is missed in between
+    let receiver = events::subscribe();
+
+    let mut socket = tungstenite::accept_hdr(stream.try_clone()?, reject_web_pages)?;
+    tracing::debug!("websocket client connected");
+
+    // reads only wait briefly, so that pings and close frames from the client
+    // are answered whenever no events are coming in
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+
+    loop {
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(event) => {
+                socket.send(Message::Text(serde_json::to_string(&event)?))?;
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        match socket.read() {
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(error))
+                if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+            {
+                // replies to pings are queued by reads and sent on flush
+                socket.flush()?;
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+}
+
+/// Rejects upgrades from web pages which aren't served from localhost, such as
+/// a local status bar's, since any page open in a browser can connect to
+/// localhost
+fn reject_web_pages(
+    request: &Request,
+    response: Response,
+) -> std::result::Result<Response, ErrorResponse> {
+    let header = |name: HeaderName| {
+        request
+            .headers()
+            .get(name)
+            .map(|value| value.to_str().unwrap_or_default())
+    };
+
+    // a page can reach localhost through a domain which resolves to it
+    let host_is_local = header(HOST).is_none_or(|host| is_local(hostname(host)));
+    let origin_is_local = header(ORIGIN).is_none_or(|origin| {
+        origin
+            .split_once("://")
+            .is_some_and(|(_, authority)| is_local(hostname(authority)))
+    });
+
+    if host_is_local && origin_is_local {
+        return Ok(response);
+    }
+
+    tracing::debug!("rejected a websocket connection from a web page");
+
+    let mut rejection = ErrorResponse::new(Some(
+        "connections from web pages are not accepted".to_string(),
+    ));
+    *rejection.status_mut() = StatusCode::FORBIDDEN;
+
+    Err(rejection)
+}
+
+/// Returns the host of an authority such as `localhost:7475`, leaving out the
+/// port
+fn hostname(authority: &str) -> &str {
+    if authority.ends_with(']') {
+        return authority;
+    }
+
+    authority
+        .rsplit_once(':')
+        .map_or(authority, |(host, _)| host)
+}
+
+/// Returns true if the host can only ever be this machine, where subdomains of
+/// localhost are used by webview-based bars
+fn is_local(host: &str) -> bool {
+    matches!(host, "127.0.0.1" | "localhost" | "[::1]") || host.ends_with(".localhost")
+}